@@ -40,6 +40,12 @@ pub(crate) enum Error {
     Unspecified(#[from] anyhow::Error),
     #[error("Poisoned lock")]
     Poisoned,
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("Too many concurrent proofs, limit: {0}")]
+    ConcurrencyLimit(u64),
+    #[error("Injected fault")]
+    Injected(StatusCode),
 }
 
 impl<T> From<PoisonError<T>> for Error {
@@ -52,6 +58,9 @@ impl Error {
     pub fn status_code(&self) -> StatusCode {
         match self {
             Error::ImageIdExists { .. } => StatusCode::NO_CONTENT,
+            Error::QuotaExceeded { .. } => StatusCode::FORBIDDEN,
+            Error::ConcurrencyLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::Injected(status) => *status,
             Error::Poisoned
             | Error::Bincode { .. }
             | Error::Unspecified { .. }
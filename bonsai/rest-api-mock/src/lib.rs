@@ -17,29 +17,218 @@ mod prover;
 mod routes;
 mod state;
 
-use std::sync::{Arc, RwLock};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use anyhow::Context;
 use axum::{
-    extract::DefaultBodyLimit,
+    extract::{DefaultBodyLimit, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::Response,
     routing::{get, post, put},
     Extension, Router,
 };
+use bonsai_sdk::alpha::responses::Quotas;
+use serde::{Deserialize, Serialize};
 use tokio::{net::TcpListener, sync::mpsc};
 use tower_http::trace::{DefaultOnRequest, TraceLayer};
 use tracing::{info, Level};
 
 use crate::{
+    error::Error,
     prover::{Prover, ProverHandle},
     routes::{
-        create_session, create_snark, get_image_upload, get_input_upload, get_receipt,
-        get_receipt_upload, put_image_upload, put_input_upload, put_receipt, session_status,
-        snark_status,
+        create_session, create_snark, get_faults, get_image_upload, get_input_upload, get_receipt,
+        get_receipt_upload, put_faults, put_image_upload, put_input_upload, put_receipt, quotas,
+        session_status, snark_status, stop_session, version,
     },
-    state::BonsaiState,
+    state::{AppState, BonsaiState},
 };
 
-fn app(state: Arc<RwLock<BonsaiState>>, prover_handle: ProverHandle) -> Router {
+/// Configuration of the Bonsai mock.
+#[derive(Clone, Debug)]
+pub struct MockConfig {
+    /// Directory to persist images, inputs, sessions and receipts to.
+    ///
+    /// If set, the state is loaded from this directory on startup, and
+    /// sessions that were still running when the mock stopped are restarted.
+    /// Otherwise all state is kept in memory.
+    pub state_dir: Option<PathBuf>,
+
+    /// How long a session stays queued, in the `Setup` state, before it
+    /// starts executing.
+    pub queue_delay: Duration,
+
+    /// How long a session stays in the `Executor` state before the guest is
+    /// executed.
+    pub run_delay: Duration,
+
+    /// The quotas reported on `/user/quotas` and enforced on new sessions.
+    pub quotas: Quotas,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            state_dir: None,
+            queue_delay: Duration::ZERO,
+            run_delay: Duration::ZERO,
+            quotas: Quotas {
+                exec_cycle_limit: 100_000,
+                max_parallelism: 1,
+                concurrent_proofs: 32,
+                cycle_budget: u64::MAX,
+                cycle_usage: 0,
+            },
+        }
+    }
+}
+
+/// Faults to inject into the Bonsai mock.
+///
+/// Each counter is decremented as its fault is injected. Faults can be set
+/// with [BonsaiMock::set_faults], or with a `PUT` of this object as JSON on
+/// `/mock/faults`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Faults {
+    /// Number of upcoming requests to fail with `http_status`.
+    pub http_errors: u32,
+
+    /// The HTTP status code to fail requests with.
+    pub http_status: u16,
+
+    /// Number of upcoming sessions whose guest execution fails.
+    pub failed_sessions: u32,
+
+    /// Number of upcoming sessions that are aborted.
+    pub aborted_sessions: u32,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            http_errors: 0,
+            http_status: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+            failed_sessions: 0,
+            aborted_sessions: 0,
+        }
+    }
+}
+
+/// A mock of Bonsai that exposes the same REST API as Bonsai alpha.
+///
+/// Note that this mock only performs execution, no proving.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use bonsai_rest_api_mock::{BonsaiMock, Faults, MockConfig};
+/// use tokio::net::TcpListener;
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let mock = BonsaiMock::new(MockConfig {
+///     queue_delay: Duration::from_secs(1),
+///     ..Default::default()
+/// })?;
+/// mock.set_faults(Faults {
+///     http_errors: 2,
+///     ..Default::default()
+/// })?;
+/// mock.serve(TcpListener::bind("127.0.0.1:8081").await?).await
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BonsaiMock {
+    state: AppState,
+}
+
+impl BonsaiMock {
+    /// Construct a [BonsaiMock], loading any state persisted to
+    /// [MockConfig::state_dir].
+    pub fn new(config: MockConfig) -> anyhow::Result<Self> {
+        let state = BonsaiState::new(config).context("failed to load Bonsai mock state")?;
+        Ok(Self {
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// Replace the faults to inject.
+    pub fn set_faults(&self, faults: Faults) -> anyhow::Result<()> {
+        self.state.write().map_err(|_| Error::Poisoned)?.faults = faults;
+        Ok(())
+    }
+
+    /// Return the faults that have not been injected yet.
+    pub fn faults(&self) -> anyhow::Result<Faults> {
+        Ok(self
+            .state
+            .read()
+            .map_err(|_| Error::Poisoned)?
+            .faults
+            .clone())
+    }
+
+    /// Serve the Bonsai API on the given listener.
+    pub async fn serve(self, listener: TcpListener) -> anyhow::Result<()> {
+        let local_addr = listener.local_addr().unwrap();
+        let port = local_addr.port();
+        let pending = {
+            let mut state = self.state.write().map_err(|_| Error::Poisoned)?;
+            state.local_url = format!("http://127.0.0.1:{port}");
+            state
+                .running_sessions()
+                .map(|session| session.task.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let (sender, receiver) = mpsc::channel(8);
+        let mut prover = Prover::new(receiver, Arc::clone(&self.state));
+
+        let prover_handle = ProverHandle { sender };
+
+        tokio::spawn(async move { prover.run().await });
+
+        // Restart the sessions that were running when the state was persisted.
+        let handle = prover_handle.clone();
+        tokio::spawn(async move {
+            for task in pending {
+                info!("Restarting session {}", task.session_id);
+                handle.execute(task).await;
+            }
+        });
+
+        info!("Local Bonsai started on {local_addr}");
+
+        axum::serve(listener, app(self.state, prover_handle))
+            .await
+            .context(format!("failed to serve Local Bonsai API on {local_addr}"))
+    }
+}
+
+async fn inject_http_errors(
+    State(s): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    if !request.uri().path().starts_with("/mock/") {
+        let fault = s.write()?.take_http_fault();
+        if let Some(status) = fault {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(Error::Injected(status));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+fn app(state: AppState, prover_handle: ProverHandle) -> Router {
     Router::new()
         .route("/images/upload/:image_id", get(get_image_upload))
         .route("/images/:image_id", put(put_image_upload))
@@ -47,11 +236,19 @@ fn app(state: Arc<RwLock<BonsaiState>>, prover_handle: ProverHandle) -> Router {
         .route("/inputs/:input_id", put(put_input_upload))
         .route("/sessions/create", post(create_session))
         .route("/sessions/status/:session_id", get(session_status))
+        .route("/sessions/stop/:session_id", get(stop_session))
         .route("/snark/create", post(create_snark))
         .route("/snark/status/:snark_id", get(snark_status))
         .route("/receipts/:session_id", get(get_receipt))
         .route("/receipts/:session_id", put(put_receipt))
         .route("/receipts/upload", get(get_receipt_upload))
+        .route("/user/quotas", get(quotas))
+        .route("/version", get(version))
+        .route("/mock/faults", get(get_faults).put(put_faults))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            inject_http_errors,
+        ))
         .layer(Extension(prover_handle))
         .with_state(state)
         .layer(DefaultBodyLimit::max(256 * 1024 * 1024))
@@ -63,79 +260,92 @@ fn app(state: Arc<RwLock<BonsaiState>>, prover_handle: ProverHandle) -> Router {
 /// Starts a mock of Bonsai on localhost at the given port. It exposes the same
 /// REST API of Bonsai alpha.
 ///
-/// Note that this mock only performs execution, no proving.
+/// Note that this mock only performs execution, no proving. See [BonsaiMock]
+/// to persist state, simulate delays or inject faults.
 pub async fn serve(listener: TcpListener) -> anyhow::Result<()> {
-    let local_addr = listener.local_addr().unwrap();
-    let port = local_addr.port();
-    let local_url = format!("http://127.0.0.1:{port}");
-    let state = Arc::new(RwLock::new(BonsaiState::new(local_url)));
-
-    let (sender, receiver) = mpsc::channel(8);
-    let mut prover = Prover::new(receiver, Arc::clone(&state));
-
-    let prover_handle = ProverHandle { sender };
-
-    tokio::spawn(async move { prover.run().await });
-
-    info!("Local Bonsai started on {local_addr}");
-
-    axum::serve(listener, app(state, prover_handle))
+    BonsaiMock::new(MockConfig::default())?
+        .serve(listener)
         .await
-        .context(format!("failed to serve Local Bonsai API on {local_addr}"))
 }
 
 #[cfg(test)]
 mod test {
     use std::time::Duration;
 
-    use anyhow::{bail, Result};
-    use bonsai_sdk::alpha_async as bonsai_sdk;
+    use anyhow::Result;
+    use bonsai_sdk::{
+        alpha::{RetryPolicy, SdkErr, SessionId, WaitOptions},
+        alpha_async::AsyncClient,
+    };
     use risc0_zkvm::compute_image_id;
     use risc0_zkvm_methods::HELLO_COMMIT_ELF;
-    use tokio::net::TcpListener;
+    use tokio::{net::TcpListener, task::JoinHandle};
+
+    use crate::{serve, BonsaiMock, Faults, MockConfig};
 
-    use crate::serve;
+    const WAIT_OPTS: WaitOptions = WaitOptions {
+        poll_interval: Duration::from_millis(100),
+        timeout: Some(Duration::from_secs(60)),
+    };
+
+    async fn start(mock: BonsaiMock) -> (AsyncClient, JoinHandle<Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(mock.serve(listener));
+        let client = AsyncClient::from_parts(
+            format!("http://{local_addr}"),
+            "test_key".to_string(),
+            risc0_zkvm::VERSION,
+        )
+        .await
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+            multiplier: 1.0,
+        });
+        (client, handle)
+    }
+
+    async fn start_session(client: &AsyncClient, elf: &[u8]) -> Result<SessionId> {
+        let image_id = hex::encode(compute_image_id(elf)?);
+        client.upload_img(image_id.clone(), elf.to_vec()).await?;
+        let input_id = client.upload_input(vec![]).await?;
+        Ok(client.create_session(image_id, input_id, vec![]).await?)
+    }
 
     async fn run_bonsai(bonsai_api_url: String, bonsai_api_key: String, elf: &[u8]) -> Result<()> {
         let client =
-            bonsai_sdk::get_client_from_parts(bonsai_api_url, bonsai_api_key, risc0_zkvm::VERSION)
-                .await?;
+            AsyncClient::from_parts(bonsai_api_url, bonsai_api_key, risc0_zkvm::VERSION).await?;
 
         // Compute the image_id, then upload the ELF with the image_id as its key.
         // TODO: it would be nice if `bonsai_sdk::upload_img` only took the ELF
         // so that the image_id can be computed server-side.
         let image_id = hex::encode(compute_image_id(elf)?);
-        bonsai_sdk::upload_img(client.clone(), image_id.clone(), elf.to_vec()).await?;
+        client.upload_img(image_id.clone(), elf.to_vec()).await?;
 
         // Prepare input data and upload it.
-        let input_id = bonsai_sdk::upload_input(client.clone(), vec![]).await?;
+        let input_id = client.upload_input(vec![]).await?;
 
         // Prepare symbolic list of receipt data and upload it.
-        let receipts_ids = vec![bonsai_sdk::upload_receipt(client.clone(), vec![]).await?];
+        let receipts_ids = vec![client.upload_receipt(vec![]).await?];
 
         // Start a session running the prover
-        let session =
-            bonsai_sdk::create_session(client.clone(), image_id, input_id, receipts_ids).await?;
-        loop {
-            let res = bonsai_sdk::session_status(client.clone(), session.clone()).await?;
-            if res.status == "RUNNING" {
-                std::thread::sleep(Duration::from_secs(15));
-                continue;
-            }
-            if res.status == "SUCCEEDED" {
-                // Download the receipt, containing the output
-                let receipt_url = res
-                    .receipt_url
-                    .expect("API error, missing receipt on completed session");
-                bonsai_sdk::download(client.clone(), receipt_url)
-                    .await
-                    .unwrap();
-            } else {
-                bail!("Error");
-            }
+        let session = client
+            .create_session(image_id, input_id, receipts_ids)
+            .await?;
+        let opts = WaitOptions {
+            poll_interval: Duration::from_millis(100),
+            timeout: Some(Duration::from_secs(60)),
+        };
+        let res = client.wait_for_session(session, opts, |_| ()).await?;
 
-            break;
-        }
+        // Download the receipt, containing the output
+        let receipt_url = res
+            .receipt_url
+            .expect("API error, missing receipt on completed session");
+        client.download(receipt_url).await?;
 
         Ok(())
     }
@@ -183,4 +393,168 @@ mod test {
 
         local_bonsai_handle.abort();
     }
+
+    #[tokio::test]
+    async fn local_bonsai_unknown_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let local_bonsai_handle = tokio::spawn(async move { serve(listener).await });
+
+        let client = AsyncClient::from_parts(
+            format!("http://{local_addr}"),
+            "test_key".to_string(),
+            risc0_zkvm::VERSION,
+        )
+        .await
+        .unwrap();
+        let session = SessionId::new("unknown".to_string());
+        let err = client.session_status(session).await.err().unwrap();
+        assert!(matches!(err, SdkErr::HttpStatus { .. }), "{err}");
+        assert!(!err.is_transient());
+
+        local_bonsai_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn local_bonsai_lifecycle() {
+        let mock = BonsaiMock::new(MockConfig {
+            queue_delay: Duration::from_secs(1),
+            ..Default::default()
+        })
+        .unwrap();
+        let (client, handle) = start(mock).await;
+
+        let session = start_session(&client, HELLO_COMMIT_ELF).await.unwrap();
+        let res = client.session_status(session.clone()).await.unwrap();
+        assert_eq!(res.status, "RUNNING");
+        assert_eq!(res.state.as_deref(), Some("Setup"));
+
+        let opts = WaitOptions {
+            poll_interval: Duration::from_millis(10),
+            timeout: Some(Duration::from_millis(100)),
+        };
+        let err = client
+            .wait_for_session(session.clone(), opts, |_| ())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, SdkErr::Timeout(_)), "{err}");
+
+        let res = client
+            .wait_for_session(session, WAIT_OPTS, |_| ())
+            .await
+            .unwrap();
+        assert!(res.receipt_url.is_some());
+        assert_eq!(res.stats.unwrap().segments, 1);
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn local_bonsai_faults() {
+        let mock = BonsaiMock::new(MockConfig::default()).unwrap();
+        let (client, handle) = start(mock.clone()).await;
+
+        // Transient errors are retried by the client.
+        mock.set_faults(Faults {
+            http_errors: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        client.upload_input(vec![]).await.unwrap();
+        assert_eq!(mock.faults().unwrap(), Faults::default());
+
+        mock.set_faults(Faults {
+            http_errors: 10,
+            http_status: 502,
+            ..Default::default()
+        })
+        .unwrap();
+        let err = client.upload_input(vec![]).await.err().unwrap();
+        assert_eq!(err.status().map(|status| status.as_u16()), Some(502));
+        assert_eq!(mock.faults().unwrap().http_errors, 6);
+
+        mock.set_faults(Faults {
+            failed_sessions: 1,
+            aborted_sessions: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        for expected in ["FAILED", "ABORTED"] {
+            let session = start_session(&client, HELLO_COMMIT_ELF).await.unwrap();
+            let err = client
+                .wait_for_session(session, WAIT_OPTS, |_| ())
+                .await
+                .err()
+                .unwrap();
+            let SdkErr::WorkflowFailed {
+                status, error_msg, ..
+            } = err
+            else {
+                panic!("unexpected error: {err}");
+            };
+            assert_eq!(status, expected);
+            assert!(error_msg.is_some());
+        }
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn local_bonsai_quotas() {
+        let config = MockConfig::default();
+        let mock = BonsaiMock::new(config.clone()).unwrap();
+        let (client, handle) = start(mock).await;
+
+        assert_eq!(client.quotas().await.unwrap(), config.quotas);
+        let session = start_session(&client, HELLO_COMMIT_ELF).await.unwrap();
+        let res = client
+            .wait_for_session(session, WAIT_OPTS, |_| ())
+            .await
+            .unwrap();
+        let quotas = client.quotas().await.unwrap();
+        assert_eq!(quotas.cycle_usage, res.stats.unwrap().total_cycles);
+        handle.abort();
+
+        let mut config = MockConfig::default();
+        config.quotas.cycle_budget = 0;
+        let (client, handle) = start(BonsaiMock::new(config).unwrap()).await;
+        let err = start_session(&client, HELLO_COMMIT_ELF)
+            .await
+            .err()
+            .unwrap();
+        let err = err.downcast::<SdkErr>().unwrap();
+        assert_eq!(err.status().map(|status| status.as_u16()), Some(403));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn local_bonsai_persistence() {
+        let state_dir = std::env::temp_dir().join(format!("bonsai-mock-{}", uuid::Uuid::new_v4()));
+        let config = MockConfig {
+            state_dir: Some(state_dir.clone()),
+            ..Default::default()
+        };
+
+        let (client, handle) = start(BonsaiMock::new(config.clone()).unwrap()).await;
+        let session = start_session(&client, HELLO_COMMIT_ELF).await.unwrap();
+        let res = client
+            .wait_for_session(session.clone(), WAIT_OPTS, |_| ())
+            .await
+            .unwrap();
+        let receipt = client.download(res.receipt_url.unwrap()).await.unwrap();
+        handle.abort();
+
+        // A new mock on a different port serves the persisted session.
+        let (client, handle) = start(BonsaiMock::new(config).unwrap()).await;
+        let res = client.session_status(session).await.unwrap();
+        assert_eq!(res.status, "SUCCEEDED");
+        assert_eq!(
+            client.download(res.receipt_url.unwrap()).await.unwrap(),
+            receipt
+        );
+        handle.abort();
+
+        std::fs::remove_dir_all(state_dir).unwrap();
+    }
 }
@@ -12,10 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use bonsai_rest_api_mock::{BonsaiMock, MockConfig};
 use tokio::net::TcpListener;
 
 #[tokio::main]
 async fn main() {
+    // An optional directory to persist the state of the mock to.
+    let config = MockConfig {
+        state_dir: std::env::args().nth(1).map(Into::into),
+        ..Default::default()
+    };
     let listener = TcpListener::bind("0.0.0.0:8081").await.unwrap();
-    let _ = bonsai_rest_api_mock::serve(listener).await;
+    let _ = BonsaiMock::new(config).unwrap().serve(listener).await;
 }
@@ -21,11 +21,15 @@ use anyhow::Context;
 use risc0_zkvm::{
     default_executor, sha::Digest, ExecutorEnv, InnerReceipt, MaybePruned, Receipt, ReceiptClaim,
 };
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use crate::{error::Error, state::BonsaiState};
+use crate::{
+    error::{DisplayErrorCauses, Error},
+    state::{BonsaiState, FAILED, SUCCEEDED},
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Task {
     pub session_id: String,
     pub image_id: String,
@@ -80,15 +84,42 @@ impl Prover {
     pub async fn handle_message(&mut self, msg: &ProverMessage) -> Result<(), Error> {
         match msg {
             ProverMessage::RunSession(task) => {
+                let config = self.storage.read()?.config.clone();
+
+                // The session stays queued in the `Setup` state for `queue_delay`.
+                tokio::time::sleep(config.queue_delay).await;
+                let running = self
+                    .storage
+                    .write()?
+                    .update_session(&task.session_id, |session| {
+                        session.state = Some("Executor".to_string())
+                    })?;
+                if !running {
+                    tracing::info!("Session {} is no longer running", task.session_id);
+                    return Ok(());
+                }
+                tokio::time::sleep(config.run_delay).await;
+
+                let fault = self.storage.write()?.take_session_fault();
+                if let Some(status) = fault {
+                    tracing::info!("Injecting {status} into session {}", task.session_id);
+                    self.storage
+                        .write()?
+                        .update_session(&task.session_id, |session| {
+                            session.status = status.to_string();
+                            session.error_msg = Some(format!("injected {status} session"));
+                        })?;
+                    return Ok(());
+                }
+
                 tracing::info!("Running task...");
                 let image = self.get_image(task).await?;
                 let input = self.get_input(task).await?;
                 let receipts = self.get_receipts(task).await?;
-                let elf = image.as_slice();
 
                 let mut env = ExecutorEnv::builder();
                 for receipt in receipts {
-                    if receipt.len() < 1 {
+                    if receipt.is_empty() {
                         continue;
                     }
                     let deserialized_receipt: Receipt = bincode::deserialize(&receipt)?;
@@ -97,16 +128,26 @@ impl Prover {
 
                 let env = env
                     .write_slice(&input)
-                    .session_limit(None)
+                    .session_limit(Some(
+                        self.storage
+                            .read()?
+                            .quotas
+                            .exec_cycle_limit
+                            .saturating_mul(1_000_000),
+                    ))
                     .segment_limit_po2(20)
                     .build()
                     .map_err(|e| {
                         anyhow::anyhow!("failed to build executor environment: {:?}", e)
                     })?;
-                let exec = default_executor();
-                let session = exec
-                    .execute(env, elf)
-                    .context("Executor failed to generate a successful session")?;
+                // Execute on a blocking thread so that status requests are
+                // still served while the guest runs.
+                let session = tokio::task::spawn_blocking(move || {
+                    default_executor()
+                        .execute(env, &image)
+                        .context("Executor failed to generate a successful session")
+                })
+                .await??;
 
                 let receipt = Receipt {
                     inner: InnerReceipt::Fake {
@@ -121,12 +162,30 @@ impl Prover {
                     journal: session.journal,
                 };
                 let receipt_bytes = bincode::serialize(&receipt)?;
-                self.storage
-                    .write()?
-                    .put_receipt(task.session_id.clone(), receipt_bytes);
-                self.storage
-                    .write()?
-                    .put_session(task.session_id.clone(), "SUCCEEDED".to_string());
+                let total_cycles = session
+                    .segments
+                    .iter()
+                    .map(|segment| 1u64 << segment.po2)
+                    .sum();
+                let cycles = session
+                    .segments
+                    .iter()
+                    .map(|segment| segment.cycles as u64)
+                    .sum();
+
+                // Sessions aborted while executing do not produce a receipt.
+                let mut storage = self.storage.write()?;
+                if storage
+                    .get_session(&task.session_id)
+                    .is_some_and(|session| session.is_running())
+                {
+                    storage.put_receipt(task.session_id.clone(), receipt_bytes)?;
+                    storage.update_session(&task.session_id, |s| {
+                        s.status = SUCCEEDED.to_string();
+                        s.stats = Some((session.segments.len(), total_cycles, cycles));
+                    })?;
+                    storage.charge_cycles(total_cycles)?;
+                }
             }
         }
 
@@ -139,11 +198,16 @@ impl Prover {
             match self.handle_message(&msg).await {
                 Ok(_) => tracing::info!("Task done!"),
                 Err(err) => {
+                    let error_msg = DisplayErrorCauses(&err).to_string();
                     match &msg {
-                        ProverMessage::RunSession(task) => self
-                            .storage
-                            .write()?
-                            .put_session(task.session_id.clone(), "FAILED".to_string()),
+                        ProverMessage::RunSession(task) => {
+                            self.storage
+                                .write()?
+                                .update_session(&task.session_id, |session| {
+                                    session.status = FAILED.to_string();
+                                    session.error_msg = Some(error_msg.clone());
+                                })?
+                        }
                     };
                     tracing::error!("Task {} failed! - {:?}", msg, err)
                 }
@@ -20,8 +20,8 @@ use axum::{
     Extension, Json,
 };
 use bonsai_sdk::alpha::responses::{
-    CreateSessRes, ImgUploadRes, ProofReq, SessionStatusRes, SnarkReceipt, SnarkReq,
-    SnarkStatusRes, UploadRes,
+    CreateSessRes, ImgUploadRes, ProofReq, Quotas, SessionStatusRes, SnarkReceipt, SnarkReq,
+    SnarkStatusRes, UploadRes, VersionInfo,
 };
 use risc0_zkvm::{Groth16Seal, Receipt};
 use tracing::info;
@@ -29,7 +29,8 @@ use tracing::info;
 use crate::{
     error::Error,
     prover::{ProverHandle, Task},
-    state::AppState,
+    state::{AppState, Session, ABORTED, SUCCEEDED},
+    Faults,
 };
 
 pub(crate) async fn get_image_upload(
@@ -50,7 +51,7 @@ pub(crate) async fn put_image_upload(
     Path(image_id): Path<String>,
     body: Bytes,
 ) -> Result<(), Error> {
-    s.write()?.put_image(image_id.clone(), body.to_vec());
+    s.write()?.put_image(image_id.clone(), body.to_vec())?;
     info!("ImageID {image_id} uploaded");
     Ok(())
 }
@@ -69,7 +70,7 @@ pub(crate) async fn put_input_upload(
     Path(input_id): Path<String>,
     body: Bytes,
 ) -> Result<(), Error> {
-    s.write()?.put_input(input_id, body.to_vec());
+    s.write()?.put_input(input_id, body.to_vec())?;
     Ok(())
 }
 
@@ -79,14 +80,23 @@ pub(crate) async fn create_session(
     Json(request): Json<ProofReq>,
 ) -> Result<Json<CreateSessRes>, Error> {
     let session_id = uuid::Uuid::new_v4();
-    s.write()?
-        .put_session(session_id.to_string(), "RUNNING".to_string());
     let task = Task {
         image_id: request.img,
         input_id: request.input,
         session_id: session_id.to_string(),
         assumptions: request.assumptions,
     };
+    {
+        let mut state = s.write()?;
+        if state.quotas.cycle_budget == 0 {
+            return Err(Error::QuotaExceeded("cycle budget exhausted".to_string()));
+        }
+        let limit = state.quotas.concurrent_proofs;
+        if state.running_sessions().count() as u64 >= limit {
+            return Err(Error::ConcurrencyLimit(limit));
+        }
+        state.put_session(session_id.to_string(), Session::new(task.clone()))?;
+    }
     prover_handle.execute(task).await;
 
     Ok(Json(CreateSessRes {
@@ -99,28 +109,35 @@ pub(crate) async fn session_status(
     Path(session_id): Path<String>,
 ) -> Result<Json<SessionStatusRes>, Error> {
     let storage = s.read()?;
-    let status = storage
+    let session = storage
         .get_session(&session_id)
         .ok_or_else(|| anyhow::anyhow!("Session not found for session id: {:?}", &session_id))?;
-    let receipt = storage.get_receipt(&session_id);
-    match receipt {
-        Some(_) => Ok(Json(SessionStatusRes {
-            status,
-            receipt_url: Some(format!("{}/receipts/{}", storage.local_url, session_id)),
-            error_msg: None,
-            state: None,
-            elapsed_time: None,
-            stats: None,
-        })),
-        None => Ok(Json(SessionStatusRes {
-            status,
-            receipt_url: None,
-            error_msg: None,
-            state: None,
-            elapsed_time: None,
-            stats: None,
-        })),
+    let receipt_url = match session.status.as_str() {
+        SUCCEEDED => Some(format!("{}/receipts/{}", storage.local_url, session_id)),
+        _ => None,
+    };
+    Ok(Json(SessionStatusRes {
+        receipt_url,
+        elapsed_time: Some(session.elapsed_time()),
+        stats: session.stats(),
+        status: session.status,
+        error_msg: session.error_msg,
+        state: session.state,
+    }))
+}
+
+pub(crate) async fn stop_session(
+    State(s): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<(), Error> {
+    let stopped = s.write()?.update_session(&session_id, |session| {
+        session.status = ABORTED.to_string();
+        session.error_msg = Some("session stopped by user".to_string());
+    })?;
+    if stopped {
+        info!("Session {session_id} aborted");
     }
+    Ok(())
 }
 
 pub(crate) async fn create_snark(
@@ -136,33 +153,33 @@ pub(crate) async fn snark_status(
     Path(snark_id): Path<String>,
 ) -> Result<Json<SnarkStatusRes>, Error> {
     let storage = s.read()?;
-    storage
+    let session = storage
         .get_session(&snark_id)
         .ok_or_else(|| anyhow::anyhow!("Snark status not found for snark id: {:?}", &snark_id))?;
-    let receipt = storage.get_receipt(&snark_id);
-    match receipt {
-        Some(bytes) => {
-            let receipt: Receipt = bincode::deserialize(&bytes)?;
-            Ok(Json(SnarkStatusRes {
-                status: "SUCCEEDED".to_string(),
-                output: Some(SnarkReceipt {
-                    snark: Groth16Seal {
-                        a: vec![],
-                        b: vec![],
-                        c: vec![],
-                    },
-                    post_state_digest: vec![],
-                    journal: receipt.journal.bytes,
-                }),
-                error_msg: None,
-            }))
-        }
-        None => Ok(Json(SnarkStatusRes {
-            status: "RUNNING".to_string(),
+    if session.status != SUCCEEDED {
+        return Ok(Json(SnarkStatusRes {
+            status: session.status,
             output: None,
-            error_msg: None,
-        })),
+            error_msg: session.error_msg,
+        }));
     }
+    let bytes = storage
+        .get_receipt(&snark_id)
+        .ok_or_else(|| anyhow::anyhow!("Receipt not found for snark id: {:?}", &snark_id))?;
+    let receipt: Receipt = bincode::deserialize(&bytes)?;
+    Ok(Json(SnarkStatusRes {
+        status: SUCCEEDED.to_string(),
+        output: Some(SnarkReceipt {
+            snark: Groth16Seal {
+                a: vec![],
+                b: vec![],
+                c: vec![],
+            },
+            post_state_digest: vec![],
+            journal: receipt.journal.bytes,
+        }),
+        error_msg: None,
+    }))
 }
 
 pub(crate) async fn get_receipt(
@@ -192,6 +209,29 @@ pub(crate) async fn put_receipt(
     Path(receipt_id): Path<String>,
     body: Bytes,
 ) -> Result<(), Error> {
-    s.write()?.put_receipt(receipt_id.clone(), body.to_vec());
+    s.write()?.put_receipt(receipt_id.clone(), body.to_vec())?;
+    Ok(())
+}
+
+pub(crate) async fn quotas(State(s): State<AppState>) -> Result<Json<Quotas>, Error> {
+    Ok(Json(s.read()?.quotas.clone()))
+}
+
+pub(crate) async fn version() -> Json<VersionInfo> {
+    Json(VersionInfo {
+        risc0_zkvm: vec![risc0_zkvm::VERSION.to_string()],
+    })
+}
+
+pub(crate) async fn get_faults(State(s): State<AppState>) -> Result<Json<Faults>, Error> {
+    Ok(Json(s.read()?.faults.clone()))
+}
+
+pub(crate) async fn put_faults(
+    State(s): State<AppState>,
+    Json(faults): Json<Faults>,
+) -> Result<(), Error> {
+    info!("Injecting faults: {faults:?}");
+    s.write()?.faults = faults;
     Ok(())
 }
@@ -14,56 +14,253 @@
 
 use std::{
     collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
+    time::SystemTime,
 };
 
+use anyhow::{anyhow, Context};
+use bonsai_sdk::alpha::responses::{Quotas, SessionStats};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, prover::Task, Faults, MockConfig};
+
 pub(crate) type AppState = Arc<RwLock<BonsaiState>>;
 
-#[derive(Clone, Default)]
+// Session statuses, as reported by Bonsai
+pub(crate) const RUNNING: &str = "RUNNING";
+pub(crate) const SUCCEEDED: &str = "SUCCEEDED";
+pub(crate) const FAILED: &str = "FAILED";
+pub(crate) const ABORTED: &str = "ABORTED";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Session {
+    pub(crate) task: Task,
+    // One of `RUNNING`, `SUCCEEDED`, `FAILED` or `ABORTED`
+    pub(crate) status: String,
+    // Where a `RUNNING` session is in the pipeline, `Setup` while queued
+    pub(crate) state: Option<String>,
+    pub(crate) error_msg: Option<String>,
+    pub(crate) created: SystemTime,
+    pub(crate) elapsed_time: Option<f64>,
+    // Segments, total cycles and user cycles of a successful session
+    pub(crate) stats: Option<(usize, u64, u64)>,
+}
+
+impl Session {
+    pub(crate) fn new(task: Task) -> Self {
+        Self {
+            task,
+            status: RUNNING.to_string(),
+            state: Some("Setup".to_string()),
+            error_msg: None,
+            created: SystemTime::now(),
+            elapsed_time: None,
+            stats: None,
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.status == RUNNING
+    }
+
+    pub(crate) fn elapsed_time(&self) -> f64 {
+        self.elapsed_time
+            .unwrap_or_else(|| self.created.elapsed().unwrap_or_default().as_secs_f64())
+    }
+
+    pub(crate) fn stats(&self) -> Option<SessionStats> {
+        self.stats
+            .map(|(segments, total_cycles, cycles)| SessionStats {
+                segments,
+                total_cycles,
+                cycles,
+            })
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct BonsaiState {
     pub(crate) local_url: String,
+    // Directory that all state is persisted to, if any
+    pub(crate) state_dir: Option<PathBuf>,
+    pub(crate) config: MockConfig,
+    pub(crate) faults: Faults,
+    pub(crate) quotas: Quotas,
     // ImageID - MemoryImage
     pub(crate) images: HashMap<String, Vec<u8>>,
     // InputID - input
     pub(crate) inputs: HashMap<String, Vec<u8>>,
-    // SessionID - Status
-    pub(crate) sessions: HashMap<String, String>,
+    // SessionID - Session
+    pub(crate) sessions: HashMap<String, Session>,
     // SessionID - Receipts
     pub(crate) receipts: HashMap<String, Vec<u8>>,
 }
 
 impl BonsaiState {
-    pub(crate) fn new(local_url: String) -> Self {
-        Self {
-            local_url,
+    /// Create the state, loading everything previously persisted to
+    /// [MockConfig::state_dir].
+    pub(crate) fn new(config: MockConfig) -> Result<Self, Error> {
+        let mut state = Self {
+            local_url: String::new(),
+            state_dir: config.state_dir.clone(),
+            faults: Faults::default(),
+            quotas: config.quotas.clone(),
+            config,
             images: HashMap::new(),
             inputs: HashMap::new(),
             sessions: HashMap::new(),
             receipts: HashMap::new(),
+        };
+        if let Some(dir) = &state.state_dir {
+            for kind in ["images", "inputs", "receipts", "sessions"] {
+                fs::create_dir_all(dir.join(kind))
+                    .with_context(|| format!("failed to create {}", dir.join(kind).display()))?;
+            }
+            state.images = load_dir(&dir.join("images"))?;
+            state.inputs = load_dir(&dir.join("inputs"))?;
+            state.receipts = load_dir(&dir.join("receipts"))?;
+            for (session_id, bytes) in load_dir(&dir.join("sessions"))? {
+                state
+                    .sessions
+                    .insert(session_id, serde_json::from_slice(&bytes)?);
+            }
+            let quotas = dir.join("quotas.json");
+            if quotas.exists() {
+                let usage: Quotas = serde_json::from_slice(&fs::read(quotas)?)?;
+                state.quotas.cycle_budget = usage.cycle_budget;
+                state.quotas.cycle_usage = usage.cycle_usage;
+            }
         }
+        Ok(state)
     }
-    pub(crate) fn put_image(&mut self, image_id: String, image: Vec<u8>) -> Option<Vec<u8>> {
-        self.images.insert(image_id, image)
+
+    fn persist(&self, kind: &str, id: &str, bytes: &[u8]) -> Result<(), Error> {
+        let Some(dir) = &self.state_dir else {
+            return Ok(());
+        };
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("invalid ID: {id:?}").into());
+        }
+        let path = dir.join(kind).join(id);
+        fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub(crate) fn put_image(&mut self, image_id: String, image: Vec<u8>) -> Result<(), Error> {
+        self.persist("images", &image_id, &image)?;
+        self.images.insert(image_id, image);
+        Ok(())
     }
     pub(crate) fn get_image(&self, image_id: impl AsRef<str>) -> Option<Vec<u8>> {
         self.images.get(image_id.as_ref()).cloned()
     }
-    pub(crate) fn put_input(&mut self, input_id: String, input: Vec<u8>) -> Option<Vec<u8>> {
-        self.inputs.insert(input_id, input)
+    pub(crate) fn put_input(&mut self, input_id: String, input: Vec<u8>) -> Result<(), Error> {
+        self.persist("inputs", &input_id, &input)?;
+        self.inputs.insert(input_id, input);
+        Ok(())
     }
     pub(crate) fn get_input(&self, input_id: impl AsRef<str>) -> Option<Vec<u8>> {
         self.inputs.get(input_id.as_ref()).cloned()
     }
-    pub(crate) fn put_session(&mut self, session_id: String, status: String) -> Option<String> {
-        self.sessions.insert(session_id, status)
+    pub(crate) fn put_session(
+        &mut self,
+        session_id: String,
+        session: Session,
+    ) -> Result<(), Error> {
+        self.persist("sessions", &session_id, &serde_json::to_vec(&session)?)?;
+        self.sessions.insert(session_id, session);
+        Ok(())
     }
-    pub(crate) fn get_session(&self, session_id: impl AsRef<str>) -> Option<String> {
+    pub(crate) fn get_session(&self, session_id: impl AsRef<str>) -> Option<Session> {
         self.sessions.get(session_id.as_ref()).cloned()
     }
-    pub(crate) fn put_receipt(&mut self, session_id: String, receipt: Vec<u8>) -> Option<Vec<u8>> {
-        self.receipts.insert(session_id, receipt)
+    pub(crate) fn put_receipt(
+        &mut self,
+        session_id: String,
+        receipt: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.persist("receipts", &session_id, &receipt)?;
+        self.receipts.insert(session_id, receipt);
+        Ok(())
     }
     pub(crate) fn get_receipt(&self, session_id: impl AsRef<str>) -> Option<Vec<u8>> {
         self.receipts.get(session_id.as_ref()).cloned()
     }
+
+    /// Update a session that is still running. Sessions that have already
+    /// finished, e.g. because they were aborted, are left untouched.
+    pub(crate) fn update_session(
+        &mut self,
+        session_id: &str,
+        update: impl FnOnce(&mut Session),
+    ) -> Result<bool, Error> {
+        let Some(mut session) = self.get_session(session_id) else {
+            return Ok(false);
+        };
+        if !session.is_running() {
+            return Ok(false);
+        }
+        update(&mut session);
+        if !session.is_running() {
+            session.state = None;
+            session.elapsed_time = Some(session.elapsed_time());
+        }
+        self.put_session(session_id.to_string(), session)?;
+        Ok(true)
+    }
+
+    pub(crate) fn running_sessions(&self) -> impl Iterator<Item = &Session> {
+        self.sessions
+            .values()
+            .filter(|session| session.is_running())
+    }
+
+    /// Charge the cycles of a finished session against the cycle budget.
+    pub(crate) fn charge_cycles(&mut self, cycles: u64) -> Result<(), Error> {
+        self.quotas.cycle_usage = self.quotas.cycle_usage.saturating_add(cycles);
+        self.quotas.cycle_budget = self.quotas.cycle_budget.saturating_sub(cycles);
+        if let Some(dir) = &self.state_dir {
+            fs::write(dir.join("quotas.json"), serde_json::to_vec(&self.quotas)?)?;
+        }
+        Ok(())
+    }
+
+    /// Consume one injected session failure, returning the status the session
+    /// should end in.
+    pub(crate) fn take_session_fault(&mut self) -> Option<&'static str> {
+        if self.faults.failed_sessions > 0 {
+            self.faults.failed_sessions -= 1;
+            Some(FAILED)
+        } else if self.faults.aborted_sessions > 0 {
+            self.faults.aborted_sessions -= 1;
+            Some(ABORTED)
+        } else {
+            None
+        }
+    }
+
+    /// Consume one injected HTTP error, returning its status code.
+    pub(crate) fn take_http_fault(&mut self) -> Option<u16> {
+        if self.faults.http_errors == 0 {
+            return None;
+        }
+        self.faults.http_errors -= 1;
+        Some(self.faults.http_status)
+    }
+}
+
+fn load_dir(dir: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let mut entries = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let id = entry.file_name().to_string_lossy().into_owned();
+        entries.insert(id, fs::read(entry.path())?);
+    }
+    Ok(entries)
 }
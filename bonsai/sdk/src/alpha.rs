@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fs::File, path::Path};
+use std::{
+    fs::File,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use reqwest::{blocking::Client as BlockingClient, header};
+use reqwest::{
+    blocking::{Client as BlockingClient, RequestBuilder, Response},
+    header, StatusCode,
+};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -30,6 +37,31 @@ pub enum SdkErr {
     /// Server side failure
     #[error("server error `{0}`")]
     InternalServerErr(String),
+    /// The server responded with a non-success HTTP status
+    #[error("HTTP status {status}: `{body}`")]
+    HttpStatus {
+        /// HTTP status code of the response
+        status: StatusCode,
+        /// Body of the response
+        body: String,
+    },
+    /// A session or SNARK session finished without succeeding
+    #[error("workflow [{uuid}] exited: {status} err: {}", error_msg.as_deref().unwrap_or("missing error_msg"))]
+    WorkflowFailed {
+        /// UUID of the session
+        uuid: String,
+        /// Final status, e.g. `FAILED`
+        status: String,
+        /// Error message reported by bonsai
+        error_msg: Option<String>,
+    },
+    /// The timeout passed while waiting for a workflow to complete
+    #[error("timed out after {0:?} waiting for workflow to complete")]
+    Timeout(Duration),
+    /// A blocking task of the async client failed
+    #[cfg(feature = "async")]
+    #[error("async task failed")]
+    JoinErr(#[from] tokio::task::JoinError),
     /// http reqwest errors
     #[error("HTTP error from reqwest")]
     HttpErr(#[from] reqwest::Error),
@@ -47,6 +79,186 @@ pub enum SdkErr {
     FileNotFound(#[from] std::io::Error),
 }
 
+impl SdkErr {
+    /// The HTTP status code of the response that caused this error, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            SdkErr::HttpStatus { status, .. } => Some(*status),
+            SdkErr::HttpErr(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the request may succeed when retried
+    ///
+    /// Connection failures, timeouts, and the `429`, `502`, `503` and `504`
+    /// statuses are transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SdkErr::HttpErr(err) => err.is_connect() || err.is_timeout(),
+            SdkErr::HttpStatus { status, .. } => matches!(
+                *status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Policy for retrying requests that fail with a transient error
+///
+/// See [SdkErr::is_transient] for the errors that are retried. Only idempotent
+/// `GET` and `PUT` requests are retried: the requests that start a session or
+/// a snark are sent once, since a retry after a `502` or `504` could start a
+/// second workflow if the first request did reach the server. The delay
+/// between retries starts at `initial_backoff` and is multiplied by
+/// `multiplier` after each retry, up to `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between retries
+    pub max_backoff: Duration,
+    /// Factor by which the delay grows after each retry
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A [RetryPolicy] that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before the given retry, counting from zero
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(retry as i32);
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Options for [SessionId::wait_for_completion] and
+/// [SnarkId::wait_for_completion]
+#[derive(Clone, Debug)]
+pub struct WaitOptions {
+    /// Delay between status requests
+    pub poll_interval: Duration,
+    /// Maximum time to wait, or [None] to wait indefinitely
+    pub timeout: Option<Duration>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            timeout: None,
+        }
+    }
+}
+
+impl WaitOptions {
+    /// Returns how long to sleep before the next poll of a wait that began at
+    /// `start`, or [SdkErr::Timeout] if the timeout has passed.
+    pub(crate) fn next_poll(&self, start: Instant) -> Result<Duration, SdkErr> {
+        let Some(timeout) = self.timeout else {
+            return Ok(self.poll_interval);
+        };
+        let remaining = timeout
+            .checked_sub(start.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(SdkErr::Timeout(timeout))?;
+        Ok(self.poll_interval.min(remaining))
+    }
+}
+
+/// The status of a workflow that can be waited on
+pub(crate) trait WorkflowStatus {
+    fn status(&self) -> &str;
+    fn error_msg(&self) -> Option<String>;
+
+    /// Returns [None] while running, or the final result once finished
+    fn finished(self, uuid: &str) -> Option<Result<Self, SdkErr>>
+    where
+        Self: Sized,
+    {
+        match self.status() {
+            "RUNNING" => None,
+            "SUCCEEDED" => Some(Ok(self)),
+            status => Some(Err(SdkErr::WorkflowFailed {
+                uuid: uuid.to_string(),
+                status: status.to_string(),
+                error_msg: self.error_msg(),
+            })),
+        }
+    }
+}
+
+impl WorkflowStatus for SessionStatusRes {
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn error_msg(&self) -> Option<String> {
+        self.error_msg.clone()
+    }
+}
+
+impl WorkflowStatus for SnarkStatusRes {
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn error_msg(&self) -> Option<String> {
+        self.error_msg.clone()
+    }
+}
+
+/// Poll `fetch` until the workflow finishes, calling `progress` with each status
+fn wait_for_completion<T: WorkflowStatus>(
+    uuid: &str,
+    opts: &WaitOptions,
+    mut fetch: impl FnMut() -> Result<T, SdkErr>,
+    mut progress: impl FnMut(&T),
+) -> Result<T, SdkErr> {
+    let start = Instant::now();
+    loop {
+        let res = fetch()?;
+        progress(&res);
+        if let Some(res) = res.finished(uuid) {
+            return res;
+        }
+        std::thread::sleep(opts.next_poll(start)?);
+    }
+}
+
+/// Returns an [SdkErr::HttpStatus] if the response is not successful
+fn check_status(res: Response) -> Result<Response, SdkErr> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let body = res.text()?;
+    Err(SdkErr::HttpStatus { status, body })
+}
+
 /// Collection of serialization object for the REST api
 pub mod responses {
     use risc0_groth16::Seal;
@@ -190,7 +402,7 @@ pub mod responses {
     }
 
     /// User quotas and cycle budgets
-    #[derive(Deserialize, Serialize)]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     pub struct Quotas {
         /// Executor cycle limit, in millions of cycles
         pub exec_cycle_limit: u64,
@@ -221,15 +433,38 @@ impl SessionId {
     /// Fetches the current status of the Session
     pub fn status(&self, client: &Client) -> Result<SessionStatusRes, SdkErr> {
         let url = format!("{}/sessions/status/{}", client.url, self.uuid);
-        let res = client.client.get(url).send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
+        let res = client.send(|| Ok(client.client.get(&url)))?;
         Ok(res.json::<SessionStatusRes>()?)
     }
 
+    /// Polls the status of the Session until it is no longer `RUNNING`
+    ///
+    /// `progress` is called with each status fetched. Returns the final status
+    /// if the Session succeeded, [SdkErr::WorkflowFailed] if it did not, or
+    /// [SdkErr::Timeout] if the [WaitOptions::timeout] passed first.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// use bonsai_sdk::alpha::{Client, SessionId, WaitOptions};
+    /// # let client = Client::from_env(risc0_zkvm::VERSION).unwrap();
+    /// # let session = SessionId::new("uuid".to_string());
+    /// let res = session
+    ///     .wait_for_completion(&client, &WaitOptions::default(), |res| {
+    ///         eprintln!("state: {}", res.state.as_deref().unwrap_or_default())
+    ///     })
+    ///     .unwrap();
+    /// let receipt_url = res.receipt_url.unwrap();
+    /// ```
+    pub fn wait_for_completion(
+        &self,
+        client: &Client,
+        opts: &WaitOptions,
+        progress: impl FnMut(&SessionStatusRes),
+    ) -> Result<SessionStatusRes, SdkErr> {
+        wait_for_completion(&self.uuid, opts, || self.status(client), progress)
+    }
+
     /// Fetches the zkvm guest logs for a session
     ///
     /// After the Execution phase of proving is completed, you can use this method
@@ -240,12 +475,7 @@ impl SessionId {
     /// from `env::log`
     pub fn logs(&self, client: &Client) -> Result<String, SdkErr> {
         let url = format!("{}/sessions/logs/{}", client.url, self.uuid);
-        let res = client.client.get(url).send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
+        let res = client.send(|| Ok(client.client.get(&url)))?;
         Ok(res.text()?)
     }
 }
@@ -266,14 +496,21 @@ impl SnarkId {
     /// Fetches the current status of the Snark Session
     pub fn status(&self, client: &Client) -> Result<SnarkStatusRes, SdkErr> {
         let url = format!("{}/snark/status/{}", client.url, self.uuid);
-        let res = client.client.get(url).send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
+        let res = client.send(|| Ok(client.client.get(&url)))?;
         Ok(res.json::<SnarkStatusRes>()?)
     }
+
+    /// Polls the status of the Snark Session until it is no longer `RUNNING`
+    ///
+    /// See [SessionId::wait_for_completion].
+    pub fn wait_for_completion(
+        &self,
+        client: &Client,
+        opts: &WaitOptions,
+        progress: impl FnMut(&SnarkStatusRes),
+    ) -> Result<SnarkStatusRes, SdkErr> {
+        wait_for_completion(&self.uuid, opts, || self.status(client), progress)
+    }
 }
 
 /// Represents a client of the REST api
@@ -281,6 +518,7 @@ impl SnarkId {
 pub struct Client {
     pub(crate) url: String,
     pub(crate) client: BlockingClient,
+    pub(crate) retry: RetryPolicy,
 }
 
 enum ImageExistsOpt {
@@ -324,6 +562,7 @@ impl Client {
         Ok(Self {
             url: api_url.to_string(),
             client,
+            retry: RetryPolicy::default(),
         })
     }
 
@@ -341,50 +580,81 @@ impl Client {
     pub fn from_parts(url: String, key: String, risc0_version: &str) -> Result<Self, SdkErr> {
         let client = construct_req_client(&key, risc0_version)?;
         let url = url.strip_suffix('/').unwrap_or(&url).to_string();
-        Ok(Self { url, client })
+        Ok(Self {
+            url,
+            client,
+            retry: RetryPolicy::default(),
+        })
     }
 
-    /// Fetch a upload presigned url for a given route
-    fn get_upload_url(&self, route: &str) -> Result<UploadRes, SdkErr> {
-        let res = self
-            .client
-            .get(format!("{}/{}/upload", self.url, route))
-            .send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
+    /// Set the [RetryPolicy] used for requests that fail with a transient error
+    ///
+    /// # Example:
+    ///
+    /// ```
+    /// use bonsai_sdk::alpha::{Client, RetryPolicy};
+    /// let url = "http://api.bonsai.xyz".to_string();
+    /// let api_key = "my_secret_key".to_string();
+    /// let client = Client::from_parts(url, api_key, risc0_zkvm::VERSION)
+    ///     .expect("Failed to construct sdk client")
+    ///     .with_retry_policy(RetryPolicy {
+    ///         max_retries: 5,
+    ///         ..Default::default()
+    ///     });
+    /// ```
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send the idempotent request built by `req`, retrying transient errors
+    /// according to the [RetryPolicy]
+    fn send(&self, req: impl Fn() -> Result<RequestBuilder, SdkErr>) -> Result<Response, SdkErr> {
+        let mut retry = 0;
+        loop {
+            let res = req()?.send().map_err(SdkErr::from).and_then(check_status);
+            match res {
+                Err(err) if err.is_transient() && retry < self.retry.max_retries => {
+                    std::thread::sleep(self.retry.backoff(retry));
+                    retry += 1;
+                }
+                res => return res,
+            }
         }
+    }
 
+    /// Send the non-idempotent request `req` once, without retrying
+    fn send_once(&self, req: RequestBuilder) -> Result<Response, SdkErr> {
+        req.send().map_err(SdkErr::from).and_then(check_status)
+    }
+
+    /// Fetch a upload presigned url for a given route
+    fn get_upload_url(&self, route: &str) -> Result<UploadRes, SdkErr> {
+        let url = format!("{}/{}/upload", self.url, route);
+        let res = self.send(|| Ok(self.client.get(&url)))?;
         Ok(res.json::<UploadRes>()?)
     }
 
     fn get_image_upload_url(&self, image_id: &str) -> Result<ImageExistsOpt, SdkErr> {
-        let res = self
-            .client
-            .get(format!("{}/images/upload/{}", self.url, image_id))
-            .send()?;
+        let url = format!("{}/images/upload/{}", self.url, image_id);
+        let res = self.send(|| Ok(self.client.get(&url)))?;
 
         if res.status() == 204 {
             return Ok(ImageExistsOpt::Exists);
         }
 
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
-
         Ok(ImageExistsOpt::New(res.json::<ImgUploadRes>()?))
     }
 
     /// Upload body to a given URL
-    fn put_data<T: Into<reqwest::blocking::Body>>(&self, url: &str, body: T) -> Result<(), SdkErr> {
-        let res = self.client.put(url).body(body).send()?;
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
-
+    ///
+    /// `body` is called for each attempt, so that the upload can be retried.
+    fn put_data<T: Into<reqwest::blocking::Body>>(
+        &self,
+        url: &str,
+        body: impl Fn() -> Result<T, SdkErr>,
+    ) -> Result<(), SdkErr> {
+        self.send(|| Ok(self.client.put(url).body(body()?)))?;
         Ok(())
     }
 
@@ -402,7 +672,7 @@ impl Client {
         match res_or_exists {
             ImageExistsOpt::Exists => Ok(true),
             ImageExistsOpt::New(upload_res) => {
-                self.put_data(&upload_res.url, buf)?;
+                self.put_data(&upload_res.url, || Ok(buf.clone()))?;
                 Ok(false)
             }
         }
@@ -420,8 +690,7 @@ impl Client {
         match res_or_exists {
             ImageExistsOpt::Exists => Ok(true),
             ImageExistsOpt::New(upload_res) => {
-                let fd = File::open(path)?;
-                self.put_data(&upload_res.url, fd)?;
+                self.put_data(&upload_res.url, || Ok(File::open(path)?))?;
                 Ok(false)
             }
         }
@@ -432,7 +701,7 @@ impl Client {
     /// Upload a input buffer to the /inputs/ route
     pub fn upload_input(&self, buf: Vec<u8>) -> Result<String, SdkErr> {
         let upload_data = self.get_upload_url("inputs")?;
-        self.put_data(&upload_data.url, buf)?;
+        self.put_data(&upload_data.url, || Ok(buf.clone()))?;
         Ok(upload_data.uuid)
     }
 
@@ -440,8 +709,7 @@ impl Client {
     pub fn upload_input_file(&self, path: &Path) -> Result<String, SdkErr> {
         let upload_data = self.get_upload_url("inputs")?;
 
-        let fd = File::open(path)?;
-        self.put_data(&upload_data.url, fd)?;
+        self.put_data(&upload_data.url, || Ok(File::open(path)?))?;
 
         Ok(upload_data.uuid)
     }
@@ -451,7 +719,7 @@ impl Client {
     /// Upload a receipt buffer to the /receipts/ route
     pub fn upload_receipt(&self, buf: Vec<u8>) -> Result<String, SdkErr> {
         let upload_data = self.get_upload_url("receipts")?;
-        self.put_data(&upload_data.url, buf)?;
+        self.put_data(&upload_data.url, || Ok(buf.clone()))?;
         Ok(upload_data.uuid)
     }
 
@@ -459,8 +727,7 @@ impl Client {
     pub fn upload_receipt_file(&self, path: &Path) -> Result<String, SdkErr> {
         let upload_data = self.get_upload_url("receipts")?;
 
-        let fd = File::open(path)?;
-        self.put_data(&upload_data.url, fd)?;
+        self.put_data(&upload_data.url, || Ok(File::open(path)?))?;
 
         Ok(upload_data.uuid)
     }
@@ -485,13 +752,7 @@ impl Client {
             assumptions,
         };
 
-        let res = self.client.post(url).json(&req).send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
-
+        let res = self.send_once(self.client.post(url).json(&req))?;
         let res: CreateSessRes = res.json()?;
 
         Ok(SessionId::new(res.uuid))
@@ -503,7 +764,7 @@ impl Client {
     ///
     /// Useful to download a [SessionId] receipt_url
     pub fn download(&self, url: &str) -> Result<Vec<u8>, SdkErr> {
-        let data = self.client.get(url).send()?.bytes()?;
+        let data = self.send(|| Ok(self.client.get(url)))?.bytes()?;
 
         Ok(data.into())
     }
@@ -519,12 +780,7 @@ impl Client {
 
         let snark_req = SnarkReq { session_id };
 
-        let res = self.client.post(url).json(&snark_req).send()?;
-
-        if !res.status().is_success() {
-            let body = res.text()?;
-            return Err(SdkErr::InternalServerErr(body));
-        }
+        let res = self.send_once(self.client.post(url).json(&snark_req))?;
 
         // Reuse the session response because its the same member format
         let res: CreateSessRes = res.json()?;
@@ -539,10 +795,9 @@ impl Client {
     /// Fetches the risc0 zkvm supported versions as well as other
     /// sub-components of bonsai
     pub fn version(&self) -> Result<VersionInfo, SdkErr> {
+        let url = format!("{}/version", self.url);
         Ok(self
-            .client
-            .get(format!("{}/version", self.url))
-            .send()?
+            .send(|| Ok(self.client.get(&url)))?
             .json::<VersionInfo>()?)
     }
 
@@ -552,11 +807,8 @@ impl Client {
     ///
     /// Returns the [Quotas] structure with relevant data on cycle budget, quotas etc.
     pub fn quotas(&self) -> Result<Quotas, SdkErr> {
-        Ok(self
-            .client
-            .get(format!("{}/user/quotas", self.url))
-            .send()?
-            .json::<Quotas>()?)
+        let url = format!("{}/user/quotas", self.url);
+        Ok(self.send(|| Ok(self.client.get(&url)))?.json::<Quotas>()?)
     }
 }
 
@@ -981,4 +1233,151 @@ mod tests {
 
         get_mock.assert();
     }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retry_transient() {
+        let server = MockServer::start();
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/user/quotas");
+            then.status(503).body("unavailable");
+        });
+
+        let server_url = format!("http://{}", server.address());
+        let client = super::Client::from_parts(server_url, TEST_KEY.to_string(), TEST_VERSION)
+            .unwrap()
+            .with_retry_policy(fast_retry());
+        let err = client.quotas().err().unwrap();
+        assert!(err.is_transient());
+        assert_eq!(err.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        get_mock.assert_hits(3);
+    }
+
+    #[test]
+    fn no_retry_create_session() {
+        let server = MockServer::start();
+
+        let create_mock = server.mock(|when, then| {
+            when.method(POST).path("/sessions/create");
+            then.status(502).body("bad gateway");
+        });
+
+        let server_url = format!("http://{}", server.address());
+        let client = super::Client::from_parts(server_url, TEST_KEY.to_string(), TEST_VERSION)
+            .unwrap()
+            .with_retry_policy(fast_retry());
+        let err = client
+            .create_session("img".to_string(), "input".to_string(), vec![])
+            .err()
+            .unwrap();
+        assert_eq!(err.status(), Some(StatusCode::BAD_GATEWAY));
+
+        create_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn no_retry_client_error() {
+        let server = MockServer::start();
+
+        let uuid = Uuid::new_v4().to_string();
+        let session_id = SessionId::new(uuid);
+        let get_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/sessions/status/{}", session_id.uuid));
+            then.status(404).body("not found");
+        });
+
+        let server_url = format!("http://{}", server.address());
+        let client = super::Client::from_parts(server_url, TEST_KEY.to_string(), TEST_VERSION)
+            .unwrap()
+            .with_retry_policy(fast_retry());
+        match session_id.status(&client) {
+            Err(SdkErr::HttpStatus { status, body }) => {
+                assert_eq!(status, StatusCode::NOT_FOUND);
+                assert_eq!(body, "not found");
+            }
+            _ => panic!("expected HttpStatus error"),
+        }
+
+        get_mock.assert_hits(1);
+    }
+
+    fn mock_session_status(server: &MockServer, session_id: &SessionId, status: &str) {
+        let response = SessionStatusRes {
+            status: status.to_string(),
+            receipt_url: None,
+            error_msg: (status == "FAILED").then(|| "guest panicked".to_string()),
+            state: None,
+            elapsed_time: None,
+            stats: None,
+        };
+        server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/sessions/status/{}", session_id.uuid));
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body_obj(&response);
+        });
+    }
+
+    #[test]
+    fn wait_for_completion() {
+        let opts = WaitOptions {
+            poll_interval: Duration::from_millis(10),
+            timeout: Some(Duration::from_millis(100)),
+        };
+        let session_id = SessionId::new(Uuid::new_v4().to_string());
+
+        let server = MockServer::start();
+        mock_session_status(&server, &session_id, "SUCCEEDED");
+        let client =
+            super::Client::from_parts(server.base_url(), TEST_KEY.to_string(), TEST_VERSION)
+                .unwrap();
+        let mut polls = 0;
+        let res = session_id
+            .wait_for_completion(&client, &opts, |_| polls += 1)
+            .unwrap();
+        assert_eq!(res.status, "SUCCEEDED");
+        assert_eq!(polls, 1);
+
+        let server = MockServer::start();
+        mock_session_status(&server, &session_id, "FAILED");
+        let client =
+            super::Client::from_parts(server.base_url(), TEST_KEY.to_string(), TEST_VERSION)
+                .unwrap();
+        match session_id.wait_for_completion(&client, &opts, |_| ()) {
+            Err(SdkErr::WorkflowFailed {
+                uuid,
+                status,
+                error_msg,
+            }) => {
+                assert_eq!(uuid, session_id.uuid);
+                assert_eq!(status, "FAILED");
+                assert_eq!(error_msg.as_deref(), Some("guest panicked"));
+            }
+            _ => panic!("expected WorkflowFailed error"),
+        }
+
+        let server = MockServer::start();
+        mock_session_status(&server, &session_id, "RUNNING");
+        let client =
+            super::Client::from_parts(server.base_url(), TEST_KEY.to_string(), TEST_VERSION)
+                .unwrap();
+        let mut polls = 0;
+        let err = session_id
+            .wait_for_completion(&client, &opts, |_| polls += 1)
+            .err()
+            .unwrap();
+        assert!(matches!(err, SdkErr::Timeout(_)));
+        assert!(polls > 1);
+    }
 }
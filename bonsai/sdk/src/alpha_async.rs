@@ -12,19 +12,48 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Instant;
+
 use crate::alpha::{
-    responses::{Quotas, SessionStatusRes, SnarkStatusRes},
-    Client, SdkErr, SessionId, SnarkId,
+    responses::{Quotas, SessionStatusRes, SnarkStatusRes, VersionInfo},
+    Client, RetryPolicy, SdkErr, SessionId, SnarkId, WaitOptions, WorkflowStatus,
 };
 
+/// Run a blocking [Client] call on the tokio blocking thread pool
+async fn run<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, SdkErr> + Send + 'static,
+) -> Result<T, SdkErr> {
+    tokio::task::spawn_blocking(f).await?
+}
+
+/// Poll `fetch` until the workflow finishes, without blocking the runtime
+async fn wait_for_completion<T, Fut>(
+    uuid: &str,
+    opts: &WaitOptions,
+    mut fetch: impl FnMut() -> Fut,
+    mut progress: impl FnMut(&T),
+) -> Result<T, SdkErr>
+where
+    T: WorkflowStatus,
+    Fut: std::future::Future<Output = Result<T, SdkErr>>,
+{
+    let start = Instant::now();
+    loop {
+        let res = fetch().await?;
+        progress(&res);
+        if let Some(res) = res.finished(uuid) {
+            return res;
+        }
+        tokio::time::sleep(opts.next_poll(start)?).await;
+    }
+}
+
 /// Construct a Bonsai SDK Client from env var
 ///
 /// Uses the BONSAI_API_URL and BONSAI_API_KEY environment variables to
 /// construct a client
 pub async fn get_client_from_env(risc0_version: &'static str) -> Result<Client, SdkErr> {
-    tokio::task::spawn_blocking(|| Client::from_env(risc0_version))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || Client::from_env(risc0_version)).await
 }
 
 /// Construct a Bonsai SDK Client from url + api key strings
@@ -33,23 +62,17 @@ pub async fn get_client_from_parts(
     api_key: String,
     risc0_version: &'static str,
 ) -> Result<Client, SdkErr> {
-    tokio::task::spawn_blocking(move || Client::from_parts(url, api_key, risc0_version))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || Client::from_parts(url, api_key, risc0_version)).await
 }
 
 /// Upload a input buffer to the /inputs/ route
 pub async fn upload_input(bonsai_client: Client, buf: Vec<u8>) -> Result<String, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.upload_input(buf))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.upload_input(buf)).await
 }
 
 /// Upload a receipt buffer to the /receipts/ route
 pub async fn upload_receipt(bonsai_client: Client, buf: Vec<u8>) -> Result<String, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.upload_receipt(buf))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.upload_receipt(buf)).await
 }
 
 /// Upload a image buffer to the /images/ route
@@ -64,9 +87,7 @@ pub async fn upload_img(
     image_id: String,
     image: Vec<u8>,
 ) -> Result<bool, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.upload_img(&image_id, image))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.upload_img(&image_id, image)).await
 }
 
 /// Create a new proof request Session
@@ -79,9 +100,7 @@ pub async fn create_session(
     input_id: String,
     assumptions: Vec<String>,
 ) -> Result<SessionId, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.create_session(img_id, input_id, assumptions))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.create_session(img_id, input_id, assumptions)).await
 }
 
 /// Fetches the current status of the Session
@@ -89,9 +108,20 @@ pub async fn session_status(
     bonsai_client: Client,
     session: SessionId,
 ) -> Result<SessionStatusRes, SdkErr> {
-    tokio::task::spawn_blocking(move || session.status(&bonsai_client))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || session.status(&bonsai_client)).await
+}
+
+/// Waits for a Session to complete without blocking the runtime
+///
+/// See [SessionId::wait_for_completion].
+pub async fn wait_for_session(
+    bonsai_client: Client,
+    session: SessionId,
+    opts: WaitOptions,
+    progress: impl FnMut(&SessionStatusRes),
+) -> Result<SessionStatusRes, SdkErr> {
+    let fetch = || session_status(bonsai_client.clone(), session.clone());
+    wait_for_completion(&session.uuid, &opts, fetch, progress).await
 }
 
 /// Fetches the zkvm guest logs for a session
@@ -103,9 +133,7 @@ pub async fn session_status(
 /// It should contain the output of all writes to those file descriptors. But does NOT include output
 /// from `env::log`
 pub async fn session_logs(bonsai_client: Client, session: SessionId) -> Result<String, SdkErr> {
-    tokio::task::spawn_blocking(move || session.logs(&bonsai_client))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || session.logs(&bonsai_client)).await
 }
 
 /// Requests a SNARK proof be created from a existing sessionId
@@ -113,32 +141,188 @@ pub async fn session_logs(bonsai_client: Client, session: SessionId) -> Result<S
 /// Supply a completed sessionId to convert the risc0 STARK proof into
 /// a SNARK proof that can be validated on ethereum-like blockchains
 pub async fn create_snark(bonsai_client: Client, session_id: String) -> Result<SnarkId, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.create_snark(session_id))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.create_snark(session_id)).await
 }
 
 /// Fetches the current status of the Snark Session
 pub async fn snark_status(bonsai_client: Client, snark: SnarkId) -> Result<SnarkStatusRes, SdkErr> {
-    tokio::task::spawn_blocking(move || snark.status(&bonsai_client))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || snark.status(&bonsai_client)).await
+}
+
+/// Waits for a Snark Session to complete without blocking the runtime
+///
+/// See [SnarkId::wait_for_completion].
+pub async fn wait_for_snark(
+    bonsai_client: Client,
+    snark: SnarkId,
+    opts: WaitOptions,
+    progress: impl FnMut(&SnarkStatusRes),
+) -> Result<SnarkStatusRes, SdkErr> {
+    let fetch = || snark_status(bonsai_client.clone(), snark.clone());
+    wait_for_completion(&snark.uuid, &opts, fetch, progress).await
 }
 
 /// Download a given url to a buffer
 ///
 /// Useful to download a [SessionId] receipt_url
 pub async fn download(bonsai_client: Client, url: String) -> Result<Vec<u8>, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.download(&url))
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.download(&url)).await
 }
 
 /// Fetches your current users quotas
 ///
 /// Returns the [Quotas] structure with relevant data on cycle budget, quotas etc.
 pub async fn quotas(bonsai_client: Client) -> Result<Quotas, SdkErr> {
-    tokio::task::spawn_blocking(move || bonsai_client.quotas())
-        .await
-        .map_err(|err| SdkErr::InternalServerErr(format!("{err}")))?
+    run(move || bonsai_client.quotas()).await
+}
+
+/// Fetches the current component versions from bonsai
+pub async fn version(bonsai_client: Client) -> Result<VersionInfo, SdkErr> {
+    run(move || bonsai_client.version()).await
+}
+
+/// An async variant of [Client]
+///
+/// This is not a native async client: each request runs the blocking [Client]
+/// with [tokio::task::spawn_blocking], so it must be used within a tokio
+/// runtime, and every request in flight occupies a thread of the runtime's
+/// blocking pool, including while it sleeps between retries. Waiting for a
+/// workflow sleeps between polls without blocking the runtime.
+///
+/// # Example:
+///
+/// ```no_run
+/// use bonsai_sdk::alpha::WaitOptions;
+/// use bonsai_sdk::alpha_async::AsyncClient;
+///
+/// # async fn run(elf: Vec<u8>, image_id: String, input: Vec<u8>) -> anyhow::Result<()> {
+/// let client = AsyncClient::from_env(risc0_zkvm::VERSION).await?;
+/// client.upload_img(image_id.clone(), elf).await?;
+/// let input_id = client.upload_input(input).await?;
+/// let session = client.create_session(image_id, input_id, vec![]).await?;
+/// let res = client
+///     .wait_for_session(session, WaitOptions::default(), |_| ())
+///     .await?;
+/// let receipt = client.download(res.receipt_url.unwrap()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncClient {
+    inner: Client,
+}
+
+impl From<Client> for AsyncClient {
+    fn from(inner: Client) -> Self {
+        Self { inner }
+    }
+}
+
+impl AsyncClient {
+    /// Construct an [AsyncClient] from env vars, see [Client::from_env]
+    pub async fn from_env(risc0_version: &'static str) -> Result<Self, SdkErr> {
+        Ok(get_client_from_env(risc0_version).await?.into())
+    }
+
+    /// Construct an [AsyncClient] from url, api key, and zkvm version, see
+    /// [Client::from_parts]
+    pub async fn from_parts(
+        url: String,
+        api_key: String,
+        risc0_version: &'static str,
+    ) -> Result<Self, SdkErr> {
+        Ok(get_client_from_parts(url, api_key, risc0_version)
+            .await?
+            .into())
+    }
+
+    /// Set the [RetryPolicy], see [Client::with_retry_policy]
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        self.inner.with_retry_policy(retry).into()
+    }
+
+    /// The underlying blocking [Client]
+    pub fn blocking(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Upload a input buffer to the /inputs/ route
+    pub async fn upload_input(&self, buf: Vec<u8>) -> Result<String, SdkErr> {
+        upload_input(self.inner.clone(), buf).await
+    }
+
+    /// Upload a receipt buffer to the /receipts/ route
+    pub async fn upload_receipt(&self, buf: Vec<u8>) -> Result<String, SdkErr> {
+        upload_receipt(self.inner.clone(), buf).await
+    }
+
+    /// Upload a image buffer to the /images/ route, see [Client::upload_img]
+    pub async fn upload_img(&self, image_id: String, image: Vec<u8>) -> Result<bool, SdkErr> {
+        upload_img(self.inner.clone(), image_id, image).await
+    }
+
+    /// Create a new proof request Session
+    pub async fn create_session(
+        &self,
+        img_id: String,
+        input_id: String,
+        assumptions: Vec<String>,
+    ) -> Result<SessionId, SdkErr> {
+        create_session(self.inner.clone(), img_id, input_id, assumptions).await
+    }
+
+    /// Fetches the current status of the Session
+    pub async fn session_status(&self, session: SessionId) -> Result<SessionStatusRes, SdkErr> {
+        session_status(self.inner.clone(), session).await
+    }
+
+    /// Waits for a Session to complete, see [SessionId::wait_for_completion]
+    pub async fn wait_for_session(
+        &self,
+        session: SessionId,
+        opts: WaitOptions,
+        progress: impl FnMut(&SessionStatusRes),
+    ) -> Result<SessionStatusRes, SdkErr> {
+        wait_for_session(self.inner.clone(), session, opts, progress).await
+    }
+
+    /// Fetches the zkvm guest logs for a session, see [SessionId::logs]
+    pub async fn session_logs(&self, session: SessionId) -> Result<String, SdkErr> {
+        session_logs(self.inner.clone(), session).await
+    }
+
+    /// Requests a SNARK proof be created from a existing sessionId
+    pub async fn create_snark(&self, session_id: String) -> Result<SnarkId, SdkErr> {
+        create_snark(self.inner.clone(), session_id).await
+    }
+
+    /// Fetches the current status of the Snark Session
+    pub async fn snark_status(&self, snark: SnarkId) -> Result<SnarkStatusRes, SdkErr> {
+        snark_status(self.inner.clone(), snark).await
+    }
+
+    /// Waits for a Snark Session to complete, see [SnarkId::wait_for_completion]
+    pub async fn wait_for_snark(
+        &self,
+        snark: SnarkId,
+        opts: WaitOptions,
+        progress: impl FnMut(&SnarkStatusRes),
+    ) -> Result<SnarkStatusRes, SdkErr> {
+        wait_for_snark(self.inner.clone(), snark, opts, progress).await
+    }
+
+    /// Download a given url to a buffer
+    pub async fn download(&self, url: String) -> Result<Vec<u8>, SdkErr> {
+        download(self.inner.clone(), url).await
+    }
+
+    /// Fetches the current component versions from bonsai
+    pub async fn version(&self) -> Result<VersionInfo, SdkErr> {
+        version(self.inner.clone()).await
+    }
+
+    /// Fetches your current users quotas
+    pub async fn quotas(&self) -> Result<Quotas, SdkErr> {
+        quotas(self.inner.clone()).await
+    }
 }
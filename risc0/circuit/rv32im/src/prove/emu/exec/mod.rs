@@ -57,6 +57,10 @@ use crate::{
 
 pub const DEFAULT_SEGMENT_LIMIT_PO2: usize = 20;
 
+/// The number of instructions executed between calls to the interrupt check
+/// set by [Executor::with_interrupt_check].
+pub const INTERRUPT_CHECK_INTERVAL: usize = 1 << 10;
+
 /// A host-side implementation of a system call.
 pub trait Syscall {
     /// Invokes the system call.
@@ -91,6 +95,12 @@ pub trait SyscallContext {
     /// Returns the current cycle count.
     // TODO(breaking change): use `u64`
     fn get_cycle(&self) -> usize;
+
+    /// Returns the program counter of the current instruction.
+    fn get_pc(&self) -> u32;
+
+    /// Returns the index of the segment being executed.
+    fn get_segment_index(&self) -> usize;
 }
 
 pub struct ExecutorResult {
@@ -137,6 +147,8 @@ pub struct Executor<'a, 'b, S: Syscall> {
     pending: PendingState,
     trace: Vec<Rc<RefCell<dyn TraceCallback + 'b>>>,
     cycles: SessionCycles,
+    segment_index: usize,
+    interrupt_check: Option<Box<dyn FnMut() -> Result<()> + 'b>>,
 }
 
 impl PendingState {
@@ -175,6 +187,21 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             },
             trace,
             cycles: SessionCycles::default(),
+            segment_index: 0,
+            interrupt_check: None,
+        }
+    }
+
+    /// Call `check` periodically during execution, and stop with its error
+    /// if it fails.
+    ///
+    /// `check` is called before the first instruction and then once every
+    /// [INTERRUPT_CHECK_INTERVAL] instructions, which allows execution to be
+    /// interrupted from another thread.
+    pub fn with_interrupt_check(self, check: impl FnMut() -> Result<()> + 'b) -> Self {
+        Self {
+            interrupt_check: Some(Box::new(check)),
+            ..self
         }
     }
 
@@ -196,7 +223,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         self.reset();
 
         let mut emu = Emulator::new();
-        let mut segments = 0;
+        let mut steps = 0;
         let initial_state = self.pager.image.get_system_state();
 
         loop {
@@ -204,6 +231,13 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                 break;
             }
 
+            if let Some(check) = self.interrupt_check.as_mut() {
+                if steps % INTERRUPT_CHECK_INTERVAL == 0 {
+                    check()?;
+                }
+                steps += 1;
+            }
+
             if let Some(max_cycles) = max_cycles {
                 if self.cycles.user >= max_cycles as usize {
                     bail!("Session limit exceeded");
@@ -241,10 +275,10 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
                     insn_cycles: self.insn_cycles,
                     po2: segment_po2,
                     exit_code: ExitCode::SystemSplit,
-                    index: segments,
+                    index: self.segment_index,
                     output_digest: self.output_digest,
                 })?;
-                segments += 1;
+                self.segment_index += 1;
                 self.cycles.total += 1 << segment_po2;
                 self.pager.clear();
                 self.insn_cycles = 0;
@@ -268,10 +302,10 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
             insn_cycles: self.insn_cycles,
             po2,
             exit_code,
-            index: segments,
+            index: self.segment_index,
             output_digest: self.output_digest,
         })?;
-        segments += 1;
+        self.segment_index += 1;
         self.cycles.total += 1 << po2;
 
         // NOTE: When a segment ends in a Halted(_) state, it may not update the
@@ -290,7 +324,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
         };
 
         Ok(ExecutorResult {
-            segments,
+            segments: self.segment_index,
             exit_code,
             post_image: self.pager.image.clone(),
             user_cycles: self.cycles.user.try_into()?,
@@ -333,6 +367,7 @@ impl<'a, 'b, S: Syscall> Executor<'a, 'b, S> {
 
     fn reset(&mut self) {
         self.pager.clear();
+        self.segment_index = 0;
         self.exit_code = None;
         self.syscalls.clear();
         self.output_digest = None;
@@ -703,6 +738,14 @@ impl<'a, 'b, S: Syscall> SyscallContext for Executor<'a, 'b, S> {
         self.cycles.user
     }
 
+    fn get_pc(&self) -> u32 {
+        self.pc.0
+    }
+
+    fn get_segment_index(&self) -> usize {
+        self.segment_index
+    }
+
     fn peek_register(&mut self, idx: usize) -> Result<u32> {
         if idx >= REG_MAX {
             bail!("invalid register: x{idx}");
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use anyhow::Result;
 use risc0_binfmt::{Digestible, ExitCode, MemoryImage};
@@ -122,3 +122,27 @@ fn system_split() {
         segments[0].post_state.digest::<ShaImpl>()
     );
 }
+
+#[test]
+fn interrupt_check() {
+    let program = testutil::simple_loop();
+    let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+    let syscall = BasicSyscall::default();
+
+    // The loop runs for ~2000 instructions, so the check is called twice.
+    let checks = Cell::new(0);
+    let err = super::Executor::new(image, &syscall, Vec::new())
+        .with_interrupt_check(|| {
+            checks.set(checks.get() + 1);
+            if checks.get() == 2 {
+                anyhow::bail!("interrupted");
+            }
+            Ok(())
+        })
+        .run(DEFAULT_SEGMENT_LIMIT_PO2, DEFAULT_SESSION_LIMIT, |_| Ok(()))
+        .err()
+        .unwrap();
+
+    assert_eq!(err.to_string(), "interrupted");
+    assert_eq!(checks.get(), 2);
+}
@@ -25,6 +25,8 @@ use risc0_zkp::core::digest::Digest;
 pub mod data_structures;
 #[cfg(feature = "prove")]
 pub mod docker;
+#[cfg(feature = "native")]
+pub mod native;
 #[cfg(feature = "prove")]
 mod seal_format;
 #[cfg(feature = "prove")]
@@ -0,0 +1,789 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A native Groth16 prover, for hosts that cannot run the Docker based
+//! prover in [crate::docker].
+//!
+//! The prover consumes the same inputs as the Docker image: the JSON produced
+//! by [crate::to_json], and the proving key of the `stark_verify` circuit in
+//! the `.zkey` format written by snarkjs. The witness is computed by running
+//! the `stark_verify` witness generator that circom compiles alongside the
+//! circuit as an external program, and only the proof is computed in-process.
+//! The witness generator is a C++ program with x86-64 assembly for the field
+//! arithmetic, so this prover requires an x86-64 host that can run it.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Cursor,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInt, BigInteger, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{
+    prepare_verifying_key,
+    r1cs_to_qap::{evaluate_constraint, LibsnarkReduction, R1CSToQAP},
+    Groth16, ProvingKey, VerifyingKey,
+};
+use ark_poly::EvaluationDomain;
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSystemRef, Result as R1CSResult, SynthesisError,
+};
+use tempfile::tempdir;
+
+use crate::{to_json, verifier::verifying_key, Seal, Verifier};
+
+/// Environment variable with the path to the proving key of the
+/// `stark_verify` circuit.
+pub const ZKEY_ENV: &str = "RISC0_GROTH16_ZKEY";
+
+/// Environment variable with the path to the `stark_verify` witness
+/// generator.
+pub const WITNESS_GENERATOR_ENV: &str = "RISC0_GROTH16_WITNESS_GENERATOR";
+
+/// Compact a given seal of an `identity_p254` receipt into a Groth16 `Seal`,
+/// using the proving key and witness generator named by [ZKEY_ENV] and
+/// [WITNESS_GENERATOR_ENV].
+///
+/// The proving key is loaded on the first call and reused by later calls, so
+/// changes to these variables after the first call have no effect.
+pub fn stark_to_snark(identity_p254_seal_bytes: &[u8]) -> Result<Seal> {
+    cached_prover()?.stark_to_snark(identity_p254_seal_bytes)
+}
+
+// Load the prover named by the environment once, since the proving key is
+// several gigabytes.
+fn cached_prover() -> Result<&'static Prover> {
+    static PROVER: OnceLock<Prover> = OnceLock::new();
+    static LOADING: Mutex<()> = Mutex::new(());
+
+    if let Some(prover) = PROVER.get() {
+        return Ok(prover);
+    }
+    let _guard = LOADING.lock().unwrap();
+    if let Some(prover) = PROVER.get() {
+        return Ok(prover);
+    }
+    let prover = Prover::from_env()?;
+    Ok(PROVER.get_or_init(|| prover))
+}
+
+/// A Groth16 prover for the `stark_verify` circuit.
+pub struct Prover {
+    pk: ProvingKey<Bn254>,
+    matrices: ConstraintMatrices<Fr>,
+    witness_generator: PathBuf,
+}
+
+impl Prover {
+    /// Load the proving key from a snarkjs `.zkey` file.
+    ///
+    /// The `witness_generator` is run as an external program, as
+    /// `<witness_generator> input.json output.wtns`, to compute the witness of
+    /// the circuit.
+    ///
+    /// Returns an error if the verifying key in the `.zkey` is not the one
+    /// that [crate::verifier::prepared_verifying_key] uses to verify seals.
+    pub fn new(zkey: impl AsRef<Path>, witness_generator: impl AsRef<Path>) -> Result<Self> {
+        let zkey = zkey.as_ref();
+        tracing::debug!("loading {}", zkey.display());
+        let bytes = std::fs::read(zkey)
+            .with_context(|| format!("failed to read proving key: {}", zkey.display()))?;
+        let (pk, matrices) = read_zkey(&bytes)
+            .with_context(|| format!("failed to parse proving key: {}", zkey.display()))?;
+        ensure!(
+            pk.vk == verifying_key()?,
+            "proving key does not match the RISC Zero Groth16 verifying key: {}",
+            zkey.display()
+        );
+        Ok(Self {
+            pk,
+            matrices,
+            witness_generator: witness_generator.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Load the proving key and witness generator named by [ZKEY_ENV] and
+    /// [WITNESS_GENERATOR_ENV].
+    pub fn from_env() -> Result<Self> {
+        let zkey = std::env::var(ZKEY_ENV).with_context(|| format!("{ZKEY_ENV} is not set"))?;
+        let witness_generator = std::env::var(WITNESS_GENERATOR_ENV)
+            .with_context(|| format!("{WITNESS_GENERATOR_ENV} is not set"))?;
+        Self::new(zkey, witness_generator)
+    }
+
+    /// The verifying key of the circuit.
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.pk.vk
+    }
+
+    /// Compact a given seal of an `identity_p254` receipt into a Groth16
+    /// `Seal`.
+    pub fn stark_to_snark(&self, identity_p254_seal_bytes: &[u8]) -> Result<Seal> {
+        let tmp_dir = tempdir()?;
+        let work_dir = std::env::var("RISC0_WORK_DIR");
+        let work_dir = work_dir.as_ref().map(Path::new).unwrap_or(tmp_dir.path());
+
+        tracing::debug!("seal-to-json");
+        let input_path = work_dir.join("input.json");
+        let witness_path = work_dir.join("output.wtns");
+        let input_json = File::create(&input_path)?;
+        to_json(&mut Cursor::new(identity_p254_seal_bytes), &input_json)?;
+
+        tracing::debug!("witness generation");
+        let status = Command::new(&self.witness_generator)
+            .arg(&input_path)
+            .arg(&witness_path)
+            .status()
+            .with_context(|| {
+                format!(
+                    "failed to run witness generator: {}",
+                    self.witness_generator.display()
+                )
+            })?;
+        if !status.success() {
+            bail!("witness generator returned failure exit code: {status}");
+        }
+        let witness = read_wtns(&std::fs::read(&witness_path)?)?;
+
+        tracing::debug!("prove");
+        self.prove(&witness)
+    }
+
+    /// Prove a full assignment of the circuit, as computed by the witness
+    /// generator, and check that the proof verifies.
+    pub fn prove(&self, witness: &[Fr]) -> Result<Seal> {
+        let num_inputs = self.matrices.num_instance_variables;
+        ensure!(
+            witness.len() == num_inputs + self.matrices.num_witness_variables,
+            "witness has {} signals, expected {}",
+            witness.len(),
+            num_inputs + self.matrices.num_witness_variables
+        );
+        ensure!(witness[0].is_one(), "first witness signal must be one");
+
+        let mut rng = rand::thread_rng();
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &self.pk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            &self.matrices,
+            num_inputs,
+            self.matrices.num_constraints,
+            witness,
+        )
+        .map_err(|err| anyhow!(err))?;
+
+        let seal = Seal {
+            a: vec![to_bytes(proof.a.x), to_bytes(proof.a.y)],
+            b: vec![
+                vec![to_bytes(proof.b.x.c1), to_bytes(proof.b.x.c0)],
+                vec![to_bytes(proof.b.y.c1), to_bytes(proof.b.y.c0)],
+            ],
+            c: vec![to_bytes(proof.c.x), to_bytes(proof.c.y)],
+        };
+        Verifier::new(
+            &seal,
+            witness[1..num_inputs].to_vec(),
+            prepare_verifying_key(&self.pk.vk),
+        )?
+        .verify()
+        .context("Groth16 proof does not verify, the witness does not satisfy the circuit")?;
+        Ok(seal)
+    }
+}
+
+// Encode a base field element in big-endian format
+fn to_bytes(x: Fq) -> Vec<u8> {
+    x.into_bigint().to_bytes_be()
+}
+
+/// The R1CS to QAP reduction used by snarkjs.
+///
+/// Unlike [LibsnarkReduction], snarkjs evaluates the QAP on the odd powers of
+/// the root of unity of twice the domain size, and its `.zkey` contains the
+/// H query for these points.
+pub(crate) struct CircomReduction;
+
+impl R1CSToQAP for CircomReduction {
+    #[allow(clippy::type_complexity)]
+    fn instance_map_with_evaluation<F: PrimeField, D: EvaluationDomain<F>>(
+        cs: ConstraintSystemRef<F>,
+        t: &F,
+    ) -> R1CSResult<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize)> {
+        LibsnarkReduction::instance_map_with_evaluation::<F, D>(cs, t)
+    }
+
+    fn witness_map_from_matrices<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        num_inputs: usize,
+        num_constraints: usize,
+        full_assignment: &[F],
+    ) -> R1CSResult<Vec<F>> {
+        let domain =
+            D::new(num_constraints + num_inputs).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let domain_size = domain.size();
+
+        let mut a = vec![F::zero(); domain_size];
+        let mut b = vec![F::zero(); domain_size];
+        let mut c = vec![F::zero(); domain_size];
+        for (i, (a_i, b_i)) in matrices.a.iter().zip(&matrices.b).enumerate() {
+            a[i] = evaluate_constraint(a_i, full_assignment);
+            b[i] = evaluate_constraint(b_i, full_assignment);
+            c[i] = a[i] * b[i];
+        }
+        a[num_constraints..num_constraints + num_inputs]
+            .clone_from_slice(&full_assignment[..num_inputs]);
+
+        // Shift the evaluations to the odd powers of the root of unity of the
+        // domain of twice the size.
+        let root = D::new(2 * domain_size)
+            .ok_or(SynthesisError::PolynomialDegreeTooLarge)?
+            .element(1);
+        for evals in [&mut a, &mut b, &mut c] {
+            domain.ifft_in_place(evals);
+            D::distribute_powers(evals, root);
+            domain.fft_in_place(evals);
+        }
+
+        let mut ab = domain.mul_polynomials_in_evaluation_domain(&a, &b);
+        for (ab_i, c_i) in ab.iter_mut().zip(c) {
+            *ab_i -= c_i;
+        }
+        Ok(ab)
+    }
+
+    fn h_query_scalars<F: PrimeField, D: EvaluationDomain<F>>(
+        max_power: usize,
+        t: F,
+        _zt: F,
+        delta_inverse: F,
+    ) -> R1CSResult<Vec<F>> {
+        // The Lagrange coefficients at `t` of the domain of twice the size,
+        // of which the odd ones match the points used by the witness map.
+        let domain_size = 2 * (max_power + 1);
+        let domain = D::new(domain_size).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let mut scalars: Vec<F> = std::iter::successors(Some(delta_inverse), |x| Some(*x * t))
+            .take(domain.size())
+            .collect();
+        domain.ifft_in_place(&mut scalars);
+        Ok(scalars.into_iter().skip(1).step_by(2).collect())
+    }
+}
+
+// Read a snarkjs `.zkey` file of a Groth16 circuit.
+//
+// See https://github.com/iden3/snarkjs/blob/master/src/zkey_utils.js
+fn read_zkey(bytes: &[u8]) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>)> {
+    let sections = read_sections(bytes, b"zkey")?;
+    let section = |id: u32| {
+        sections
+            .get(&id)
+            .map(|x| Reader(x))
+            .with_context(|| format!("missing section {id}"))
+    };
+
+    let protocol = section(1)?.u32()?;
+    ensure!(
+        protocol == 1,
+        "unsupported protocol: {protocol}, expected Groth16"
+    );
+
+    let mut header = section(2)?;
+    header.modulus::<Fq>()?;
+    header.modulus::<Fr>()?;
+    let n_vars = header.u32()? as usize;
+    let n_public = header.u32()? as usize;
+    let domain_size = header.u32()? as usize;
+    ensure!(n_vars > n_public, "invalid number of public signals");
+    let alpha_g1 = header.g1()?;
+    let beta_g1 = header.g1()?;
+    let beta_g2 = header.g2()?;
+    let gamma_g2 = header.g2()?;
+    let delta_g1 = header.g1()?;
+    let delta_g2 = header.g2()?;
+
+    let gamma_abc_g1 = section(3)?.g1s(n_public + 1)?;
+    let a_query = section(5)?.g1s(n_vars)?;
+    let b_g1_query = section(6)?.g1s(n_vars)?;
+    let b_g2_query = section(7)?.g2s(n_vars)?;
+    let l_query = section(8)?.g1s(n_vars - n_public - 1)?;
+    let h_query = section(9)?.g1s(domain_size)?;
+
+    // The coefficients of A and B. These include a constraint for each
+    // public signal, which come last and are added by the witness map.
+    let mut coeffs = section(4)?;
+    let mut a = vec![vec![]; domain_size];
+    let mut b = vec![vec![]; domain_size];
+    let mut max_constraint = 0;
+    for _ in 0..coeffs.u32()? {
+        let matrix = coeffs.u32()?;
+        let constraint = coeffs.u32()? as usize;
+        let signal = coeffs.u32()? as usize;
+        // Coefficients are stored in Montgomery form, multiplied by R^2.
+        let value = coeffs.fr()?;
+        let value = Fr::new_unchecked(value.into_bigint());
+        ensure!(
+            constraint < domain_size && signal < n_vars,
+            "coefficient out of range"
+        );
+        max_constraint = max_constraint.max(constraint);
+        match matrix {
+            0 => a[constraint].push((value, signal)),
+            1 => b[constraint].push((value, signal)),
+            _ => bail!("invalid coefficient matrix: {matrix}"),
+        }
+    }
+    ensure!(
+        max_constraint >= n_public,
+        "missing public signal constraints"
+    );
+    let num_constraints = max_constraint - n_public;
+    a.truncate(num_constraints);
+    b.truncate(num_constraints);
+
+    let expected_domain_size =
+        ark_poly::GeneralEvaluationDomain::<Fr>::new(num_constraints + n_public + 1)
+            .map(|domain| domain.size());
+    ensure!(
+        expected_domain_size == Some(domain_size),
+        "unexpected domain size: {domain_size}"
+    );
+
+    let pk = ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            gamma_abc_g1,
+        },
+        beta_g1,
+        delta_g1,
+        a_query,
+        b_g1_query,
+        b_g2_query,
+        h_query,
+        l_query,
+    };
+    let matrices = ConstraintMatrices {
+        num_instance_variables: n_public + 1,
+        num_witness_variables: n_vars - n_public - 1,
+        num_constraints,
+        a_num_non_zero: a.iter().map(Vec::len).sum(),
+        b_num_non_zero: b.iter().map(Vec::len).sum(),
+        c_num_non_zero: 0,
+        a,
+        b,
+        c: vec![],
+    };
+    Ok((pk, matrices))
+}
+
+// Read a `.wtns` file as written by a circom witness generator.
+fn read_wtns(bytes: &[u8]) -> Result<Vec<Fr>> {
+    let sections = read_sections(bytes, b"wtns")?;
+    let mut header = Reader(sections.get(&1).context("missing witness header")?);
+    header.modulus::<Fr>()?;
+    let n_witness = header.u32()?;
+
+    let mut values = Reader(sections.get(&2).context("missing witness values")?);
+    (0..n_witness)
+        .map(|_| Fr::from_bigint(values.bigint()?).context("witness value is not a field element"))
+        .collect()
+}
+
+// Read the sections of a binary file in the iden3 format shared by `.zkey`,
+// `.wtns` and `.r1cs` files.
+fn read_sections<'a>(bytes: &'a [u8], magic: &[u8]) -> Result<HashMap<u32, &'a [u8]>> {
+    let mut reader = Reader(bytes);
+    ensure!(
+        reader.take(4)? == magic,
+        "invalid file type, expected: {}",
+        String::from_utf8_lossy(magic)
+    );
+    let _version = reader.u32()?;
+    let mut sections = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let id = reader.u32()?;
+        let len = reader.u64()?;
+        sections.insert(id, reader.take(len.try_into()?)?);
+    }
+    Ok(sections)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.0.len() >= len, "file is truncated");
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn bigint(&mut self) -> Result<BigInt<4>> {
+        let mut limbs = [0u64; 4];
+        for limb in limbs.iter_mut() {
+            *limb = self.u64()?;
+        }
+        Ok(BigInt(limbs))
+    }
+
+    // Read the size and modulus of a field, which must match `F`.
+    fn modulus<F: PrimeField<BigInt = BigInt<4>>>(&mut self) -> Result<()> {
+        let n8 = self.u32()?;
+        ensure!(n8 == 32, "unsupported field size: {n8}");
+        ensure!(
+            self.bigint()? == F::MODULUS,
+            "unsupported curve, expected BN254"
+        );
+        Ok(())
+    }
+
+    // Read an element of the base field in Montgomery form
+    fn fq(&mut self) -> Result<Fq> {
+        let x = self.bigint()?;
+        ensure!(x < Fq::MODULUS, "field element out of range");
+        Ok(Fq::new_unchecked(x))
+    }
+
+    // Read an element of the scalar field in Montgomery form
+    fn fr(&mut self) -> Result<Fr> {
+        let x = self.bigint()?;
+        ensure!(x < Fr::MODULUS, "field element out of range");
+        Ok(Fr::new_unchecked(x))
+    }
+
+    fn g1(&mut self) -> Result<G1Affine> {
+        let (x, y) = (self.fq()?, self.fq()?);
+        if x.is_zero() && y.is_zero() {
+            return Ok(G1Affine::identity());
+        }
+        let point = G1Affine::new_unchecked(x, y);
+        ensure!(point.is_on_curve(), "G1 point is not on the curve");
+        Ok(point)
+    }
+
+    fn g2(&mut self) -> Result<G2Affine> {
+        let x = Fq2::new(self.fq()?, self.fq()?);
+        let y = Fq2::new(self.fq()?, self.fq()?);
+        if x.is_zero() && y.is_zero() {
+            return Ok(G2Affine::identity());
+        }
+        let point = G2Affine::new_unchecked(x, y);
+        ensure!(point.is_on_curve(), "G2 point is not on the curve");
+        Ok(point)
+    }
+
+    fn g1s(&mut self, len: usize) -> Result<Vec<G1Affine>> {
+        (0..len).map(|_| self.g1()).collect()
+    }
+
+    fn g2s(&mut self, len: usize) -> Result<Vec<G2Affine>> {
+        (0..len).map(|_| self.g2()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::Field;
+    use ark_relations::{
+        lc,
+        r1cs::{ConstraintSynthesizer, ConstraintSystem, OptimizationGoal},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // Proves knowledge of `x` such that `x^3 + x + 5 == out`.
+    #[derive(Clone)]
+    struct Cubic {
+        x: Fr,
+    }
+
+    impl ConstraintSynthesizer<Fr> for Cubic {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> R1CSResult<()> {
+            let x_val = self.x;
+            let out = cs.new_input_variable(|| Ok(x_val * x_val * x_val + x_val + Fr::from(5)))?;
+            let x = cs.new_witness_variable(|| Ok(x_val))?;
+            let x2 = cs.new_witness_variable(|| Ok(x_val * x_val))?;
+            let x3 = cs.new_witness_variable(|| Ok(x_val * x_val * x_val))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x2)?;
+            cs.enforce_constraint(lc!() + x2, lc!() + x, lc!() + x3)?;
+            cs.enforce_constraint(
+                lc!() + x3 + x + (Fr::from(5), ConstraintSystem::<Fr>::one()),
+                lc!() + ConstraintSystem::<Fr>::one(),
+                lc!() + out,
+            )?;
+            Ok(())
+        }
+    }
+
+    fn put_bigint(out: &mut Vec<u8>, x: BigInt<4>) {
+        x.0.iter().for_each(|limb| out.extend(limb.to_le_bytes()));
+    }
+
+    fn put_g1(out: &mut Vec<u8>, point: &G1Affine) {
+        let (x, y) = if point.infinity {
+            (Fq::zero(), Fq::zero())
+        } else {
+            (point.x, point.y)
+        };
+        put_bigint(out, x.0);
+        put_bigint(out, y.0);
+    }
+
+    fn put_g2(out: &mut Vec<u8>, point: &G2Affine) {
+        let (x, y) = if point.infinity {
+            (Fq2::zero(), Fq2::zero())
+        } else {
+            (point.x, point.y)
+        };
+        for x in [x.c0, x.c1, y.c0, y.c1] {
+            put_bigint(out, x.0);
+        }
+    }
+
+    fn put_modulus<F: PrimeField<BigInt = BigInt<4>>>(out: &mut Vec<u8>) {
+        out.extend(32u32.to_le_bytes());
+        put_bigint(out, F::MODULUS);
+    }
+
+    fn write_file(magic: &[u8], sections: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut out = magic.to_vec();
+        out.extend(1u32.to_le_bytes());
+        out.extend((sections.len() as u32).to_le_bytes());
+        for (id, section) in sections.into_iter().enumerate() {
+            out.extend((id as u32 + 1).to_le_bytes());
+            out.extend((section.len() as u64).to_le_bytes());
+            out.extend(section);
+        }
+        out
+    }
+
+    // Write a proving key in the format of snarkjs.
+    fn write_zkey(pk: &ProvingKey<Bn254>, matrices: &ConstraintMatrices<Fr>) -> Vec<u8> {
+        let n_public = matrices.num_instance_variables - 1;
+        let n_vars = matrices.num_instance_variables + matrices.num_witness_variables;
+
+        let mut header = vec![];
+        put_modulus::<Fq>(&mut header);
+        put_modulus::<Fr>(&mut header);
+        header.extend((n_vars as u32).to_le_bytes());
+        header.extend((n_public as u32).to_le_bytes());
+        header.extend((pk.h_query.len() as u32).to_le_bytes());
+        put_g1(&mut header, &pk.vk.alpha_g1);
+        put_g1(&mut header, &pk.beta_g1);
+        put_g2(&mut header, &pk.vk.beta_g2);
+        put_g2(&mut header, &pk.vk.gamma_g2);
+        put_g1(&mut header, &pk.delta_g1);
+        put_g2(&mut header, &pk.vk.delta_g2);
+
+        let r2 = Fr::from_bigint(Fr::R).unwrap().square();
+        let public_constraints = (0..=n_public).map(|signal| {
+            (
+                0,
+                matrices.num_constraints + signal,
+                vec![(Fr::one(), signal)],
+            )
+        });
+        let constraints = [(0, &matrices.a), (1, &matrices.b)]
+            .into_iter()
+            .flat_map(|(id, matrix)| {
+                matrix
+                    .iter()
+                    .enumerate()
+                    .map(move |(constraint, terms)| (id, constraint, terms.clone()))
+            })
+            .chain(public_constraints);
+        let mut coeffs = vec![];
+        let mut count = 0u32;
+        for (matrix, constraint, terms) in constraints {
+            for (value, signal) in terms {
+                coeffs.extend((matrix as u32).to_le_bytes());
+                coeffs.extend((constraint as u32).to_le_bytes());
+                coeffs.extend((signal as u32).to_le_bytes());
+                put_bigint(&mut coeffs, (value * r2).into_bigint());
+                count += 1;
+            }
+        }
+        coeffs.splice(0..0, count.to_le_bytes());
+
+        let g1s = |points: &[G1Affine]| {
+            let mut out = vec![];
+            points.iter().for_each(|point| put_g1(&mut out, point));
+            out
+        };
+        let mut b_g2 = vec![];
+        pk.b_g2_query
+            .iter()
+            .for_each(|point| put_g2(&mut b_g2, point));
+
+        write_file(
+            b"zkey",
+            vec![
+                1u32.to_le_bytes().to_vec(),
+                header,
+                g1s(&pk.vk.gamma_abc_g1),
+                coeffs,
+                g1s(&pk.a_query),
+                g1s(&pk.b_g1_query),
+                b_g2,
+                g1s(&pk.l_query),
+                g1s(&pk.h_query),
+            ],
+        )
+    }
+
+    fn write_wtns(witness: &[Fr]) -> Vec<u8> {
+        let mut header = vec![];
+        put_modulus::<Fr>(&mut header);
+        header.extend((witness.len() as u32).to_le_bytes());
+        let mut values = vec![];
+        witness
+            .iter()
+            .for_each(|x| put_bigint(&mut values, x.into_bigint()));
+        write_file(b"wtns", vec![header, values])
+    }
+
+    // Has the same public inputs as the `stark_verify` circuit, each equal to a
+    // witness signal.
+    #[derive(Clone)]
+    struct PublicInputs;
+
+    impl ConstraintSynthesizer<Fr> for PublicInputs {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> R1CSResult<()> {
+            for i in 0..4u64 {
+                let value = Fr::from(i);
+                let input = cs.new_input_variable(|| Ok(value))?;
+                let x = cs.new_witness_variable(|| Ok(value))?;
+                cs.enforce_constraint(
+                    lc!() + x,
+                    lc!() + ConstraintSystem::<Fr>::one(),
+                    lc!() + input,
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    fn synthesize(circuit: impl ConstraintSynthesizer<Fr>) -> (ConstraintMatrices<Fr>, Vec<Fr>) {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.finalize();
+        let matrices = cs.to_matrices().unwrap();
+        let cs = cs.borrow().unwrap();
+        let witness = [
+            cs.instance_assignment.as_slice(),
+            cs.witness_assignment.as_slice(),
+        ]
+        .concat();
+        (matrices, witness)
+    }
+
+    #[test]
+    fn prove() {
+        let circuit = Cubic { x: Fr::from(3) };
+        let mut rng = StdRng::seed_from_u64(0);
+        let pk = Groth16::<Bn254, CircomReduction>::generate_random_parameters_with_reduction(
+            circuit.clone(),
+            &mut rng,
+        )
+        .unwrap();
+        let (matrices, witness) = synthesize(circuit);
+        assert_eq!(witness[1], Fr::from(35));
+
+        let (zkey_pk, zkey_matrices) = read_zkey(&write_zkey(&pk, &matrices)).unwrap();
+        assert_eq!(zkey_pk, pk);
+        assert_eq!(zkey_matrices.num_constraints, matrices.num_constraints);
+        assert_eq!(zkey_matrices.a, matrices.a);
+        assert_eq!(zkey_matrices.b, matrices.b);
+        assert_eq!(read_wtns(&write_wtns(&witness)).unwrap(), witness);
+
+        let prover = Prover {
+            pk: zkey_pk,
+            matrices: zkey_matrices,
+            witness_generator: PathBuf::new(),
+        };
+        let seal = prover.prove(&witness).unwrap();
+        Verifier::new(
+            &Seal::from_vec(&seal.to_vec()).unwrap(),
+            vec![Fr::from(35)],
+            prepare_verifying_key(prover.verifying_key()),
+        )
+        .unwrap()
+        .verify()
+        .unwrap();
+
+        let mut bad_witness = witness.clone();
+        bad_witness[1] = Fr::from(36);
+        let err = prover.prove(&bad_witness).unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{err}");
+    }
+
+    #[test]
+    fn verifying_key_mismatch() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let tmp_dir = tempdir().unwrap();
+        let zkey_path = tmp_dir.path().join("stark_verify.zkey");
+
+        // A key with the verifying key used by the RISC Zero verifier loads.
+        let circuit = PublicInputs;
+        let mut pk = Groth16::<Bn254, CircomReduction>::generate_random_parameters_with_reduction(
+            circuit.clone(),
+            &mut rng,
+        )
+        .unwrap();
+        pk.vk = verifying_key().unwrap();
+        let (matrices, _) = synthesize(circuit);
+        std::fs::write(&zkey_path, write_zkey(&pk, &matrices)).unwrap();
+        let prover = Prover::new(&zkey_path, "stark_verify").unwrap();
+        assert_eq!(prover.verifying_key(), &verifying_key().unwrap());
+
+        // Any other key is rejected.
+        let circuit = Cubic { x: Fr::from(3) };
+        let pk = Groth16::<Bn254, CircomReduction>::generate_random_parameters_with_reduction(
+            circuit.clone(),
+            &mut rng,
+        )
+        .unwrap();
+        let (matrices, _) = synthesize(circuit);
+        std::fs::write(&zkey_path, write_zkey(&pk, &matrices)).unwrap();
+        let err = Prover::new(&zkey_path, "stark_verify").err().unwrap();
+        assert!(err.to_string().contains("does not match"), "{err}");
+    }
+
+    // Requires the proving key of the `stark_verify` circuit, named by
+    // [ZKEY_ENV] and [WITNESS_GENERATOR_ENV].
+    #[test]
+    #[ignore]
+    fn stark_verify_zkey() {
+        let prover = Prover::from_env().unwrap();
+        assert_eq!(prover.verifying_key(), &verifying_key().unwrap());
+    }
+}
@@ -145,6 +145,11 @@ impl Verifier {
 
 /// Computes the default prepared verifying key, used by Bonsai.
 pub fn prepared_verifying_key() -> Result<PreparedVerifyingKey<Bn254>, Error> {
+    Ok(prepare_verifying_key(&verifying_key()?))
+}
+
+/// Computes the default verifying key, used by Bonsai.
+pub fn verifying_key() -> Result<VerifyingKey<Bn254>, Error> {
     let alpha_g1 = g1_from_bytes(&[from_u256(ALPHA_X)?, from_u256(ALPHA_Y)?])?;
     let beta_g2 = g2_from_bytes(&vec![
         vec![from_u256(BETA_X1)?, from_u256(BETA_X2)?],
@@ -166,13 +171,11 @@ pub fn prepared_verifying_key() -> Result<PreparedVerifyingKey<Bn254>, Error> {
     let ic4 = g1_from_bytes(&[from_u256(IC4_X)?, from_u256(IC4_Y)?])?;
     let gamma_abc_g1 = vec![ic0, ic1, ic2, ic3, ic4];
 
-    let vk = VerifyingKey::<Bn254> {
+    Ok(VerifyingKey::<Bn254> {
         alpha_g1,
         beta_g2,
         gamma_g2,
         delta_g2,
         gamma_abc_g1,
-    };
-
-    Ok(prepare_verifying_key(&vk))
+    })
 }
@@ -0,0 +1,64 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Output for `r0vm --execute-only`, which runs a guest without proving it.
+
+use std::{fs, path::Path};
+
+use risc0_zkvm::{Container, ExitCode, Session};
+use serde::Serialize;
+
+/// A summary of an executed [Session], written as JSON.
+#[derive(Serialize)]
+struct SessionSummary {
+    exit_code: ExitCode,
+    /// The journal as a hex string, or `None` if the session has no journal.
+    journal: Option<String>,
+    user_cycles: u64,
+    total_cycles: u64,
+    segments: usize,
+}
+
+/// Write a JSON summary of the session to `summary_out`, or to stdout if not specified.
+pub(crate) fn write_summary(session: &Session, summary_out: Option<&Path>) {
+    let summary = SessionSummary {
+        exit_code: session.exit_code,
+        journal: session.journal.as_ref().map(|x| hex::encode(&x.bytes)),
+        user_cycles: session.user_cycles,
+        total_cycles: session.total_cycles,
+        segments: session.segments.len(),
+    };
+    let json = serde_json::to_string_pretty(&summary).unwrap();
+    match summary_out {
+        Some(path) => fs::write(path, json).expect("Unable to write summary file"),
+        None => println!("{json}"),
+    }
+}
+
+/// Write the journal and each serialized segment of the session to `dir`.
+///
+/// The journal is written to `journal.bin` and each segment is written in the
+/// [risc0_zkvm::Container] format to `segment-<index>.bin`.
+pub(crate) fn dump_session(session: &Session, dir: &Path) {
+    fs::create_dir_all(dir).expect("Unable to create dump directory");
+    if let Some(journal) = session.journal.as_ref() {
+        fs::write(dir.join("journal.bin"), &journal.bytes).expect("Unable to write journal");
+    }
+    for segment in session.segments.iter() {
+        let segment = segment.resolve().unwrap();
+        let data = segment.to_container_bytes().unwrap();
+        fs::write(dir.join(format!("segment-{}.bin", segment.index)), data)
+            .expect("Unable to write segment");
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `r0vm inspect` subcommand, which prints the contents of a receipt.
+
+use std::{fmt::Write, fs, path::PathBuf};
+
+use clap::Args;
+use hex::FromHex;
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    Container, InnerReceipt, MaybePruned, Receipt, ReceiptClaim, SystemState,
+};
+use serde::Serialize;
+
+#[derive(Args)]
+pub(crate) struct InspectArgs {
+    /// The receipt file to inspect, as written by `r0vm --receipt`.
+    receipt: PathBuf,
+
+    /// Print the receipt as JSON instead of human-readable text.
+    #[arg(long)]
+    json: bool,
+
+    /// Verify the receipt against this image ID, given as a hex string.
+    #[arg(long)]
+    image_id: Option<String>,
+}
+
+/// A summary of a receipt, suitable for printing.
+#[derive(Serialize)]
+struct ReceiptSummary {
+    /// One of `composite`, `succinct`, `compact`, or `fake`.
+    kind: &'static str,
+    /// Number of segment receipts, for composite receipts.
+    segments: Option<usize>,
+    /// Hash function used by the segment receipts, for composite receipts.
+    hashfn: Option<String>,
+    /// Total size of the seal in bytes.
+    seal_size: usize,
+    /// The claim, if it could be decoded from the receipt.
+    claim: Option<ClaimSummary>,
+    /// The error encountered while decoding the claim.
+    claim_error: Option<String>,
+    journal: JournalSummary,
+    verification: Option<VerificationSummary>,
+}
+
+#[derive(Serialize)]
+struct ClaimSummary {
+    digest: String,
+    pre: Field<StateSummary>,
+    post: Field<StateSummary>,
+    exit_code: String,
+    input: String,
+    /// The output, or `None` if the execution produced no output.
+    output: Field<Option<OutputSummary>>,
+}
+
+#[derive(Serialize)]
+struct StateSummary {
+    pc: u32,
+    merkle_root: String,
+}
+
+#[derive(Serialize)]
+struct OutputSummary {
+    /// The length of the journal committed to by the claim.
+    journal: Field<usize>,
+    /// The digests of the claims assumed by this execution.
+    assumptions: Field<Vec<Field<String>>>,
+}
+
+#[derive(Serialize)]
+struct JournalSummary {
+    len: usize,
+    hex: String,
+}
+
+#[derive(Serialize)]
+struct VerificationSummary {
+    image_id: String,
+    error: Option<String>,
+}
+
+/// A [MaybePruned] claim field, which is either available or represented only by its digest.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Field<T> {
+    Value(T),
+    Pruned(String),
+}
+
+impl<T> Field<T> {
+    fn new<U: Clone + Serialize>(field: &MaybePruned<U>, f: impl FnOnce(&U) -> T) -> Self {
+        match field {
+            MaybePruned::Value(value) => Self::Value(f(value)),
+            MaybePruned::Pruned(digest) => Self::Pruned(digest.to_string()),
+        }
+    }
+
+    fn to_text(&self, f: impl FnOnce(&T) -> String) -> String {
+        match self {
+            Field::Value(value) => f(value),
+            Field::Pruned(digest) => format!("pruned {digest}"),
+        }
+    }
+}
+
+pub(crate) fn run(args: &InspectArgs) {
+    let data = fs::read(&args.receipt).expect("Unable to read receipt file");
+    let receipt = Receipt::from_container_bytes(&data).expect("Unable to decode receipt");
+
+    let verification = args.image_id.as_ref().map(|image_id| {
+        let image_id = Digest::from_hex(image_id).expect("Image ID must be a 32-byte hex string");
+        VerificationSummary {
+            image_id: image_id.to_string(),
+            error: receipt.verify(image_id).err().map(|err| err.to_string()),
+        }
+    });
+
+    let summary = ReceiptSummary::new(&receipt, verification);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+    } else {
+        print!("{}", summary.to_text());
+    }
+
+    if summary.verification.is_some_and(|x| x.error.is_some()) {
+        std::process::exit(1);
+    }
+}
+
+impl ReceiptSummary {
+    fn new(receipt: &Receipt, verification: Option<VerificationSummary>) -> Self {
+        let (kind, segments, hashfn, seal_size) = match &receipt.inner {
+            InnerReceipt::Composite(inner) => (
+                "composite",
+                Some(inner.segments.len()),
+                inner.segments.first().map(|x| x.hashfn.clone()),
+                inner.segments.iter().map(|x| x.seal.len() * 4).sum(),
+            ),
+            InnerReceipt::Succinct(inner) => ("succinct", None, None, inner.seal.len() * 4),
+            InnerReceipt::Compact(inner) => ("compact", None, None, inner.seal.len()),
+            InnerReceipt::Fake { .. } => ("fake", None, None, 0),
+        };
+        let (claim, claim_error) = match receipt.get_claim() {
+            Ok(claim) => (Some(ClaimSummary::new(&claim)), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            kind,
+            segments,
+            hashfn,
+            seal_size,
+            claim,
+            claim_error,
+            journal: JournalSummary {
+                len: receipt.journal.bytes.len(),
+                hex: hex::encode(&receipt.journal.bytes),
+            },
+            verification,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "kind:          {}", self.kind).unwrap();
+        if let Some(segments) = self.segments {
+            let hashfn = self.hashfn.as_deref().unwrap_or("none");
+            writeln!(out, "segments:      {segments} ({hashfn})").unwrap();
+        }
+        writeln!(out, "seal size:     {} bytes", self.seal_size).unwrap();
+
+        if let Some(claim) = &self.claim {
+            claim.write_text(&mut out);
+        }
+        if let Some(err) = &self.claim_error {
+            writeln!(out, "claim:         invalid: {err}").unwrap();
+        }
+
+        writeln!(out, "journal:       {} bytes", self.journal.len).unwrap();
+        if !self.journal.hex.is_empty() {
+            writeln!(out, "  hex:         {}", self.journal.hex).unwrap();
+        }
+
+        if let Some(verification) = &self.verification {
+            let result = match &verification.error {
+                None => "ok".to_string(),
+                Some(err) => format!("failed: {err}"),
+            };
+            writeln!(out, "image id:      {}", verification.image_id).unwrap();
+            writeln!(out, "verification:  {result}").unwrap();
+        }
+        out
+    }
+}
+
+impl ClaimSummary {
+    fn new(claim: &ReceiptClaim) -> Self {
+        Self {
+            digest: claim.digest().to_string(),
+            pre: Field::new(&claim.pre, StateSummary::new),
+            post: Field::new(&claim.post, StateSummary::new),
+            exit_code: format!("{:?}", claim.exit_code),
+            input: claim.input.to_string(),
+            output: Field::new(&claim.output, |output| {
+                output.as_ref().map(|output| OutputSummary {
+                    journal: Field::new(&output.journal, Vec::len),
+                    assumptions: Field::new(&output.assumptions, |assumptions| {
+                        assumptions
+                            .0
+                            .iter()
+                            .map(|x| Field::new(x, |claim| claim.digest().to_string()))
+                            .collect()
+                    }),
+                })
+            }),
+        }
+    }
+
+    fn write_text(&self, out: &mut String) {
+        writeln!(out, "claim:         {}", self.digest).unwrap();
+        writeln!(
+            out,
+            "  pre:         {}",
+            self.pre.to_text(StateSummary::to_text)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  post:        {}",
+            self.post.to_text(StateSummary::to_text)
+        )
+        .unwrap();
+        writeln!(out, "  exit code:   {}", self.exit_code).unwrap();
+        writeln!(out, "  input:       {}", self.input).unwrap();
+        match &self.output {
+            Field::Value(None) => writeln!(out, "  output:      none").unwrap(),
+            Field::Pruned(digest) => writeln!(out, "  output:      pruned {digest}").unwrap(),
+            Field::Value(Some(output)) => {
+                writeln!(out, "  output:").unwrap();
+                let journal = output.journal.to_text(|len| format!("{len} bytes"));
+                writeln!(out, "    journal:     {journal}").unwrap();
+                match &output.assumptions {
+                    Field::Pruned(digest) => {
+                        writeln!(out, "    assumptions: pruned {digest}").unwrap()
+                    }
+                    Field::Value(assumptions) => {
+                        writeln!(out, "    assumptions: {}", assumptions.len()).unwrap();
+                        for assumption in assumptions {
+                            let text = assumption.to_text(|digest| digest.clone());
+                            writeln!(out, "      - {text}").unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl StateSummary {
+    fn new(state: &SystemState) -> Self {
+        Self {
+            pc: state.pc,
+            merkle_root: state.merkle_root.to_string(),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        format!("pc = {:#010x}, merkle root = {}", self.pc, self.merkle_root)
+    }
+}
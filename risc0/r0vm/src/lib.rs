@@ -12,18 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod execute;
+mod inspect;
+
 use std::{fs, io, path::PathBuf, rc::Rc};
 
-use clap::{Args, Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use risc0_zkvm::{
-    get_prover_server, ApiServer, ExecutorEnv, ExecutorImpl, ProverOpts, ProverServer,
-    VerifierContext,
+    get_prover_server, ApiDaemon, ApiServer, Container, DaemonOpts, ExecutorEnv, ExecutorImpl,
+    ProverOpts, ProverServer, VerifierContext,
 };
 
 /// Runs a RISC-V ELF binary within the RISC Zero ZKVM.
 #[derive(Parser)]
-#[command(about, version, author)]
+#[command(
+    about,
+    version,
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[command(flatten)]
     mode: Mode,
 
@@ -31,6 +43,10 @@ struct Cli {
     #[arg(long)]
     receipt: Option<PathBuf>,
 
+    /// The encoding of the receipt output file.
+    #[arg(long, value_enum, requires = "receipt", default_value_t = ReceiptFormat::Bincode)]
+    receipt_format: ReceiptFormat,
+
     /// The hash function to use to produce a proof.
     #[arg(long, value_enum, default_value_t = HashFn::Poseidon2)]
     hashfn: HashFn,
@@ -63,6 +79,39 @@ struct Cli {
     /// to read it.
     #[arg(long, env = "RISC0_PPROF_OUT")]
     pprof_out: Option<PathBuf>,
+
+    /// Execute the guest without proving it, and write a JSON summary of the
+    /// session including its exit code, journal, cycle counts, and number of
+    /// segments.
+    #[arg(long, conflicts_with_all = ["receipt", "port", "listen"])]
+    execute_only: bool,
+
+    /// File to write the `--execute-only` summary to.
+    ///
+    /// Writes the summary to stdout if a summary file is not provided.
+    #[arg(long, requires = "execute_only")]
+    summary: Option<PathBuf>,
+
+    /// Directory to write the journal and the serialized segments of the
+    /// session to when using `--execute-only`.
+    #[arg(long, requires = "execute_only")]
+    dump_dir: Option<PathBuf>,
+
+    /// The maximum number of clients connected at once when using `--listen`.
+    #[arg(long, requires = "listen", default_value_t = DaemonOpts::default().max_connections)]
+    max_connections: usize,
+
+    /// The number of proving requests served at once when using `--listen`.
+    ///
+    /// Further requests are queued until a worker is free.
+    #[arg(long, requires = "listen", default_value_t = DaemonOpts::default().workers)]
+    workers: usize,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the claim, journal, and seal kind of a receipt, and optionally verify it.
+    Inspect(inspect::InspectArgs),
 }
 
 #[derive(Args)]
@@ -71,6 +120,11 @@ struct Mode {
     #[arg(long)]
     port: Option<u16>,
 
+    /// Run as a daemon serving many clients, listening on a TCP address such
+    /// as `127.0.0.1:9000`, or on a Unix domain socket given as `unix:<path>`.
+    #[arg(long)]
+    listen: Option<String>,
+
     /// The ELF to execute
     #[arg(long)]
     elf: Option<PathBuf>,
@@ -80,6 +134,14 @@ struct Mode {
     image: Option<PathBuf>,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ReceiptFormat {
+    /// The raw bincode encoding of the receipt.
+    Bincode,
+    /// The versioned [risc0_zkvm::Container] format.
+    Container,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum HashFn {
     #[value(name = "sha-256")]
@@ -94,11 +156,21 @@ pub fn main() {
         .init();
 
     let args = Cli::parse();
+    if let Some(Command::Inspect(ref inspect_args)) = args.command {
+        inspect::run(inspect_args);
+        return;
+    }
+
     if let Some(port) = args.mode.port {
         run_server(port);
         return;
     }
 
+    if let Some(ref addr) = args.mode.listen {
+        run_daemon(addr, args.daemon_opts());
+        return;
+    }
+
     let env = {
         let mut builder = ExecutorEnv::builder();
 
@@ -136,11 +208,22 @@ pub fn main() {
         exec.run().unwrap()
     };
 
+    if args.execute_only {
+        if let Some(dump_dir) = args.dump_dir.as_ref() {
+            execute::dump_session(&session, dump_dir);
+        }
+        execute::write_summary(&session, args.summary.as_deref());
+        return;
+    }
+
     let prover = args.get_prover();
     let ctx = VerifierContext::default();
     let receipt = prover.prove_session(&ctx, &session).unwrap();
 
-    let receipt_data = bincode::serialize(&receipt).unwrap();
+    let receipt_data = match args.receipt_format {
+        ReceiptFormat::Bincode => bincode::serialize(&receipt).unwrap(),
+        ReceiptFormat::Container => receipt.to_container_bytes().unwrap(),
+    };
     let receipt_bytes = bytemuck::cast_slice(&receipt_data);
     if let Some(receipt_file) = args.receipt.as_ref() {
         fs::write(receipt_file, receipt_bytes).expect("Unable to write receipt file");
@@ -163,10 +246,18 @@ impl Cli {
         let opts = ProverOpts {
             hashfn: hashfn.to_string(),
             prove_guest_errors: self.prove_guest_errors,
+            ..Default::default()
         };
 
         get_prover_server(&opts).unwrap()
     }
+
+    fn daemon_opts(&self) -> DaemonOpts {
+        DaemonOpts {
+            max_connections: self.max_connections,
+            workers: self.workers,
+        }
+    }
 }
 
 fn run_server(port: u16) {
@@ -174,3 +265,14 @@ fn run_server(port: u16) {
     let server = ApiServer::new_tcp(addr);
     server.run().unwrap()
 }
+
+fn run_daemon(addr: &str, opts: DaemonOpts) {
+    let daemon = match addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        Some(path) => ApiDaemon::bind_unix(path, opts),
+        #[cfg(not(unix))]
+        Some(_) => panic!("Unix domain sockets are not supported on this platform"),
+        None => ApiDaemon::bind_tcp(addr, opts),
+    };
+    daemon.unwrap().run().unwrap()
+}
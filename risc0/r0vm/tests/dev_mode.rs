@@ -60,6 +60,50 @@ fn dev_mode_verify_fail() {
     });
 }
 
+#[test]
+#[cfg(not(feature = "disable-dev-mode"))]
+fn dev_mode_inspect() {
+    let temp = TempDir::new().unwrap();
+    let receipt_file = temp.child("receipt.dat");
+    let receipt = run_dev_mode();
+    std::fs::write(&receipt_file, bincode::serialize(&receipt).unwrap()).unwrap();
+
+    let image_id = risc0_zkvm::sha::Digest::from(risc0_zkvm_methods::MULTI_TEST_ID).to_string();
+    let output = Command::cargo_bin("r0vm")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("inspect")
+        .arg("--json")
+        .arg("--image-id")
+        .arg(&image_id)
+        .arg(&*receipt_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["kind"], "fake");
+    assert_eq!(summary["claim"]["exit_code"], "Halted(0)");
+    assert_eq!(summary["verification"]["image_id"], image_id);
+    assert!(summary["verification"]["error"].is_null());
+
+    let mut wrong_image_id = image_id.into_bytes();
+    wrong_image_id[0] = if wrong_image_id[0] == b'0' {
+        b'1'
+    } else {
+        b'0'
+    };
+    Command::cargo_bin("r0vm")
+        .unwrap()
+        .env("RISC0_DEV_MODE", "1")
+        .arg("inspect")
+        .arg("--image-id")
+        .arg(String::from_utf8(wrong_image_id).unwrap())
+        .arg(&*receipt_file)
+        .assert()
+        .failure();
+}
+
 #[test]
 #[should_panic(
     expected = "zkVM: Inconsistent settings -- please resolve. The RISC0_DEV_MODE environment variable is set but dev mode has been disabled by feature flag."
@@ -0,0 +1,68 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use assert_cmd::Command;
+use assert_fs::{fixture::PathChild, TempDir};
+use risc0_zkvm::{serde::to_vec, Container, Segment};
+use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_PATH};
+
+#[test]
+fn execute_only() {
+    let temp = TempDir::new().unwrap();
+    let summary_file = temp.child("summary.json");
+    let dump_dir = temp.child("dump");
+    let input = to_vec(&MultiTestSpec::DoNothing).unwrap();
+
+    let mut cmd = Command::cargo_bin("r0vm").unwrap();
+    cmd.arg("--elf")
+        .arg(MULTI_TEST_PATH)
+        .arg("--execute-only")
+        .arg("--summary")
+        .arg(&*summary_file)
+        .arg("--dump-dir")
+        .arg(&*dump_dir)
+        .write_stdin(bytemuck::cast_slice(&input));
+    cmd.assert().success();
+
+    let summary: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&summary_file).unwrap()).unwrap();
+    assert_eq!(summary["exit_code"], serde_json::json!({ "Halted": 0 }));
+    assert_eq!(summary["journal"], "");
+    assert!(summary["user_cycles"].as_u64().unwrap() > 0);
+    assert!(summary["total_cycles"].as_u64().unwrap() >= summary["user_cycles"].as_u64().unwrap());
+
+    let segments = summary["segments"].as_u64().unwrap();
+    assert!(segments > 0);
+    for index in 0..segments {
+        let data = std::fs::read(dump_dir.child(format!("segment-{index}.bin"))).unwrap();
+        let segment = Segment::from_container_bytes(&data).unwrap();
+        assert_eq!(segment.index as u64, index);
+    }
+    assert!(std::fs::read(dump_dir.child("journal.bin"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn execute_only_conflicts_with_receipt() {
+    Command::cargo_bin("r0vm")
+        .unwrap()
+        .arg("--elf")
+        .arg(MULTI_TEST_PATH)
+        .arg("--execute-only")
+        .arg("--receipt")
+        .arg("receipt.dat")
+        .assert()
+        .failure();
+}
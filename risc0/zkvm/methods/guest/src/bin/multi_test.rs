@@ -230,6 +230,16 @@ fn main() {
             }
             env::commit_slice(&result);
         }
+        MultiTestSpec::BigIntModulus { x, y, exp, modulus } => {
+            let p = bigint::Modulus::new(modulus);
+            env::commit(&(
+                p.add(&x, &y),
+                p.sub(&x, &y),
+                p.mul(&x, &y),
+                p.pow(&x, &exp),
+                p.inv(&x),
+            ));
+        }
         MultiTestSpec::LibM => {
             use core::hint::black_box;
             let f = black_box(1.0_f32);
@@ -76,6 +76,14 @@ pub enum MultiTestSpec {
         y: [u32; bigint::WIDTH_WORDS],
         modulus: [u32; bigint::WIDTH_WORDS],
     },
+    /// Run the [bigint::Modulus] operations on a 384-bit modulus and commit
+    /// the results of add, sub, mul, pow and inv, in that order.
+    BigIntModulus {
+        x: [u32; bigint::WIDTH_384_WORDS],
+        y: [u32; bigint::WIDTH_384_WORDS],
+        exp: Vec<u32>,
+        modulus: [u32; bigint::WIDTH_384_WORDS],
+    },
     BusyLoop {
         /// Busy loop until the guest has run for at least this number of cycles
         cycles: u32,
@@ -81,18 +81,7 @@ pub const MAX_BUF_BYTES: usize = 4 * 1024;
 pub const MAX_BUF_WORDS: usize = MAX_BUF_BYTES / WORD_SIZE;
 pub const MAX_SHA_COMPRESS_BLOCKS: usize = 1000;
 
-pub mod bigint {
-    pub const OP_MULTIPLY: u32 = 0;
-
-    /// BigInt width, in bits, handled by the BigInt accelerator circuit.
-    pub const WIDTH_BITS: usize = 256;
-
-    /// BigInt width, in bytes, handled by the BigInt accelerator circuit.
-    pub const WIDTH_BYTES: usize = WIDTH_BITS / 8;
-
-    /// BigInt width, in words, handled by the BigInt accelerator circuit.
-    pub const WIDTH_WORDS: usize = WIDTH_BYTES / crate::WORD_SIZE;
-}
+pub mod bigint;
 
 // TODO: We can probably use ffi::CStr::from_bytes_with_nul once it's
 // const-stablized instead of rolling our own structure:
@@ -0,0 +1,480 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Big integer arithmetic built on the BigInt accelerator.
+//!
+//! The accelerator computes a single 256-bit modular multiplication per call
+//! ([OP_MULTIPLY]). The helpers in this module extend it to other operations
+//! and to wider moduli. Integers are represented as little-endian arrays of
+//! 32-bit words, and every operation expects its inputs to be reduced, i.e.
+//! less than the modulus.
+//!
+//! * [add_mod] and [sub_mod] work with moduli of any width. They only need
+//!   additions, so they do not call the accelerator.
+//! * [Modulus] performs Montgomery multiplication and exponentiation for odd
+//!   moduli of up to [MAX_WIDTH_WORDS] words, such as the 384-bit BLS12-381
+//!   base field or 2048-bit RSA moduli, and inversion for prime moduli. Each
+//!   128-bit by 128-bit limb product is computed with one call to the
+//!   accelerator.
+//!
+//! Outside of the zkVM, the limb products are computed in software, so that
+//! the same code can be used and tested on the host.
+
+pub const OP_MULTIPLY: u32 = 0;
+
+/// BigInt width, in bits, handled by the BigInt accelerator circuit.
+pub const WIDTH_BITS: usize = 256;
+
+/// BigInt width, in bytes, handled by the BigInt accelerator circuit.
+pub const WIDTH_BYTES: usize = WIDTH_BITS / 8;
+
+/// BigInt width, in words, handled by the BigInt accelerator circuit.
+pub const WIDTH_WORDS: usize = WIDTH_BYTES / crate::WORD_SIZE;
+
+/// Width, in words, of a 384-bit integer.
+pub const WIDTH_384_WORDS: usize = 384 / 32;
+
+/// Width, in words, of a 2048-bit integer.
+pub const WIDTH_2048_WORDS: usize = 2048 / 32;
+
+/// Maximum width, in words, of a [Modulus].
+pub const MAX_WIDTH_WORDS: usize = WIDTH_2048_WORDS;
+
+/// Width, in words, of the limbs multiplied by the accelerator.
+const LIMB_WORDS: usize = WIDTH_WORDS / 2;
+
+const MAX_LIMBS: usize = MAX_WIDTH_WORDS / LIMB_WORDS;
+
+/// Compute `(x + y) mod modulus`.
+///
+/// `x` and `y` must be less than `modulus`.
+pub fn add_mod<const W: usize>(x: &[u32; W], y: &[u32; W], modulus: &[u32; W]) -> [u32; W] {
+    let (mut sum, carry) = add(x, y);
+    if carry || !less_than(&sum, modulus) {
+        sum = sub(&sum, modulus).0;
+    }
+    sum
+}
+
+/// Compute `(x - y) mod modulus`.
+///
+/// `x` and `y` must be less than `modulus`.
+pub fn sub_mod<const W: usize>(x: &[u32; W], y: &[u32; W], modulus: &[u32; W]) -> [u32; W] {
+    let (diff, borrow) = sub(x, y);
+    if borrow {
+        add(&diff, modulus).0
+    } else {
+        diff
+    }
+}
+
+/// An odd modulus of `W` words, prepared for Montgomery multiplication.
+///
+/// `W` must be a multiple of 4 and at most [MAX_WIDTH_WORDS]; use
+/// [WIDTH_WORDS], [WIDTH_384_WORDS] or [WIDTH_2048_WORDS] for the common sizes.
+///
+/// ```
+/// use risc0_zkvm_platform::syscall::bigint::{Modulus, WIDTH_384_WORDS};
+///
+/// let mut p = [0u32; WIDTH_384_WORDS];
+/// p[0] = 23;
+/// let p = Modulus::new(p);
+///
+/// let mut x = [0u32; WIDTH_384_WORDS];
+/// x[0] = 5;
+/// assert_eq!(p.mul(&x, &p.inv(&x).unwrap())[0], 1);
+/// assert_eq!(p.inv(&[0u32; WIDTH_384_WORDS]), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Modulus<const W: usize> {
+    modulus: [u32; W],
+    // -modulus^-1 mod 2^128
+    n_prime: u128,
+    // 2^(32 * W) mod modulus, i.e. 1 in Montgomery form.
+    one: [u32; W],
+    // 2^(64 * W) mod modulus, used to convert into Montgomery form.
+    r2: [u32; W],
+}
+
+impl<const W: usize> Modulus<W> {
+    /// Prepare `modulus` for use in modular arithmetic.
+    ///
+    /// Panics if `modulus` is even or equal to one, or if `W` is not a
+    /// supported width.
+    pub fn new(modulus: [u32; W]) -> Self {
+        assert!(
+            W % LIMB_WORDS == 0 && W > 0 && W <= MAX_WIDTH_WORDS,
+            "unsupported modulus width: {W} words"
+        );
+        assert!(modulus[0] & 1 == 1, "modulus must be odd");
+        let bits = bit_len(&modulus);
+        assert!(bits > 1, "modulus must be greater than one");
+
+        // Newton's iteration doubles the number of correct low bits each step.
+        let n0 = to_limb(&modulus, 0);
+        let mut inv: u128 = 1;
+        for _ in 0..7 {
+            inv = inv.wrapping_mul(2u128.wrapping_sub(n0.wrapping_mul(inv)));
+        }
+
+        // Find 2^(32 * W) mod modulus by doubling the highest power of two
+        // below the modulus.
+        let mut one = [0u32; W];
+        one[(bits - 1) / 32] = 1 << ((bits - 1) % 32);
+        for _ in bits - 1..32 * W {
+            one = add_mod(&one, &one, &modulus);
+        }
+
+        let mut this = Self {
+            modulus,
+            n_prime: inv.wrapping_neg(),
+            one,
+            r2: [0u32; W],
+        };
+
+        // 2 * R in Montgomery form is 2, so raising it to the power 32 * W in
+        // Montgomery form yields R * R.
+        let two = add_mod(&one, &one, &modulus);
+        let mut r2 = one;
+        for bit in (0..u32::BITS).rev() {
+            r2 = this.mont_mul(&r2, &r2);
+            if ((32 * W as u32) >> bit) & 1 == 1 {
+                r2 = this.mont_mul(&r2, &two);
+            }
+        }
+        this.r2 = r2;
+        this
+    }
+
+    /// Return the modulus.
+    pub fn modulus(&self) -> &[u32; W] {
+        &self.modulus
+    }
+
+    /// Compute `(x + y) mod modulus`.
+    pub fn add(&self, x: &[u32; W], y: &[u32; W]) -> [u32; W] {
+        add_mod(x, y, &self.modulus)
+    }
+
+    /// Compute `(x - y) mod modulus`.
+    pub fn sub(&self, x: &[u32; W], y: &[u32; W]) -> [u32; W] {
+        sub_mod(x, y, &self.modulus)
+    }
+
+    /// Compute `(x * y) mod modulus`.
+    pub fn mul(&self, x: &[u32; W], y: &[u32; W]) -> [u32; W] {
+        self.mont_mul(&self.mont_mul(x, y), &self.r2)
+    }
+
+    /// Compute `x^exp mod modulus`, where `exp` is a little-endian array of
+    /// words of any length.
+    pub fn pow(&self, x: &[u32; W], exp: &[u32]) -> [u32; W] {
+        let base = self.mont_mul(x, &self.r2);
+        let mut acc = self.one;
+        for word in exp.iter().rev() {
+            for bit in (0..u32::BITS).rev() {
+                acc = self.mont_mul(&acc, &acc);
+                if (word >> bit) & 1 == 1 {
+                    acc = self.mont_mul(&acc, &base);
+                }
+            }
+        }
+        let mut one = [0u32; W];
+        one[0] = 1;
+        self.mont_mul(&acc, &one)
+    }
+
+    /// Compute `x^-1 mod modulus`.
+    ///
+    /// The inverse is computed as `x^(modulus - 2)` using Fermat's little
+    /// theorem, which only holds for a prime modulus. The result is checked,
+    /// so [None] is returned if `x` is zero or, for a composite modulus, if
+    /// the computed value is not an inverse of `x`.
+    pub fn inv(&self, x: &[u32; W]) -> Option<[u32; W]> {
+        let mut one = [0u32; W];
+        one[0] = 1;
+        let two = add(&one, &one).0;
+        let inv = self.pow(x, &sub(&self.modulus, &two).0);
+        (self.mul(x, &inv) == one).then_some(inv)
+    }
+
+    /// Compute `x * y * 2^(-32 * W) mod modulus` using the coarsely integrated
+    /// operand scanning (CIOS) method over 128-bit limbs.
+    fn mont_mul(&self, x: &[u32; W], y: &[u32; W]) -> [u32; W] {
+        let n = W / LIMB_WORDS;
+        let mut t = [0u128; MAX_LIMBS + 2];
+        for i in 0..n {
+            let y_i = to_limb(y, i);
+            let mut carry = 0;
+            for (j, t_j) in t.iter_mut().enumerate().take(n) {
+                (*t_j, carry) = mul_add(to_limb(x, j), y_i, *t_j, carry);
+            }
+            let (sum, overflow) = t[n].overflowing_add(carry);
+            t[n] = sum;
+            t[n + 1] = overflow as u128;
+
+            let m = mul_limb(t[0], self.n_prime).0;
+            let (_, mut carry) = mul_add(m, to_limb(&self.modulus, 0), t[0], 0);
+            for j in 1..n {
+                (t[j - 1], carry) = mul_add(m, to_limb(&self.modulus, j), t[j], carry);
+            }
+            let (sum, overflow) = t[n].overflowing_add(carry);
+            t[n - 1] = sum;
+            t[n] = t[n + 1] + overflow as u128;
+        }
+
+        let mut result = [0u32; W];
+        for (i, limb) in t.iter().enumerate().take(n) {
+            from_limb(&mut result, i, *limb);
+        }
+        if t[n] != 0 || !less_than(&result, &self.modulus) {
+            result = sub(&result, &self.modulus).0;
+        }
+        result
+    }
+}
+
+fn add<const W: usize>(x: &[u32; W], y: &[u32; W]) -> ([u32; W], bool) {
+    let mut sum = [0u32; W];
+    let mut carry = false;
+    for i in 0..W {
+        let (s, c1) = x[i].overflowing_add(y[i]);
+        let (s, c2) = s.overflowing_add(carry as u32);
+        sum[i] = s;
+        carry = c1 || c2;
+    }
+    (sum, carry)
+}
+
+fn sub<const W: usize>(x: &[u32; W], y: &[u32; W]) -> ([u32; W], bool) {
+    let mut diff = [0u32; W];
+    let mut borrow = false;
+    for i in 0..W {
+        let (d, b1) = x[i].overflowing_sub(y[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u32);
+        diff[i] = d;
+        borrow = b1 || b2;
+    }
+    (diff, borrow)
+}
+
+fn less_than<const W: usize>(x: &[u32; W], y: &[u32; W]) -> bool {
+    for i in (0..W).rev() {
+        if x[i] != y[i] {
+            return x[i] < y[i];
+        }
+    }
+    false
+}
+
+fn bit_len<const W: usize>(x: &[u32; W]) -> usize {
+    for i in (0..W).rev() {
+        if x[i] != 0 {
+            return 32 * i + (32 - x[i].leading_zeros() as usize);
+        }
+    }
+    0
+}
+
+fn to_limb(x: &[u32], i: usize) -> u128 {
+    x[i * LIMB_WORDS..(i + 1) * LIMB_WORDS]
+        .iter()
+        .rev()
+        .fold(0, |acc, word| (acc << 32) | *word as u128)
+}
+
+fn from_limb(x: &mut [u32], i: usize, limb: u128) {
+    for (j, word) in x[i * LIMB_WORDS..(i + 1) * LIMB_WORDS]
+        .iter_mut()
+        .enumerate()
+    {
+        *word = (limb >> (32 * j)) as u32;
+    }
+}
+
+/// Compute `a * b + c + d`, returned as (low, high) limbs.
+fn mul_add(a: u128, b: u128, c: u128, d: u128) -> (u128, u128) {
+    let (lo, hi) = mul_limb(a, b);
+    let (lo, c1) = lo.overflowing_add(c);
+    let (lo, c2) = lo.overflowing_add(d);
+    // a * b + c + d < 2^256, so the high limb cannot overflow.
+    (lo, hi + c1 as u128 + c2 as u128)
+}
+
+/// Compute the full product of two 128-bit limbs, returned as (low, high)
+/// limbs.
+#[cfg(target_os = "zkvm")]
+fn mul_limb(a: u128, b: u128) -> (u128, u128) {
+    let mut x = [0u32; WIDTH_WORDS];
+    let mut y = [0u32; WIDTH_WORDS];
+    from_limb(&mut x, 0, a);
+    from_limb(&mut y, 0, b);
+    let mut result = [0u32; WIDTH_WORDS];
+    // A zero modulus computes the product without reduction, which cannot
+    // overflow for inputs below 2^128.
+    unsafe { super::sys_bigint(&mut result, OP_MULTIPLY, &x, &y, &[0u32; WIDTH_WORDS]) };
+    (to_limb(&result, 0), to_limb(&result, 1))
+}
+
+/// Compute the full product of two 128-bit limbs, returned as (low, high)
+/// limbs.
+#[cfg(not(target_os = "zkvm"))]
+fn mul_limb(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a0, a1) = (a & MASK, a >> 64);
+    let (b0, b1) = (b & MASK, b >> 64);
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (lo, hi)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    // Deterministic xorshift generator, so failures are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u32(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 32) as u32
+        }
+
+        fn below<const W: usize>(&mut self, modulus: &[u32; W]) -> [u32; W] {
+            let mut x = [0u32; W];
+            x.iter_mut().for_each(|word| *word = self.next_u32());
+            from_big(&(to_big(&x) % to_big(modulus)))
+        }
+    }
+
+    fn to_big(x: &[u32]) -> BigUint {
+        BigUint::from_slice(x)
+    }
+
+    fn from_big<const W: usize>(x: &BigUint) -> [u32; W] {
+        let mut out = [0u32; W];
+        for (i, digit) in x.to_u32_digits().into_iter().enumerate() {
+            out[i] = digit;
+        }
+        out
+    }
+
+    fn from_hex<const W: usize>(hex: &str) -> [u32; W] {
+        from_big(&BigUint::parse_bytes(hex.as_bytes(), 16).unwrap())
+    }
+
+    fn check<const W: usize>(modulus: [u32; W], prime: bool) {
+        let n = to_big(&modulus);
+        let p = Modulus::new(modulus);
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d ^ W as u64);
+        let zero = [0u32; W];
+        let mut one = [0u32; W];
+        one[0] = 1;
+        let values: Vec<[u32; W]> = [zero, one, sub(&modulus, &one).0]
+            .into_iter()
+            .chain((0..16).map(|_| rng.below(&modulus)))
+            .collect();
+
+        for x in values.iter() {
+            for y in values.iter() {
+                let (bx, by) = (to_big(x), to_big(y));
+                assert_eq!(to_big(&p.add(x, y)), (&bx + &by) % &n);
+                assert_eq!(to_big(&p.sub(x, y)), (&bx + &n - &by) % &n);
+                assert_eq!(to_big(&p.mul(x, y)), (&bx * &by) % &n);
+            }
+            let exp = rng.below(&modulus);
+            assert_eq!(to_big(&p.pow(x, &exp)), to_big(x).modpow(&to_big(&exp), &n));
+            if to_big(x) == BigUint::default() {
+                assert_eq!(p.inv(x), None);
+            } else if prime {
+                assert_eq!(to_big(&p.mul(x, &p.inv(x).unwrap())), BigUint::from(1u32));
+            } else if let Some(inv) = p.inv(x) {
+                assert_eq!(to_big(&p.mul(x, &inv)), BigUint::from(1u32));
+            }
+        }
+    }
+
+    #[test]
+    fn secp256k1() {
+        check::<WIDTH_WORDS>(
+            from_hex("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"),
+            true,
+        );
+    }
+
+    #[test]
+    fn bls12_381() {
+        check::<WIDTH_384_WORDS>(
+            from_hex(
+                "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab",
+            ),
+            true,
+        );
+    }
+
+    #[test]
+    fn rsa_2048() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let mut modulus = [0u32; WIDTH_2048_WORDS];
+        modulus.iter_mut().for_each(|word| *word = rng.next_u32());
+        modulus[0] |= 1;
+        modulus[WIDTH_2048_WORDS - 1] |= 1 << 31;
+        check(modulus, false);
+
+        // A modulus that does not use the full width.
+        modulus[WIDTH_2048_WORDS - 1] = 0;
+        modulus[WIDTH_2048_WORDS - 2] >>= 7;
+        check(modulus, false);
+    }
+
+    #[test]
+    fn mul_limb_matches_reference() {
+        let mut rng = Rng(1);
+        for _ in 0..64 {
+            let a = to_limb(
+                &[rng.next_u32(), rng.next_u32(), rng.next_u32(), u32::MAX],
+                0,
+            );
+            let b = to_limb(
+                &[rng.next_u32(), u32::MAX, rng.next_u32(), rng.next_u32()],
+                0,
+            );
+            let (lo, hi) = mul_limb(a, b);
+            let mut words = [0u32; WIDTH_WORDS];
+            from_limb(&mut words, 0, lo);
+            from_limb(&mut words, 1, hi);
+            assert_eq!(to_big(&words), BigUint::from(a) * BigUint::from(b));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus must be odd")]
+    fn even_modulus() {
+        Modulus::new([2u32; WIDTH_WORDS]);
+    }
+}
@@ -82,6 +82,7 @@ use risc0_zkvm_platform::{
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
+    merkle_journal::MerkleJournal,
     serde::{Deserializer, Serializer, WordRead, WordWrite},
     sha::{
         rust_crypto::{Digest as _, Sha256},
@@ -535,6 +536,26 @@ pub fn commit_slice<T: Pod>(slice: &[T]) {
     journal().write_slice(slice);
 }
 
+/// Commit the root of the given [MerkleJournal] to the journal.
+///
+/// Only the 32-byte root is written to the journal, so the receipt commits to
+/// the records and their number without including them. The host can then prove the inclusion
+/// of individual records with [MerkleJournal::prove].
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::{guest::env, merkle_journal::MerkleJournal};
+///
+/// let mut journal = MerkleJournal::new();
+/// journal.push(b"first record").unwrap();
+/// journal.push(b"second record").unwrap();
+/// env::commit_merkle_root(&journal);
+/// ```
+pub fn commit_merkle_root(journal: &MerkleJournal) {
+    commit_slice(journal.root().as_bytes());
+}
+
 /// Return the number of processor cycles that have occurred since the guest
 /// began.
 ///
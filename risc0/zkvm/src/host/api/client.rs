@@ -18,9 +18,11 @@ use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 use prost::Message;
 
+#[cfg(unix)]
+use super::UnixConnector;
 use super::{
-    malformed_err, pb, Asset, AssetRequest, ConnectionWrapper, Connector, ParentProcessConnector,
-    SessionInfo,
+    malformed_err, pb, Asset, AssetRequest, ConnectionWrapper, Connector, JobId, JobStatus,
+    ParentProcessConnector, SessionInfo, TcpConnector,
 };
 use crate::{
     get_version,
@@ -35,6 +37,7 @@ use crate::{
 /// A client implementation for interacting with a zkVM server.
 pub struct Client {
     connector: Box<dyn Connector>,
+    job_id: Option<JobId>,
 }
 
 impl Default for Client {
@@ -56,6 +59,19 @@ impl Client {
         Ok(Self::with_connector(Box::new(connector)))
     }
 
+    /// Construct a [Client] that connects to a server listening on the
+    /// specified TCP/IP address, such as `r0vm --listen`.
+    pub fn new_tcp<A: AsRef<str>>(addr: A) -> Self {
+        Self::with_connector(Box::new(TcpConnector::new(addr.as_ref())))
+    }
+
+    /// Construct a [Client] that connects to a server listening on the Unix
+    /// domain socket at the specified path, such as `r0vm --listen unix:<path>`.
+    #[cfg(unix)]
+    pub fn new_unix<P: AsRef<Path>>(path: P) -> Self {
+        Self::with_connector(Box::new(UnixConnector::new(path.as_ref())))
+    }
+
     /// Construct a [Client] based on environment variables.
     pub fn from_env() -> Result<Self> {
         Client::new_sub_process(get_r0vm_path())
@@ -64,10 +80,100 @@ impl Client {
     /// Construct a [Client] using the specified [Connector] to establish a
     /// connection with the server.
     pub fn with_connector(connector: Box<dyn Connector>) -> Self {
-        Self { connector }
+        Self {
+            connector,
+            job_id: None,
+        }
+    }
+
+    /// Track the requests made by this [Client] under the specified [JobId].
+    ///
+    /// The progress of these requests can then be queried with
+    /// [Client::status], and they can be stopped with [Client::cancel], from
+    /// this or any other [Client] connected to the same server.
+    ///
+    /// Jobs are only tracked by an [crate::ApiDaemon]. Requests made under a
+    /// [JobId] fail if the [Connector] of this [Client] does not reach one.
+    pub fn with_job_id(self, job_id: JobId) -> Self {
+        Self {
+            job_id: Some(job_id),
+            ..self
+        }
+    }
+
+    /// Return the [JobId] that requests made by this [Client] are tracked
+    /// under, if any.
+    pub fn job_id(&self) -> Option<&JobId> {
+        self.job_id.as_ref()
+    }
+
+    /// Query the progress of the specified job.
+    pub fn status(&self, job_id: &JobId) -> Result<JobStatus> {
+        self.check_tracks_jobs()?;
+        let mut conn = self.connect()?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::Status(
+                pb::api::StatusRequest {
+                    job_id: job_id.to_string(),
+                },
+            )),
+        };
+        tracing::trace!("tx: {request:?}");
+        conn.send(request)?;
+
+        let reply: pb::api::StatusReply = conn.recv()?;
+
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::status_reply::Kind::Ok(status) => status.try_into(),
+            pb::api::status_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        let code = conn.close()?;
+        if code != 0 {
+            bail!("Child finished with: {code}");
+        }
+
+        result
+    }
+
+    /// Cancel the specified job.
+    ///
+    /// Cancellation is cooperative: requests of the job stop at the next
+    /// segment or recursion step, and fail with an error.
+    pub fn cancel(&self, job_id: &JobId) -> Result<()> {
+        self.check_tracks_jobs()?;
+        let mut conn = self.connect()?;
+
+        let request = pb::api::ServerRequest {
+            kind: Some(pb::api::server_request::Kind::Cancel(
+                pb::api::CancelRequest {
+                    job_id: job_id.to_string(),
+                },
+            )),
+        };
+        tracing::trace!("tx: {request:?}");
+        conn.send(request)?;
+
+        let reply: pb::api::GenericReply = conn.recv()?;
+
+        let result = match reply.kind.ok_or(malformed_err())? {
+            pb::api::generic_reply::Kind::Ok(()) => Ok(()),
+            pb::api::generic_reply::Kind::Error(err) => Err(err.into()),
+        };
+
+        let code = conn.close()?;
+        if code != 0 {
+            bail!("Child finished with: {code}");
+        }
+
+        result
     }
 
     /// Prove the specified ELF binary.
+    ///
+    /// The receipt is compressed into the [crate::ReceiptKind] given by
+    /// [ProverOpts::receipt_kind].
     pub fn prove(&self, env: &ExecutorEnv<'_>, opts: ProverOpts, binary: Asset) -> Result<Receipt> {
         let mut conn = self.connect()?;
 
@@ -75,10 +181,12 @@ impl Client {
             kind: Some(pb::api::server_request::Kind::Prove(
                 pb::api::ProveRequest {
                     env: Some(self.make_execute_env(env, binary.try_into()?)?),
+                    receipt_kind: pb::api::ReceiptKind::from(opts.receipt_kind).into(),
                     opts: Some(opts.into()),
                     receipt_out: Some(pb::api::AssetRequest {
                         kind: Some(pb::api::asset_request::Kind::Inline(())),
                     }),
+                    job_id: self.job_id_pb(),
                 },
             )),
         };
@@ -114,6 +222,7 @@ impl Client {
                 pb::api::ExecuteRequest {
                     env: Some(self.make_execute_env(env, binary.try_into()?)?),
                     segments_out: Some(segments_out.try_into()?),
+                    job_id: self.job_id_pb(),
                 },
             )),
         };
@@ -145,6 +254,7 @@ impl Client {
                     opts: Some(opts.into()),
                     segment: Some(segment.try_into()?),
                     receipt_out: Some(receipt_out.try_into()?),
+                    job_id: self.job_id_pb(),
                 },
             )),
         };
@@ -189,6 +299,7 @@ impl Client {
                 opts: Some(opts.into()),
                 receipt: Some(receipt.try_into()?),
                 receipt_out: Some(receipt_out.try_into()?),
+                job_id: self.job_id_pb(),
             })),
         };
         tracing::trace!("tx: {request:?}");
@@ -232,6 +343,7 @@ impl Client {
                 left_receipt: Some(left_receipt.try_into()?),
                 right_receipt: Some(right_receipt.try_into()?),
                 receipt_out: Some(receipt_out.try_into()?),
+                job_id: self.job_id_pb(),
             })),
         };
         tracing::trace!("tx: {request:?}");
@@ -278,6 +390,7 @@ impl Client {
                     conditional_receipt: Some(conditional_receipt.try_into()?),
                     assumption_receipt: Some(assumption_receipt.try_into()?),
                     receipt_out: Some(receipt_out.try_into()?),
+                    job_id: self.job_id_pb(),
                 },
             )),
         };
@@ -322,6 +435,7 @@ impl Client {
                     opts: Some(opts.into()),
                     receipt: Some(receipt.try_into()?),
                     receipt_out: Some(receipt_out.try_into()?),
+                    job_id: self.job_id_pb(),
                 },
             )),
         };
@@ -347,7 +461,17 @@ impl Client {
         result
     }
 
+    fn check_tracks_jobs(&self) -> Result<()> {
+        if !self.connector.tracks_jobs() {
+            bail!("jobs are only tracked by the API daemon, which this client is not connected to");
+        }
+        Ok(())
+    }
+
     fn connect(&self) -> Result<ConnectionWrapper> {
+        if self.job_id.is_some() {
+            self.check_tracks_jobs()?;
+        }
         let mut conn = self.connector.connect()?;
 
         let client_version = get_version().map_err(|err| anyhow!(err))?;
@@ -382,6 +506,13 @@ impl Client {
         Ok(conn)
     }
 
+    fn job_id_pb(&self) -> String {
+        self.job_id
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
     fn make_execute_env(
         &self,
         env: &ExecutorEnv<'_>,
@@ -396,6 +527,7 @@ impl Client {
             write_fds: env.posix_io.borrow().write_fds.keys().cloned().collect(),
             segment_limit_po2: env.segment_limit_po2,
             session_limit: env.session_limit,
+            cycle_report: env.cycle_report,
             trace_events: (!env.trace.is_empty()).then_some(()),
             pprof_out: env
                 .pprof_out
@@ -487,6 +619,7 @@ impl Client {
                                         .exit_code
                                         .ok_or(malformed_err())?
                                         .try_into()?,
+                                    cycle_report: session.cycle_report.map(Into::into),
                                 }),
                                 None => Err(malformed_err()),
                             }
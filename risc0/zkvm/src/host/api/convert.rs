@@ -19,14 +19,17 @@ use prost::{Message, Name};
 use risc0_binfmt::{MemoryImage, PageTableInfo, SystemState};
 use risc0_zkp::core::digest::Digest;
 
-use super::{malformed_err, path_to_string, pb, Asset, AssetRequest};
+use super::{malformed_err, path_to_string, pb, Asset, AssetRequest, JobState, JobStatus};
 use crate::{
     host::{
-        receipt::{decode_receipt_claim_from_seal, CompositeReceipt, InnerReceipt, SegmentReceipt},
+        receipt::{
+            decode_receipt_claim_from_seal, CompactReceipt, CompositeReceipt, InnerReceipt,
+            SegmentReceipt,
+        },
         recursion::SuccinctReceipt,
     },
-    Assumptions, ExitCode, Journal, MaybePruned, Output, ProverOpts, Receipt, ReceiptClaim,
-    TraceEvent,
+    Assumptions, CycleReport, ExitCode, FunctionCycles, Journal, MaybePruned, Output, ProverOpts,
+    Receipt, ReceiptClaim, ReceiptKind, StackCycles, TraceEvent,
 };
 
 mod ver {
@@ -36,6 +39,7 @@ mod ver {
     pub const RECEIPT: CompatVersion = CompatVersion { value: 1 };
     pub const SEGMENT_RECEIPT: CompatVersion = CompatVersion { value: 1 };
     pub const SUCCINCT_RECEIPT: CompatVersion = CompatVersion { value: 1 };
+    pub const GROTH16_RECEIPT: CompatVersion = CompatVersion { value: 1 };
 }
 
 impl TryFrom<AssetRequest> for pb::api::AssetRequest {
@@ -144,6 +148,56 @@ impl TryFrom<pb::api::TraceEvent> for TraceEvent {
     }
 }
 
+impl From<CycleReport> for pb::api::CycleReport {
+    fn from(value: CycleReport) -> Self {
+        Self {
+            total_cycles: value.total_cycles,
+            functions: value
+                .functions
+                .into_iter()
+                .map(|x| pb::api::cycle_report::FunctionCycles {
+                    name: x.name,
+                    self_cycles: x.self_cycles,
+                    total_cycles: x.total_cycles,
+                })
+                .collect(),
+            stacks: value
+                .stacks
+                .into_iter()
+                .map(|x| pb::api::cycle_report::StackCycles {
+                    frames: x.frames,
+                    cycles: x.cycles,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<pb::api::CycleReport> for CycleReport {
+    fn from(value: pb::api::CycleReport) -> Self {
+        Self {
+            total_cycles: value.total_cycles,
+            functions: value
+                .functions
+                .into_iter()
+                .map(|x| FunctionCycles {
+                    name: x.name,
+                    self_cycles: x.self_cycles,
+                    total_cycles: x.total_cycles,
+                })
+                .collect(),
+            stacks: value
+                .stacks
+                .into_iter()
+                .map(|x| StackCycles {
+                    frames: x.frames,
+                    cycles: x.cycles,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl From<ExitCode> for pb::base::ExitCode {
     fn from(value: ExitCode) -> Self {
         Self {
@@ -170,6 +224,58 @@ impl TryFrom<pb::base::ExitCode> for ExitCode {
     }
 }
 
+impl From<JobState> for pb::api::JobState {
+    fn from(value: JobState) -> Self {
+        match value {
+            JobState::Queued => Self::Queued,
+            JobState::Running => Self::Running,
+            JobState::Succeeded => Self::Succeeded,
+            JobState::Failed => Self::Failed,
+            JobState::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+impl From<pb::api::JobState> for JobState {
+    fn from(value: pb::api::JobState) -> Self {
+        match value {
+            pb::api::JobState::Queued => Self::Queued,
+            pb::api::JobState::Running => Self::Running,
+            pb::api::JobState::Succeeded => Self::Succeeded,
+            pb::api::JobState::Failed => Self::Failed,
+            pb::api::JobState::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+impl From<JobStatus> for pb::api::JobStatus {
+    fn from(value: JobStatus) -> Self {
+        Self {
+            state: pb::api::JobState::from(value.state).into(),
+            segments_executed: value.segments_executed,
+            segments_proven: value.segments_proven,
+            recursion_steps: value.recursion_steps,
+            error: value.error,
+        }
+    }
+}
+
+impl TryFrom<pb::api::JobStatus> for JobStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: pb::api::JobStatus) -> Result<Self> {
+        Ok(Self {
+            state: pb::api::JobState::try_from(value.state)
+                .map_err(|_| malformed_err())?
+                .into(),
+            segments_executed: value.segments_executed,
+            segments_proven: value.segments_proven,
+            recursion_steps: value.recursion_steps,
+            error: value.error,
+        })
+    }
+}
+
 impl From<Result<(), anyhow::Error>> for pb::api::GenericReply {
     fn from(result: Result<(), anyhow::Error>) -> Self {
         Self {
@@ -200,6 +306,8 @@ impl From<pb::api::ProverOpts> for ProverOpts {
         Self {
             hashfn: opts.hashfn,
             prove_guest_errors: opts.prove_guest_errors,
+            receipt_kind: ReceiptKind::Composite,
+            cancellation_token: None,
         }
     }
 }
@@ -213,6 +321,26 @@ impl From<ProverOpts> for pb::api::ProverOpts {
     }
 }
 
+impl From<ReceiptKind> for pb::api::ReceiptKind {
+    fn from(value: ReceiptKind) -> Self {
+        match value {
+            ReceiptKind::Composite => Self::Composite,
+            ReceiptKind::Succinct => Self::Succinct,
+            ReceiptKind::Compact => Self::Compact,
+        }
+    }
+}
+
+impl From<pb::api::ReceiptKind> for ReceiptKind {
+    fn from(value: pb::api::ReceiptKind) -> Self {
+        match value {
+            pb::api::ReceiptKind::Composite => Self::Composite,
+            pb::api::ReceiptKind::Succinct => Self::Succinct,
+            pb::api::ReceiptKind::Compact => Self::Compact,
+        }
+    }
+}
+
 impl From<MemoryImage> for pb::core::MemoryImage {
     fn from(value: MemoryImage) -> Self {
         let pages = value
@@ -416,7 +544,9 @@ impl From<InnerReceipt> for pb::core::InnerReceipt {
                         claim: Some(claim.into()),
                     })
                 }
-                InnerReceipt::Compact(_) => unimplemented!(),
+                InnerReceipt::Compact(inner) => {
+                    pb::core::inner_receipt::Kind::Groth16(inner.into())
+                }
             }),
         }
     }
@@ -428,7 +558,7 @@ impl TryFrom<pb::core::InnerReceipt> for InnerReceipt {
     fn try_from(value: pb::core::InnerReceipt) -> Result<Self> {
         Ok(match value.kind.ok_or(malformed_err())? {
             pb::core::inner_receipt::Kind::Composite(inner) => Self::Composite(inner.try_into()?),
-            pb::core::inner_receipt::Kind::Groth16(_) => unimplemented!(),
+            pb::core::inner_receipt::Kind::Groth16(inner) => Self::Compact(inner.try_into()?),
             pb::core::inner_receipt::Kind::Succinct(inner) => Self::Succinct(inner.try_into()?),
             pb::core::inner_receipt::Kind::Fake(inner) => Self::Fake {
                 claim: inner.claim.ok_or(malformed_err())?.try_into()?,
@@ -437,6 +567,32 @@ impl TryFrom<pb::core::InnerReceipt> for InnerReceipt {
     }
 }
 
+impl From<CompactReceipt> for pb::core::Groth16Receipt {
+    fn from(value: CompactReceipt) -> Self {
+        Self {
+            version: Some(ver::GROTH16_RECEIPT),
+            seal: value.seal,
+            claim: Some(value.claim.into()),
+        }
+    }
+}
+
+impl TryFrom<pb::core::Groth16Receipt> for CompactReceipt {
+    type Error = anyhow::Error;
+
+    fn try_from(value: pb::core::Groth16Receipt) -> Result<Self> {
+        let version = value.version.ok_or(malformed_err())?.value;
+        if version > ver::GROTH16_RECEIPT.value {
+            bail!("Incompatible Groth16Receipt version: {version}");
+        }
+
+        Ok(Self {
+            seal: value.seal,
+            claim: value.claim.ok_or(malformed_err())?.try_into()?,
+        })
+    }
+}
+
 impl From<CompositeReceipt> for pb::core::CompositeReceipt {
     fn from(value: CompositeReceipt) -> Self {
         Self {
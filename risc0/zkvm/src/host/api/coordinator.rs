@@ -0,0 +1,354 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving the segments of a session on several zkVM servers at once.
+
+#[cfg(unix)]
+use std::path::Path;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+};
+
+use anyhow::{ensure, Result};
+
+#[cfg(unix)]
+use super::UnixConnector;
+use super::{client::Client, Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnector};
+use crate::{
+    host::{
+        receipt::{SegmentReceipt, SuccinctReceipt},
+        server::{
+            prove::{
+                compress::JoinSteps,
+                pipeline::{prove_segments, resolve_session, session_receipt, SegmentPipeline},
+            },
+            session::NullSegmentRef,
+        },
+    },
+    sha::Digestible,
+    ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, Segment, Session, VerifierContext,
+};
+
+/// Proves the [Segment]s of a session by dispatching them to several zkVM
+/// servers.
+///
+/// Each [Segment] is sent to one of the endpoints with
+/// [Client::prove_segment], and the resulting receipt is lifted on the same
+/// endpoint with [Client::lift]. The lifted receipts are then joined in order
+/// with [Client::join] on whichever endpoint is free, resulting in a single
+/// [SuccinctReceipt] for the session.
+///
+/// Every receipt returned by an endpoint is verified, and the claim of each
+/// lifted receipt is checked against the claim of its segment. A request that
+/// fails, or returns a receipt that does not verify or has the wrong claim, is
+/// retried on the next free endpoint, up to the configured number of attempts.
+///
+/// Each endpoint is sent one request at a time. To keep several requests in
+/// flight on a server with more than one worker, such as
+/// `r0vm --listen <ADDR> --workers <N>`, add it more than once.
+///
+/// ```no_run
+/// use risc0_zkvm::{ApiCoordinator, ProverOpts};
+///
+/// let coordinator = ApiCoordinator::new(ProverOpts::default())
+///     .with_tcp_endpoint("10.0.0.1:9000")
+///     .with_tcp_endpoint("10.0.0.2:9000");
+/// ```
+pub struct Coordinator {
+    endpoints: Vec<Arc<dyn Connector + Send + Sync>>,
+    opts: ProverOpts,
+    max_attempts: usize,
+}
+
+impl Coordinator {
+    /// Construct a [Coordinator] without endpoints, using the specified
+    /// [ProverOpts].
+    ///
+    /// By default, each request is attempted up to 3 times.
+    pub fn new(opts: ProverOpts) -> Self {
+        Self {
+            endpoints: Vec::new(),
+            opts,
+            max_attempts: 3,
+        }
+    }
+
+    /// Add an endpoint that is connected to with the specified [Connector].
+    pub fn with_endpoint(mut self, connector: impl Connector + Send + Sync + 'static) -> Self {
+        self.endpoints.push(Arc::new(connector));
+        self
+    }
+
+    /// Add an endpoint listening on the specified TCP address.
+    pub fn with_tcp_endpoint<A: AsRef<str>>(self, addr: A) -> Self {
+        self.with_endpoint(TcpConnector::new(addr.as_ref()))
+    }
+
+    /// Add an endpoint listening on the Unix domain socket at the specified
+    /// path.
+    #[cfg(unix)]
+    pub fn with_unix_endpoint<P: AsRef<Path>>(self, path: P) -> Self {
+        self.with_endpoint(UnixConnector::new(path.as_ref()))
+    }
+
+    /// Set the number of times a request is attempted before proving fails.
+    pub fn with_max_attempts(self, max_attempts: usize) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..self
+        }
+    }
+
+    /// Prove the specified ELF binary.
+    ///
+    /// Segments are dispatched as soon as the executor produces them, so
+    /// execution and proving overlap.
+    pub fn prove(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<Receipt> {
+        self.prove_with_ctx(env, &VerifierContext::default(), elf)
+    }
+
+    /// Prove the specified ELF binary using the specified [VerifierContext].
+    pub fn prove_with_ctx(
+        &self,
+        env: ExecutorEnv<'_>,
+        ctx: &VerifierContext,
+        elf: &[u8],
+    ) -> Result<Receipt> {
+        let mut exec = ExecutorImpl::from_elf(env, elf)?;
+        let (session, continuation_receipt) = self.prove_segments(|submit| {
+            exec.run_with_callback(|segment| {
+                submit(segment)?;
+                Ok(Box::new(NullSegmentRef))
+            })
+        })?;
+        let succinct_receipt = self.resolve_session(&session, continuation_receipt)?;
+        session_receipt(&session, succinct_receipt, ctx)
+    }
+
+    /// Prove the segments of the specified [Session], and resolve its
+    /// assumptions.
+    ///
+    /// Returns an error if the claim of the resulting receipt does not match
+    /// the claim of the session.
+    pub fn prove_session(&self, session: &Session) -> Result<SuccinctReceipt> {
+        let ((), continuation_receipt) = self.prove_segments(|submit| {
+            session
+                .segments
+                .iter()
+                .try_for_each(|segment_ref| submit(segment_ref.resolve()?))
+        })?;
+        let receipt = self.resolve_session(session, continuation_receipt)?;
+        let session_claim = session.get_claim()?;
+        ensure!(
+            receipt.claim.digest() == session_claim.digest(),
+            "session and receipt claim do not match: session {}, receipt {}",
+            hex::encode(session_claim.digest()),
+            hex::encode(receipt.claim.digest())
+        );
+        Ok(receipt)
+    }
+
+    /// Prove the segments passed to `submit` by `produce`, and join them into
+    /// a single [SuccinctReceipt].
+    fn prove_segments<T, F>(&self, produce: F) -> Result<(T, SuccinctReceipt)>
+    where
+        F: FnOnce(&mut dyn FnMut(Segment) -> Result<()>) -> Result<T>,
+    {
+        ensure!(!self.endpoints.is_empty(), "no endpoints to prove on");
+        ensure!(
+            self.opts.hashfn == "poseidon2",
+            "Coordinator requires the poseidon2 hash function, got: {}",
+            self.opts.hashfn
+        );
+
+        let endpoints = self.endpoints();
+        let n = self.endpoints.len();
+        prove_segments(&endpoints, n, n, produce)
+    }
+
+    /// Resolve the assumptions of the session.
+    fn resolve_session(
+        &self,
+        session: &Session,
+        continuation_receipt: SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        resolve_session(&self.endpoints(), session, continuation_receipt)
+    }
+
+    fn endpoints(&self) -> Endpoints<'_> {
+        Endpoints {
+            connectors: &self.endpoints,
+            opts: &self.opts,
+            free: Mutex::new((0..self.endpoints.len()).collect()),
+            available: Condvar::new(),
+            max_attempts: self.max_attempts,
+        }
+    }
+}
+
+/// The endpoints of a [Coordinator], tracking which are not busy with a
+/// request.
+struct Endpoints<'a> {
+    connectors: &'a [Arc<dyn Connector + Send + Sync>],
+    opts: &'a ProverOpts,
+    free: Mutex<VecDeque<usize>>,
+    available: Condvar,
+    max_attempts: usize,
+}
+
+impl<'a> Endpoints<'a> {
+    /// Run `f` on the next free endpoint, retrying on another endpoint if it
+    /// fails.
+    fn dispatch<T>(&self, what: &str, f: impl Fn(&Client) -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            let idx = self.acquire();
+            tracing::debug!("coordinator: {what} on endpoint {idx}, attempt {attempt}");
+            let client =
+                Client::with_connector(Box::new(SharedConnector(self.connectors[idx].clone())));
+            let result = f(&client);
+            self.release(idx);
+            match result {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.max_attempts => {
+                    tracing::warn!("coordinator: {what} failed on endpoint {idx}: {err:#}");
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(err.context(format!("{what} failed after {attempt} attempts")))
+                }
+            }
+        }
+    }
+
+    fn acquire(&self) -> usize {
+        let mut free = self.free.lock().unwrap();
+        loop {
+            if let Some(idx) = free.pop_front() {
+                return idx;
+            }
+            free = self.available.wait(free).unwrap();
+        }
+    }
+
+    /// Endpoints are released to the back of the queue, so that a request that
+    /// failed is retried on a different endpoint if there is one.
+    fn release(&self, idx: usize) {
+        self.free.lock().unwrap().push_back(idx);
+        self.available.notify_one();
+    }
+
+    /// Lift `receipt` on the given endpoint, checking that the lifted receipt
+    /// verifies and has the same claim as the segment.
+    fn lift_on(&self, client: &Client, receipt: SegmentReceipt) -> Result<SuccinctReceipt> {
+        let claim = receipt.claim.digest();
+        let lifted = client.lift(self.opts.clone(), receipt.try_into()?, AssetRequest::Inline)?;
+        lifted.verify_integrity()?;
+        ensure!(
+            lifted.claim.digest() == claim,
+            "lifted receipt claim does not match the segment: segment {}, lifted {}",
+            hex::encode(claim),
+            hex::encode(lifted.claim.digest())
+        );
+        Ok(lifted)
+    }
+}
+
+impl SegmentPipeline for Endpoints<'_> {
+    // Requests are sent to whichever endpoint is free, so threads need no
+    // state of their own.
+    type Worker = ();
+
+    fn worker(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn lift_segment(&self, _: &(), segment: Segment) -> Result<SuccinctReceipt> {
+        let index = segment.index;
+        let asset = Asset::Inline(bincode::serialize(&segment)?.into());
+        drop(segment);
+        let ctx = VerifierContext::default();
+        self.dispatch(&format!("segment {index}"), |client| {
+            let receipt =
+                client.prove_segment(self.opts.clone(), asset.clone(), AssetRequest::Inline)?;
+            ensure!(
+                receipt.index == index,
+                "expected receipt for segment {index}, got {}",
+                receipt.index
+            );
+            receipt.verify_integrity_with_context(&ctx)?;
+            self.lift_on(client, receipt)
+        })
+    }
+
+    fn join(
+        &self,
+        _: &(),
+        left: &SuccinctReceipt,
+        right: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        JoinSteps::join(self, left, right)
+    }
+}
+
+impl JoinSteps for Endpoints<'_> {
+    fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
+        self.dispatch("lift", |client| self.lift_on(client, receipt.clone()))
+    }
+
+    fn join(&self, left: &SuccinctReceipt, right: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        self.dispatch("join", |client| {
+            let receipt = client.join(
+                self.opts.clone(),
+                left.clone().try_into()?,
+                right.clone().try_into()?,
+                AssetRequest::Inline,
+            )?;
+            receipt.verify_integrity()?;
+            Ok(receipt)
+        })
+    }
+
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        assumption: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        self.dispatch("resolve", |client| {
+            let receipt = client.resolve(
+                self.opts.clone(),
+                conditional.clone().try_into()?,
+                assumption.clone().try_into()?,
+                AssetRequest::Inline,
+            )?;
+            receipt.verify_integrity()?;
+            Ok(receipt)
+        })
+    }
+}
+
+/// A [Connector] shared by the [Client]s that a [Coordinator] creates for each
+/// request, since a [Client] cannot be shared across threads.
+struct SharedConnector(Arc<dyn Connector + Send + Sync>);
+
+impl Connector for SharedConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        self.0.connect()
+    }
+
+    fn tracks_jobs(&self) -> bool {
+        self.0.tracks_jobs()
+    }
+}
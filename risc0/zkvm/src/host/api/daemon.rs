@@ -0,0 +1,279 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    net::TcpListener,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+};
+
+#[cfg(unix)]
+use anyhow::bail;
+use anyhow::{anyhow, Result};
+
+#[cfg(unix)]
+use super::UnixConnection;
+use super::{jobs::Jobs, pb, server::Server, ConnectionWrapper, TcpConnection};
+
+/// Options for a [Daemon].
+#[derive(Clone, Debug)]
+pub struct DaemonOpts {
+    /// The maximum number of clients connected at once.
+    ///
+    /// Clients that connect beyond this limit are refused during the
+    /// handshake.
+    pub max_connections: usize,
+
+    /// The number of workers that proving requests are queued onto.
+    ///
+    /// Requests to `prove`, `prove_segment`, `lift`, `join`, `resolve` and
+    /// `identity_p254` wait for a free worker, in the order they were
+    /// received. Requests to `execute`, `status` and `cancel` are served right
+    /// away.
+    pub workers: usize,
+}
+
+impl Default for DaemonOpts {
+    fn default() -> Self {
+        Self {
+            max_connections: 64,
+            workers: 1,
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn accept(&self) -> Result<ConnectionWrapper> {
+        Ok(match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                ConnectionWrapper::new(Box::new(TcpConnection::new(stream)))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                ConnectionWrapper::new(Box::new(UnixConnection::new(stream)))
+            }
+        })
+    }
+}
+
+/// A long-lived server that accepts many clients of the zkVM at once.
+///
+/// Unlike [Server], which connects to a single client, a [Daemon] listens on
+/// a TCP or Unix domain socket. Clients connect with
+/// [crate::ApiClient::new_tcp] or [crate::ApiClient::new_unix], and each
+/// connection goes through the same version handshake. Jobs are tracked across
+/// all connections, so that one client can query the status of a job started
+/// by another.
+pub struct Daemon {
+    listener: Listener,
+    opts: DaemonOpts,
+}
+
+impl Daemon {
+    /// Listen for clients on the specified TCP/IP address.
+    pub fn bind_tcp<A: AsRef<str>>(addr: A, opts: DaemonOpts) -> Result<Self> {
+        let listener = TcpListener::bind(addr.as_ref())?;
+        Ok(Self {
+            listener: Listener::Tcp(listener),
+            opts,
+        })
+    }
+
+    /// Listen for clients on a Unix domain socket at the specified path.
+    ///
+    /// A socket left behind at this path by a previous daemon is replaced, but
+    /// binding fails if another daemon is still listening on it.
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P, opts: DaemonOpts) -> Result<Self> {
+        let path = path.as_ref();
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                if UnixStream::connect(path).is_ok() {
+                    bail!("a daemon is already listening on {}", path.display());
+                }
+                std::fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(Self {
+            listener: Listener::Unix(listener),
+            opts,
+        })
+    }
+
+    /// The TCP port that the daemon is listening on, if any.
+    pub fn port(&self) -> Option<u16> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr().ok().map(|addr| addr.port()),
+            #[cfg(unix)]
+            Listener::Unix(_) => None,
+        }
+    }
+
+    /// Serve clients.
+    ///
+    /// Failing to accept a connection, for example because the process has run
+    /// out of file descriptors, is logged and does not stop the daemon.
+    pub fn run(&self) -> Result<()> {
+        let pool = Arc::new(WorkerPool::new(self.opts.workers.max(1)));
+        let jobs = Arc::new(Jobs::default());
+        let active = Arc::new(AtomicUsize::new(0));
+        loop {
+            let conn = match self.listener.accept() {
+                Ok(conn) => conn,
+                Err(err) => {
+                    tracing::warn!("failed to accept connection: {err}");
+                    // Back off, since errors such as running out of file
+                    // descriptors last until other connections are closed.
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            let reject =
+                (active.fetch_add(1, Ordering::SeqCst) >= self.opts.max_connections).then(|| {
+                    format!(
+                        "connection limit reached: {} clients",
+                        self.opts.max_connections
+                    )
+                });
+            let guard = ActiveGuard(active.clone());
+            let pool = pool.clone();
+            let jobs = jobs.clone();
+            let spawned = thread::Builder::new()
+                .name("connection".into())
+                .spawn(move || {
+                    let _guard = guard;
+                    if let Err(err) = serve_connection(conn, reject, &pool, jobs) {
+                        tracing::debug!("connection closed: {err}");
+                    }
+                });
+            if let Err(err) = spawned {
+                tracing::warn!("failed to spawn connection thread: {err}");
+            }
+        }
+    }
+}
+
+// How long to wait after failing to accept a connection before trying again.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+fn serve_connection(
+    mut conn: ConnectionWrapper,
+    reject: Option<String>,
+    pool: &WorkerPool,
+    jobs: Arc<Jobs>,
+) -> Result<()> {
+    Server::handshake(&mut conn, reject)?;
+
+    let request: pb::api::ServerRequest = conn.recv()?;
+    tracing::trace!("rx: {request:?}");
+    let job_id = match request.kind.as_ref() {
+        Some(pb::api::server_request::Kind::Prove(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::ProveSegment(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::Lift(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::Join(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::Resolve(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::IdentiyP254(request)) => &request.job_id,
+        Some(pb::api::server_request::Kind::Execute(_))
+        | Some(pb::api::server_request::Kind::Status(_))
+        | Some(pb::api::server_request::Kind::Cancel(_))
+        | None => return Server::dispatch(conn, request, &jobs),
+    };
+    jobs.get_or_insert(job_id).queue();
+    pool.run(move || Server::dispatch(conn, request, &jobs))?
+}
+
+// Decrements the number of active connections when a connection is closed.
+struct ActiveGuard(Arc<AtomicUsize>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// A fixed number of threads that run jobs in the order they were queued.
+struct WorkerPool {
+    queue: Mutex<Sender<Job>>,
+}
+
+impl WorkerPool {
+    fn new(workers: usize) -> Self {
+        let (queue, jobs) = channel::<Job>();
+        let jobs = Arc::new(Mutex::new(jobs));
+        for idx in 0..workers {
+            let jobs = jobs.clone();
+            thread::Builder::new()
+                .name(format!("worker-{idx}"))
+                .spawn(move || Self::worker(&jobs))
+                .unwrap();
+        }
+        Self {
+            queue: Mutex::new(queue),
+        }
+    }
+
+    fn worker(jobs: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = jobs.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    // Queue `f` and wait for a worker to run it.
+    fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            tx.send(result).ok();
+        });
+        self.queue
+            .lock()
+            .unwrap()
+            .send(job)
+            .map_err(|_| anyhow!("worker pool is shut down"))?;
+        rx.recv()?.map_err(|_| anyhow!("worker panicked"))
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::{anyhow, Result};
+
+use super::{JobState, JobStatus};
+use crate::{CancellationToken, Segment, SessionEvents};
+
+// The number of jobs kept before the oldest finished jobs are forgotten.
+const MAX_JOBS: usize = 1024;
+
+/// The jobs known to a server, shared by all of its connections.
+#[derive(Default)]
+pub(crate) struct Jobs {
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+}
+
+impl Jobs {
+    /// Return the job with the specified ID, registering it if it is new.
+    ///
+    /// Requests without a job ID get a job that is not registered, so that
+    /// they can be handled the same way.
+    pub(crate) fn get_or_insert(&self, job_id: &str) -> Arc<Job> {
+        if job_id.is_empty() {
+            return Arc::new(Job::default());
+        }
+        let mut jobs = self.jobs.lock().unwrap();
+        if !jobs.contains_key(job_id) && jobs.len() >= MAX_JOBS {
+            Self::evict(&mut jobs);
+        }
+        jobs.entry(job_id.to_string()).or_default().clone()
+    }
+
+    /// Return the job with the specified ID.
+    pub(crate) fn get(&self, job_id: &str) -> Result<Arc<Job>> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(job_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown job: {job_id}"))
+    }
+
+    // Forget the job that finished first.
+    fn evict(jobs: &mut HashMap<String, Arc<Job>>) {
+        let oldest = jobs
+            .iter()
+            .filter_map(|(id, job)| Some((job.progress.lock().unwrap().finished_at?, id)))
+            .min()
+            .map(|(_, id)| id.clone());
+        if let Some(id) = oldest {
+            jobs.remove(&id);
+        }
+    }
+}
+
+/// The progress of a job, updated by the requests that belong to it.
+pub(crate) struct Job {
+    progress: Mutex<Progress>,
+}
+
+struct Progress {
+    status: JobStatus,
+    queued: usize,
+    active: usize,
+    finished_at: Option<Instant>,
+    // Cancels the requests of this job. It is replaced once every cancelled
+    // request has finished, so that the job ID can be used again.
+    token: CancellationToken,
+}
+
+impl Default for Job {
+    fn default() -> Self {
+        Self {
+            progress: Mutex::new(Progress {
+                status: JobStatus {
+                    state: JobState::Queued,
+                    segments_executed: 0,
+                    segments_proven: 0,
+                    recursion_steps: 0,
+                    error: None,
+                },
+                queued: 0,
+                active: 0,
+                finished_at: None,
+                token: CancellationToken::new(),
+            }),
+        }
+    }
+}
+
+impl Job {
+    /// Return the current status of this job.
+    pub(crate) fn status(&self) -> JobStatus {
+        self.progress.lock().unwrap().status.clone()
+    }
+
+    /// Return the token that cancels the requests of this job.
+    pub(crate) fn token(&self) -> CancellationToken {
+        self.progress.lock().unwrap().token.clone()
+    }
+
+    /// Cancel the requests of this job that are queued or running.
+    ///
+    /// Cancelling a job with no such requests has no effect, and requests
+    /// made after the cancelled ones have finished are not cancelled.
+    pub(crate) fn cancel(&self) {
+        let progress = self.progress.lock().unwrap();
+        if progress.queued > 0 || progress.active > 0 {
+            progress.token.cancel();
+        }
+    }
+
+    /// Mark a request of this job as waiting for a worker.
+    pub(crate) fn queue(&self) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.queued += 1;
+        if progress.active == 0 {
+            progress.status.state = JobState::Queued;
+        }
+    }
+
+    /// Run a request of this job, recording its outcome.
+    pub(crate) fn run<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.start();
+        let result = f();
+        self.finish(&result);
+        result
+    }
+
+    /// Mark a request of this job as started.
+    pub(crate) fn start(&self) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.queued = progress.queued.saturating_sub(1);
+        progress.active += 1;
+        progress.finished_at = None;
+        progress.status.state = JobState::Running;
+        progress.status.error = None;
+    }
+
+    /// Mark a request of this job as finished with the specified result.
+    ///
+    /// The state of a job with several requests reflects the most recent one
+    /// to finish.
+    pub(crate) fn finish<T>(&self, result: &Result<T>) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.active = progress.active.saturating_sub(1);
+        match result {
+            Ok(_) if progress.active > 0 => {}
+            Ok(_) => progress.status.state = JobState::Succeeded,
+            Err(_) if progress.token.is_cancelled() => progress.status.state = JobState::Cancelled,
+            Err(err) => {
+                progress.status.state = JobState::Failed;
+                progress.status.error = Some(err.to_string());
+            }
+        }
+        if progress.active == 0 {
+            progress.finished_at = Some(Instant::now());
+            if progress.queued == 0 && progress.token.is_cancelled() {
+                progress.token = CancellationToken::new();
+            }
+        }
+    }
+
+    pub(crate) fn add_segments_executed(&self, count: usize) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.status.segments_executed += count as u32;
+    }
+
+    pub(crate) fn add_segment_proven(&self) {
+        self.progress.lock().unwrap().status.segments_proven += 1;
+    }
+
+    pub(crate) fn add_recursion_step(&self) {
+        self.progress.lock().unwrap().status.recursion_steps += 1;
+    }
+}
+
+/// Counts the segments proven while proving a [crate::Session].
+pub(crate) struct JobHook(pub(crate) Arc<Job>);
+
+impl SessionEvents for JobHook {
+    fn on_post_prove_segment(&self, _segment: &Segment) {
+        self.0.add_segment_proven();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::{JobState, Jobs};
+
+    #[test]
+    fn lifecycle() {
+        let jobs = Jobs::default();
+        assert!(jobs.get("job").is_err());
+
+        let job = jobs.get_or_insert("job");
+        assert_eq!(job.status().state, JobState::Queued);
+
+        job.start();
+        job.add_segments_executed(2);
+        job.add_segment_proven();
+        assert_eq!(jobs.get("job").unwrap().status().state, JobState::Running);
+
+        job.finish(&Err::<(), _>(anyhow!("oops")));
+        let status = jobs.get("job").unwrap().status();
+        assert_eq!(status.state, JobState::Failed);
+        assert_eq!(status.error.as_deref(), Some("oops"));
+        assert_eq!(status.segments_executed, 2);
+        assert_eq!(status.segments_proven, 1);
+
+        job.start();
+        job.add_recursion_step();
+        job.finish(&Ok(()));
+        let status = job.status();
+        assert_eq!(status.state, JobState::Succeeded);
+        assert_eq!(status.error, None);
+        assert_eq!(status.recursion_steps, 1);
+
+        // A job with nothing queued or running is not cancelled.
+        job.cancel();
+        assert!(!job.token().is_cancelled());
+
+        job.queue();
+        job.cancel();
+        job.start();
+        assert!(job.token().is_cancelled());
+        job.finish(&Err::<(), _>(anyhow!("cancelled")));
+        assert_eq!(job.status().state, JobState::Cancelled);
+
+        // Once the cancelled requests have finished, the job can be used again.
+        assert!(!job.token().is_cancelled());
+        job.start();
+        job.finish(&Ok(()));
+        assert_eq!(job.status().state, JobState::Succeeded);
+    }
+
+    #[test]
+    fn untracked() {
+        let jobs = Jobs::default();
+        jobs.get_or_insert("").start();
+        assert!(jobs.get("").is_err());
+    }
+}
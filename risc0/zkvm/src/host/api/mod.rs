@@ -15,30 +15,39 @@
 pub(crate) mod client;
 pub(crate) mod convert;
 #[cfg(feature = "prove")]
+pub(crate) mod coordinator;
+#[cfg(feature = "prove")]
+pub(crate) mod daemon;
+#[cfg(feature = "prove")]
+pub(crate) mod jobs;
+#[cfg(feature = "prove")]
 pub(crate) mod server;
 #[cfg(test)]
 #[cfg(feature = "prove")]
 mod tests;
 
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
 use std::{
+    fmt,
     io::{Read, Write},
     net::{TcpListener, TcpStream},
     path::{Path, PathBuf},
     process::{Child, Command},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
         mpsc::channel,
         Arc,
     },
     thread,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, Context, Result};
 use bytes::{Buf, BufMut, Bytes};
 use prost::Message;
 
-use crate::{ExitCode, Journal};
+use crate::{CycleReport, ExitCode, Journal};
 
 mod pb {
     pub(crate) mod api {
@@ -56,12 +65,75 @@ const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 trait RootMessage: Message {}
 
-pub trait Connection {
-    fn stream(&self) -> &TcpStream;
+pub trait Connection: Send {
+    fn stream(&self) -> &Stream;
     fn close(&mut self) -> Result<i32>;
     fn try_clone(&self) -> Result<Box<dyn Connection>>;
 }
 
+/// The transport underlying a [Connection].
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> Result<Self> {
+        Ok(match self {
+            Stream::Tcp(stream) => Stream::Tcp(stream.try_clone()?),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Stream::Unix(stream.try_clone()?),
+        })
+    }
+}
+
+impl Read for &Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref stream) => {
+                let mut stream = stream;
+                stream.read(buf)
+            }
+            #[cfg(unix)]
+            Stream::Unix(ref stream) => {
+                let mut stream = stream;
+                stream.read(buf)
+            }
+        }
+    }
+}
+
+impl Write for &Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref stream) => {
+                let mut stream = stream;
+                stream.write(buf)
+            }
+            #[cfg(unix)]
+            Stream::Unix(ref stream) => {
+                let mut stream = stream;
+                stream.write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match *self {
+            Stream::Tcp(ref stream) => {
+                let mut stream = stream;
+                stream.flush()
+            }
+            #[cfg(unix)]
+            Stream::Unix(ref stream) => {
+                let mut stream = stream;
+                stream.flush()
+            }
+        }
+    }
+}
+
 pub struct ConnectionWrapper {
     inner: Box<dyn Connection>,
     buf: Vec<u8>,
@@ -82,6 +154,7 @@ impl RootMessage for pb::api::ResolveRequest {}
 impl RootMessage for pb::api::ResolveReply {}
 impl RootMessage for pb::api::IdentityP254Request {}
 impl RootMessage for pb::api::IdentityP254Reply {}
+impl RootMessage for pb::api::StatusReply {}
 
 impl ConnectionWrapper {
     fn new(inner: Box<dyn Connection>) -> Self {
@@ -123,6 +196,15 @@ impl ConnectionWrapper {
 pub trait Connector {
     /// Create a client-server connection
     fn connect(&self) -> Result<ConnectionWrapper>;
+
+    /// Whether the servers reached by this [Connector] share one job table
+    /// between connections, as a [crate::ApiDaemon] does.
+    ///
+    /// Jobs can only be queried and cancelled through a [Connector] that
+    /// tracks jobs. By default, a [Connector] does not track jobs.
+    fn tracks_jobs(&self) -> bool {
+        false
+    }
 }
 
 struct ParentProcessConnector {
@@ -147,6 +229,7 @@ impl ParentProcessConnector {
     }
 }
 
+// Each connection is served by a new child process, so jobs are not tracked.
 impl Connector for ParentProcessConnector {
     fn connect(&self) -> Result<ConnectionWrapper> {
         let addr = self.listener.local_addr()?;
@@ -189,7 +272,6 @@ struct TcpConnector {
 }
 
 impl TcpConnector {
-    #[cfg(feature = "prove")]
     pub(crate) fn new(addr: &str) -> Self {
         Self {
             addr: addr.to_string(),
@@ -203,25 +285,68 @@ impl Connector for TcpConnector {
         let stream = TcpStream::connect(&self.addr)?;
         Ok(ConnectionWrapper::new(Box::new(TcpConnection::new(stream))))
     }
+
+    // Sockets are served by an [crate::ApiDaemon].
+    fn tracks_jobs(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(unix)]
+struct UnixConnector {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixConnector {
+    pub(crate) fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Connector for UnixConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        tracing::debug!("connect");
+        let stream = UnixStream::connect(&self.path)?;
+        Ok(ConnectionWrapper::new(Box::new(UnixConnection::new(
+            stream,
+        ))))
+    }
+
+    // Sockets are served by an [crate::ApiDaemon].
+    fn tracks_jobs(&self) -> bool {
+        true
+    }
 }
 
 struct ParentProcessConnection {
     child: Child,
-    stream: TcpStream,
+    stream: Stream,
 }
 
 struct TcpConnection {
-    stream: TcpStream,
+    stream: Stream,
+}
+
+#[cfg(unix)]
+struct UnixConnection {
+    stream: Stream,
 }
 
 impl ParentProcessConnection {
     pub fn new(child: Child, stream: TcpStream) -> Self {
-        Self { child, stream }
+        Self {
+            child,
+            stream: Stream::Tcp(stream),
+        }
     }
 }
 
 impl Connection for ParentProcessConnection {
-    fn stream(&self) -> &TcpStream {
+    fn stream(&self) -> &Stream {
         &self.stream
     }
 
@@ -237,12 +362,14 @@ impl Connection for ParentProcessConnection {
 
 impl TcpConnection {
     pub fn new(stream: TcpStream) -> Self {
-        Self { stream }
+        Self {
+            stream: Stream::Tcp(stream),
+        }
     }
 }
 
 impl Connection for TcpConnection {
-    fn stream(&self) -> &TcpStream {
+    fn stream(&self) -> &Stream {
         &self.stream
     }
 
@@ -251,7 +378,35 @@ impl Connection for TcpConnection {
     }
 
     fn try_clone(&self) -> Result<Box<dyn Connection>> {
-        Ok(Box::new(Self::new(self.stream.try_clone()?)))
+        Ok(Box::new(Self {
+            stream: self.stream.try_clone()?,
+        }))
+    }
+}
+
+#[cfg(unix)]
+impl UnixConnection {
+    pub fn new(stream: UnixStream) -> Self {
+        Self {
+            stream: Stream::Unix(stream),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixConnection {
+    fn stream(&self) -> &Stream {
+        &self.stream
+    }
+
+    fn close(&mut self) -> Result<i32> {
+        Ok(0)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn Connection>> {
+        Ok(Box::new(Self {
+            stream: self.stream.try_clone()?,
+        }))
     }
 }
 
@@ -300,6 +455,12 @@ pub struct SessionInfo {
 
     /// The [ExitCode] of the session.
     pub exit_code: ExitCode,
+
+    /// The [CycleReport] collected during execution.
+    ///
+    /// A report is only collected if execution was started with
+    /// [crate::ExecutorEnvBuilder::enable_cycle_report].
+    pub cycle_report: Option<CycleReport>,
 }
 
 /// Provides information about a segment of execution.
@@ -313,6 +474,101 @@ pub struct SegmentInfo {
     pub cycles: u32,
 }
 
+/// Identifies a job on a zkVM server.
+///
+/// Requests made by a [client::Client] with a job ID set by
+/// [client::Client::with_job_id] are tracked by the server under that ID.
+/// Their progress can be queried with [client::Client::status], and they can
+/// be stopped with [client::Client::cancel].
+///
+/// Jobs are only tracked for as long as the server runs, so this is most
+/// useful with a long-lived server such as `r0vm --listen`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JobId(String);
+
+impl JobId {
+    /// Construct a new [JobId] that is unique to this process.
+    pub fn new() -> Self {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self(format!("{nanos:x}-{:x}-{count:x}", std::process::id()))
+    }
+
+    /// Return this ID as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for JobId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<String> for JobId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for JobId {
+    fn from(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The state of a job on a zkVM server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    /// The job is waiting for a free worker.
+    Queued,
+
+    /// The job is executing or proving.
+    Running,
+
+    /// The most recent request of the job succeeded.
+    Succeeded,
+
+    /// The most recent request of the job failed.
+    Failed,
+
+    /// The job was cancelled with [client::Client::cancel].
+    Cancelled,
+}
+
+/// Provides information about the progress of a job.
+///
+/// A job may span several requests, for example one `execute` followed by a
+/// `prove_segment` for each segment, and the counts add up across all of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobStatus {
+    /// The current state of the job.
+    pub state: JobState,
+
+    /// The number of segments executed.
+    pub segments_executed: u32,
+
+    /// The number of segments proven.
+    pub segments_proven: u32,
+
+    /// The number of lift, join, resolve and identity_p254 steps completed.
+    pub recursion_steps: u32,
+
+    /// The reason the job failed, if it did.
+    pub error: Option<String>,
+}
+
 impl Asset {
     /// Return the bytes for this asset.
     pub fn as_bytes(&self) -> Result<Bytes> {
@@ -16,21 +16,28 @@ use std::{
     error::Error as StdError,
     io::{BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 use prost::Message;
+use tempfile::tempdir;
 
-use super::{malformed_err, path_to_string, pb, ConnectionWrapper, Connector, TcpConnector};
+use super::{
+    jobs::{Job, JobHook, Jobs},
+    malformed_err, path_to_string, pb, ConnectionWrapper, Connector, TcpConnector,
+};
 use crate::{
     get_prover_server, get_version,
     host::{
-        client::slice_io::SliceIo, recursion::SuccinctReceipt, server::session::NullSegmentRef,
+        client::{env::SegmentPath, slice_io::SliceIo},
+        recursion::SuccinctReceipt,
+        server::session::NullSegmentRef,
     },
     receipt_claim::{MaybePruned, ReceiptClaim},
-    ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, Segment, SegmentReceipt, TraceCallback,
-    TraceEvent, VerifierContext,
+    CancellationToken, ExecutorEnv, ExecutorImpl, FileSegmentRef, ProverOpts, Receipt, Segment,
+    SegmentReceipt, TraceCallback, TraceEvent, VerifierContext,
 };
 
 /// A server implementation for handling requests by clients of the zkVM.
@@ -204,10 +211,26 @@ impl Server {
     }
 
     /// Start the [Server] and run until all requests are complete.
+    ///
+    /// A [Server] handles a single request, so it does not keep track of
+    /// jobs: status and cancel requests for a job only succeed against an
+    /// [crate::ApiDaemon], which shares one job table between its connections.
     pub fn run(&self) -> Result<()> {
         tracing::debug!("connect");
         let mut conn = self.connector.connect()?;
+        Self::handshake(&mut conn, None)?;
+
+        let request: pb::api::ServerRequest = conn.recv()?;
+        tracing::trace!("rx: {request:?}");
+        Self::dispatch(conn, request, &Jobs::default())
+    }
 
+    /// Receive the [pb::api::HelloRequest] of a client and check that its
+    /// version is compatible.
+    ///
+    /// If `reject` is set, the client is sent this reason instead and the
+    /// connection is refused.
+    pub(crate) fn handshake(conn: &mut ConnectionWrapper, reject: Option<String>) -> Result<()> {
         let server_version = get_version().map_err(|err| anyhow!(err))?;
 
         let request: pb::api::HelloRequest = conn.recv()?;
@@ -218,11 +241,22 @@ impl Server {
             .ok_or(malformed_err())?
             .try_into()
             .map_err(|err: semver::Error| anyhow!(err))?;
-        if !check_client_version(&client_version, &server_version) {
-            let msg = format!(
-                "incompatible client version: {client_version}, server version: {server_version}"
-            );
+        let reject = reject.or_else(|| {
+            (!check_client_version(&client_version, &server_version)).then(|| {
+                format!(
+                    "incompatible client version: {client_version}, server version: {server_version}"
+                )
+            })
+        });
+        if let Some(msg) = reject {
             tracing::debug!("{msg}");
+            let reply = pb::api::HelloReply {
+                kind: Some(pb::api::hello_reply::Kind::Error(pb::api::GenericError {
+                    reason: msg.clone(),
+                })),
+            };
+            tracing::trace!("tx: {reply:?}");
+            conn.send(reply)?;
             bail!(msg);
         }
 
@@ -232,36 +266,90 @@ impl Server {
             })),
         };
         tracing::trace!("tx: {reply:?}");
-        conn.send(reply)?;
+        conn.send(reply)
+    }
 
-        let request: pb::api::ServerRequest = conn.recv()?;
-        tracing::trace!("rx: {request:?}");
+    /// Handle a single request of a client.
+    ///
+    /// Requests with a job ID are tracked in `jobs`.
+    pub(crate) fn dispatch(
+        conn: ConnectionWrapper,
+        request: pb::api::ServerRequest,
+        jobs: &Jobs,
+    ) -> Result<()> {
         match request.kind.ok_or(malformed_err())? {
-            pb::api::server_request::Kind::Prove(request) => self.on_prove(conn, request),
-            pb::api::server_request::Kind::Execute(request) => self.on_execute(conn, request),
+            pb::api::server_request::Kind::Prove(request) => {
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_prove(conn, request, &job)
+            }
+            pb::api::server_request::Kind::Execute(request) => {
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_execute(conn, request, &job)
+            }
             pb::api::server_request::Kind::ProveSegment(request) => {
-                self.on_prove_segment(conn, request)
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_prove_segment(conn, request, &job)
+            }
+            pb::api::server_request::Kind::Lift(request) => {
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_lift(conn, request, &job)
+            }
+            pb::api::server_request::Kind::Join(request) => {
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_join(conn, request, &job)
+            }
+            pb::api::server_request::Kind::Resolve(request) => {
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_resolve(conn, request, &job)
             }
-            pb::api::server_request::Kind::Lift(request) => self.on_lift(conn, request),
-            pb::api::server_request::Kind::Join(request) => self.on_join(conn, request),
-            pb::api::server_request::Kind::Resolve(request) => self.on_resolve(conn, request),
             pb::api::server_request::Kind::IdentiyP254(request) => {
-                self.on_identity_p254(conn, request)
+                let job = jobs.get_or_insert(&request.job_id);
+                Self::on_identity_p254(conn, request, &job)
             }
+            pb::api::server_request::Kind::Status(request) => Self::on_status(conn, request, jobs),
+            pb::api::server_request::Kind::Cancel(request) => Self::on_cancel(conn, request, jobs),
         }
     }
 
+    fn on_status(
+        mut conn: ConnectionWrapper,
+        request: pb::api::StatusRequest,
+        jobs: &Jobs,
+    ) -> Result<()> {
+        let msg = pb::api::StatusReply {
+            kind: Some(match jobs.get(&request.job_id) {
+                Ok(job) => pb::api::status_reply::Kind::Ok(job.status().into()),
+                Err(err) => pb::api::status_reply::Kind::Error(err.into()),
+            }),
+        };
+
+        tracing::trace!("tx: {msg:?}");
+        conn.send(msg)
+    }
+
+    fn on_cancel(
+        mut conn: ConnectionWrapper,
+        request: pb::api::CancelRequest,
+        jobs: &Jobs,
+    ) -> Result<()> {
+        let msg: pb::api::GenericReply = jobs.get(&request.job_id).map(|job| job.cancel()).into();
+
+        tracing::trace!("tx: {msg:?}");
+        conn.send(msg)
+    }
+
     fn on_execute(
-        &self,
         mut conn: ConnectionWrapper,
         request: pb::api::ExecuteRequest,
+        job: &Job,
     ) -> Result<()> {
         fn inner(
             conn: &mut ConnectionWrapper,
             request: pb::api::ExecuteRequest,
+            job: &Job,
         ) -> Result<pb::api::ServerReply> {
             let env_request = request.env.ok_or(malformed_err())?;
-            let env = build_env(&conn, &env_request)?;
+            let env = build_env(&conn, &env_request, &job.token())?;
 
             let binary = env_request.binary.ok_or(malformed_err())?;
 
@@ -299,6 +387,7 @@ impl Server {
                 if let pb::api::generic_reply::Kind::Error(err) = kind {
                     bail!(err)
                 }
+                job.add_segments_executed(1);
 
                 Ok(Box::new(NullSegmentRef))
             })?;
@@ -311,6 +400,7 @@ impl Server {
                                 segments: session.segments.len().try_into()?,
                                 journal: session.journal.unwrap_or_default().bytes,
                                 exit_code: Some(session.exit_code.into()),
+                                cycle_report: session.cycle_report.map(Into::into),
                             }),
                         },
                     )),
@@ -318,31 +408,48 @@ impl Server {
             })
         }
 
-        let msg = inner(&mut conn, request).unwrap_or_else(|err| pb::api::ServerReply {
-            kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = job
+            .run(|| inner(&mut conn, request, job))
+            .unwrap_or_else(|err| pb::api::ServerReply {
+                kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                })),
+            });
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
     }
 
-    fn on_prove(&self, mut conn: ConnectionWrapper, request: pb::api::ProveRequest) -> Result<()> {
+    fn on_prove(
+        mut conn: ConnectionWrapper,
+        request: pb::api::ProveRequest,
+        job: &Arc<Job>,
+    ) -> Result<()> {
         fn inner(
             conn: &mut ConnectionWrapper,
             request: pb::api::ProveRequest,
+            job: &Arc<Job>,
         ) -> Result<pb::api::ServerReply> {
+            let receipt_kind = request.receipt_kind().into();
             let env_request = request.env.ok_or(malformed_err())?;
-            let env = build_env(&conn, &env_request)?;
+            let env = build_env(&conn, &env_request, &job.token())?;
 
             let binary = env_request.binary.ok_or(malformed_err())?;
             let bytes = binary.as_bytes()?;
 
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+            let opts = prover_opts(request.opts, job)?;
             let prover = get_prover_server(&opts)?;
             let ctx = VerifierContext::default();
-            let receipt = prover.prove_with_ctx(env, &ctx, &bytes)?;
+            let mut exec = ExecutorImpl::from_elf(env, &bytes)?;
+            let segment_path = SegmentPath::TempDir(Arc::new(tempdir()?));
+            let mut session = exec.run_with_callback(|segment| {
+                let segment_ref = FileSegmentRef::new(&segment, &segment_path)?;
+                job.add_segments_executed(1);
+                Ok(Box::new(segment_ref))
+            })?;
+            session.add_hook(JobHook(job.clone()));
+            let receipt = prover.prove_session(&ctx, &session)?;
+            let receipt = prover.compress_to(&receipt, receipt_kind)?;
 
             let receipt_pb: pb::core::Receipt = receipt.into();
             let receipt_bytes = receipt_pb.encode_to_vec();
@@ -363,29 +470,35 @@ impl Server {
             })
         }
 
-        let msg = inner(&mut conn, request).unwrap_or_else(|err| pb::api::ServerReply {
-            kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = job
+            .run(|| inner(&mut conn, request, job))
+            .unwrap_or_else(|err| pb::api::ServerReply {
+                kind: Some(pb::api::server_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                })),
+            });
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
     }
 
     fn on_prove_segment(
-        &self,
         mut conn: ConnectionWrapper,
         request: pb::api::ProveSegmentRequest,
+        job: &Job,
     ) -> Result<()> {
-        fn inner(request: pb::api::ProveSegmentRequest) -> Result<pb::api::ProveSegmentReply> {
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+        fn inner(
+            request: pb::api::ProveSegmentRequest,
+            job: &Job,
+        ) -> Result<pb::api::ProveSegmentReply> {
+            let opts = prover_opts(request.opts, job)?;
             let segment_bytes = request.segment.ok_or(malformed_err())?.as_bytes()?;
             let segment: Segment = bincode::deserialize(&segment_bytes)?;
 
             let prover = get_prover_server(&opts)?;
             let ctx = VerifierContext::default();
             let receipt = prover.prove_segment(&ctx, &segment)?;
+            job.add_segment_proven();
 
             let receipt_pb: pb::core::SegmentReceipt = receipt.into();
             let receipt_bytes = receipt_pb.encode_to_vec();
@@ -404,26 +517,33 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ProveSegmentReply {
-            kind: Some(pb::api::prove_segment_reply::Kind::Error(
-                pb::api::GenericError {
-                    reason: err.to_string(),
-                },
-            )),
-        });
+        let msg =
+            job.run(|| inner(request, job))
+                .unwrap_or_else(|err| pb::api::ProveSegmentReply {
+                    kind: Some(pb::api::prove_segment_reply::Kind::Error(
+                        pb::api::GenericError {
+                            reason: err.to_string(),
+                        },
+                    )),
+                });
 
         tracing::trace!("tx: {msg:?}");
         conn.send(msg)
     }
 
-    fn on_lift(&self, mut conn: ConnectionWrapper, request: pb::api::LiftRequest) -> Result<()> {
-        fn inner(request: pb::api::LiftRequest) -> Result<pb::api::LiftReply> {
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+    fn on_lift(
+        mut conn: ConnectionWrapper,
+        request: pb::api::LiftRequest,
+        job: &Job,
+    ) -> Result<()> {
+        fn inner(request: pb::api::LiftRequest, job: &Job) -> Result<pb::api::LiftReply> {
+            let opts = prover_opts(request.opts, job)?;
             let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
             let segment_receipt: SegmentReceipt = bincode::deserialize(&receipt_bytes)?;
 
             let prover = get_prover_server(&opts)?;
             let receipt = prover.lift(&segment_receipt)?;
+            job.add_recursion_step();
 
             let succinct_receipt_pb: pb::core::SuccinctReceipt = receipt.into();
             let succinct_receipt_bytes = succinct_receipt_pb.encode_to_vec();
@@ -440,19 +560,25 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::LiftReply {
-            kind: Some(pb::api::lift_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = job
+            .run(|| inner(request, job))
+            .unwrap_or_else(|err| pb::api::LiftReply {
+                kind: Some(pb::api::lift_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                })),
+            });
 
         tracing::debug!("tx: {msg:?}");
         conn.send(msg)
     }
 
-    fn on_join(&self, mut conn: ConnectionWrapper, request: pb::api::JoinRequest) -> Result<()> {
-        fn inner(request: pb::api::JoinRequest) -> Result<pb::api::JoinReply> {
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+    fn on_join(
+        mut conn: ConnectionWrapper,
+        request: pb::api::JoinRequest,
+        job: &Job,
+    ) -> Result<()> {
+        fn inner(request: pb::api::JoinRequest, job: &Job) -> Result<pb::api::JoinReply> {
+            let opts = prover_opts(request.opts, job)?;
             let left_receipt_bytes = request.left_receipt.ok_or(malformed_err())?.as_bytes()?;
             let left_succinct_receipt: SuccinctReceipt = bincode::deserialize(&left_receipt_bytes)?;
             let right_receipt_bytes = request.right_receipt.ok_or(malformed_err())?.as_bytes()?;
@@ -461,6 +587,7 @@ impl Server {
 
             let prover = get_prover_server(&opts)?;
             let receipt = prover.join(&left_succinct_receipt, &right_succinct_receipt)?;
+            job.add_recursion_step();
 
             let succinct_receipt_pb: pb::core::SuccinctReceipt = receipt.into();
             let succinct_receipt_bytes = succinct_receipt_pb.encode_to_vec();
@@ -477,23 +604,25 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::JoinReply {
-            kind: Some(pb::api::join_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = job
+            .run(|| inner(request, job))
+            .unwrap_or_else(|err| pb::api::JoinReply {
+                kind: Some(pb::api::join_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                })),
+            });
 
         tracing::debug!("tx: {msg:?}");
         conn.send(msg)
     }
 
     fn on_resolve(
-        &self,
         mut conn: ConnectionWrapper,
         request: pb::api::ResolveRequest,
+        job: &Job,
     ) -> Result<()> {
-        fn inner(request: pb::api::ResolveRequest) -> Result<pb::api::ResolveReply> {
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+        fn inner(request: pb::api::ResolveRequest, job: &Job) -> Result<pb::api::ResolveReply> {
+            let opts = prover_opts(request.opts, job)?;
             let conditional_receipt_bytes = request
                 .conditional_receipt
                 .ok_or(malformed_err())?
@@ -510,6 +639,7 @@ impl Server {
             let prover = get_prover_server(&opts)?;
             let receipt =
                 prover.resolve(&conditional_succinct_receipt, &assumption_succinct_receipt)?;
+            job.add_recursion_step();
 
             let succinct_receipt_pb: pb::core::SuccinctReceipt = receipt.into();
             let succinct_receipt_bytes = succinct_receipt_pb.encode_to_vec();
@@ -526,28 +656,34 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::ResolveReply {
-            kind: Some(pb::api::resolve_reply::Kind::Error(pb::api::GenericError {
-                reason: err.to_string(),
-            })),
-        });
+        let msg = job
+            .run(|| inner(request, job))
+            .unwrap_or_else(|err| pb::api::ResolveReply {
+                kind: Some(pb::api::resolve_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                })),
+            });
 
         tracing::debug!("tx: {msg:?}");
         conn.send(msg)
     }
 
     fn on_identity_p254(
-        &self,
         mut conn: ConnectionWrapper,
         request: pb::api::IdentityP254Request,
+        job: &Job,
     ) -> Result<()> {
-        fn inner(request: pb::api::IdentityP254Request) -> Result<pb::api::IdentityP254Reply> {
-            let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+        fn inner(
+            request: pb::api::IdentityP254Request,
+            job: &Job,
+        ) -> Result<pb::api::IdentityP254Reply> {
+            let opts = prover_opts(request.opts, job)?;
             let receipt_bytes = request.receipt.ok_or(malformed_err())?.as_bytes()?;
             let succinct_receipt: SuccinctReceipt = bincode::deserialize(&receipt_bytes)?;
 
             let prover = get_prover_server(&opts)?;
             let receipt = prover.identity_p254(&succinct_receipt)?;
+            job.add_recursion_step();
 
             let succinct_receipt_pb: pb::core::SuccinctReceipt = receipt.into();
             let succinct_receipt_bytes = succinct_receipt_pb.encode_to_vec();
@@ -566,24 +702,35 @@ impl Server {
             })
         }
 
-        let msg = inner(request).unwrap_or_else(|err| pb::api::IdentityP254Reply {
-            kind: Some(pb::api::identity_p254_reply::Kind::Error(
-                pb::api::GenericError {
-                    reason: err.to_string(),
-                },
-            )),
-        });
+        let msg =
+            job.run(|| inner(request, job))
+                .unwrap_or_else(|err| pb::api::IdentityP254Reply {
+                    kind: Some(pb::api::identity_p254_reply::Kind::Error(
+                        pb::api::GenericError {
+                            reason: err.to_string(),
+                        },
+                    )),
+                });
 
         tracing::debug!("tx: {msg:?}");
         conn.send(msg)
     }
 }
 
+// Proving is cancelled along with the job.
+fn prover_opts(opts: Option<pb::api::ProverOpts>, job: &Job) -> Result<ProverOpts> {
+    let mut opts: ProverOpts = opts.ok_or(malformed_err())?.into();
+    opts.cancellation_token = Some(job.token());
+    Ok(opts)
+}
+
 fn build_env<'a>(
     conn: &ConnectionWrapper,
     request: &pb::api::ExecutorEnv,
+    token: &CancellationToken,
 ) -> Result<ExecutorEnv<'a>> {
     let mut env_builder = ExecutorEnv::builder();
+    env_builder.cancellation_token(token.clone());
     env_builder.env_vars(request.env_vars.clone());
     env_builder.args(&request.args);
     for fd in request.read_fds.iter() {
@@ -603,6 +750,9 @@ fn build_env<'a>(
         env_builder.segment_limit_po2(segment_limit_po2);
     }
     env_builder.session_limit(request.session_limit);
+    if request.cycle_report {
+        env_builder.enable_cycle_report();
+    }
     if let Some(_) = request.trace_events {
         let proxy = TraceProxy::new(conn.try_clone()?);
         env_builder.trace_callback(proxy);
@@ -18,7 +18,7 @@ use std::{
     thread,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use risc0_zkvm_methods::{
     multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
     MULTI_TEST_PATH,
@@ -26,10 +26,14 @@ use risc0_zkvm_methods::{
 use tempfile::{tempdir, TempDir};
 use test_log::test;
 
-use super::{Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnection};
+use super::{
+    pb, Asset, AssetRequest, ConnectionWrapper, Connector, JobId, JobState, TcpConnection,
+    TcpConnector,
+};
 use crate::{
-    recursion::SuccinctReceipt, ApiClient, ApiServer, ExecutorEnv, InnerReceipt, ProverOpts,
-    Receipt, SegmentReceipt, SessionInfo, VerifierContext,
+    get_version, recursion::SuccinctReceipt, ApiClient, ApiCoordinator, ApiDaemon, ApiServer,
+    DaemonOpts, ExecutorEnv, ExecutorImpl, InnerReceipt, Journal, ProverOpts, Receipt,
+    SegmentReceipt, SessionInfo, VerifierContext,
 };
 
 struct TestClientConnector {
@@ -316,3 +320,319 @@ fn guest_error_forwarding() {
     let binary = Asset::Inline(MULTI_TEST_ELF.into());
     TestClient::new().execute(env, binary);
 }
+
+fn spawn_daemon(daemon: ApiDaemon) {
+    thread::Builder::new()
+        .name("daemon".into())
+        .spawn(move || daemon.run().unwrap())
+        .unwrap();
+}
+
+fn execute_do_nothing(client: &ApiClient) -> Result<SessionInfo> {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    client.execute(&env, binary, AssetRequest::Inline, |_info, _asset| Ok(()))
+}
+
+#[test]
+fn daemon_tcp() {
+    let opts = DaemonOpts {
+        max_connections: 8,
+        workers: 2,
+    };
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", opts).unwrap();
+    let addr = format!("127.0.0.1:{}", daemon.port().unwrap());
+    spawn_daemon(daemon);
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let addr = addr.clone();
+            thread::spawn(move || {
+                let client = ApiClient::new_tcp(addr);
+                let mut segments = Vec::new();
+                let env = ExecutorEnv::builder()
+                    .write(&MultiTestSpec::DoNothing)
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                let binary = Asset::Inline(MULTI_TEST_ELF.into());
+                client
+                    .execute(&env, binary, AssetRequest::Inline, |_info, asset| {
+                        segments.push(asset);
+                        Ok(())
+                    })
+                    .unwrap();
+                let opts = ProverOpts::fast();
+                let receipt = client
+                    .prove_segment(opts, segments[0].clone(), AssetRequest::Inline)
+                    .unwrap();
+                receipt
+                    .verify_integrity_with_context(&VerifierContext::default())
+                    .unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon_unix() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("r0vm.sock");
+    let daemon = ApiDaemon::bind_unix(&path, DaemonOpts::default()).unwrap();
+    spawn_daemon(daemon);
+
+    let client = ApiClient::new_unix(&path);
+    let session = execute_do_nothing(&client).unwrap();
+    assert_eq!(session.segments.len(), 1);
+
+    // A second daemon does not take over the socket of a live one.
+    let err = ApiDaemon::bind_unix(&path, DaemonOpts::default())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("already listening"), "{err}");
+    execute_do_nothing(&client).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon_unix_stale_socket() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("r0vm.sock");
+    // A socket that nothing listens on, as left behind by a daemon that exited.
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let daemon = ApiDaemon::bind_unix(&path, DaemonOpts::default()).unwrap();
+    spawn_daemon(daemon);
+    execute_do_nothing(&ApiClient::new_unix(&path)).unwrap();
+}
+
+#[test]
+fn daemon_connection_limit() {
+    let opts = DaemonOpts {
+        max_connections: 1,
+        workers: 1,
+    };
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", opts).unwrap();
+    let addr = format!("127.0.0.1:{}", daemon.port().unwrap());
+    spawn_daemon(daemon);
+
+    // Hold the only connection open after the handshake.
+    let mut conn = TcpConnector::new(&addr).connect().unwrap();
+    conn.send(pb::api::HelloRequest {
+        version: Some(get_version().unwrap().into()),
+    })
+    .unwrap();
+    let reply: pb::api::HelloReply = conn.recv().unwrap();
+    assert!(matches!(
+        reply.kind,
+        Some(pb::api::hello_reply::Kind::Ok(_))
+    ));
+
+    let client = ApiClient::new_tcp(&addr);
+    let err = execute_do_nothing(&client).unwrap_err();
+    assert!(err.to_string().contains("connection limit"), "{err}");
+
+    drop(conn);
+    // The slot is released once the daemon notices the closed connection.
+    let session = (0..50)
+        .find_map(|_| {
+            thread::sleep(std::time::Duration::from_millis(100));
+            execute_do_nothing(&client).ok()
+        })
+        .unwrap();
+    assert_eq!(session.segments.len(), 1);
+}
+
+#[test]
+fn daemon_version_mismatch() {
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", DaemonOpts::default()).unwrap();
+    let addr = format!("127.0.0.1:{}", daemon.port().unwrap());
+    spawn_daemon(daemon);
+
+    let mut conn = TcpConnector::new(&addr).connect().unwrap();
+    conn.send(pb::api::HelloRequest {
+        version: Some(semver::Version::new(0, 1, 0).into()),
+    })
+    .unwrap();
+    let reply: pb::api::HelloReply = conn.recv().unwrap();
+    match reply.kind {
+        Some(pb::api::hello_reply::Kind::Error(err)) => {
+            assert!(
+                err.reason.contains("incompatible client version"),
+                "{}",
+                err.reason
+            )
+        }
+        kind => panic!("unexpected reply: {kind:?}"),
+    }
+}
+
+#[test]
+fn daemon_job_status() {
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", DaemonOpts::default()).unwrap();
+    let addr = format!("127.0.0.1:{}", daemon.port().unwrap());
+    spawn_daemon(daemon);
+
+    let job_id = JobId::new();
+    let client = ApiClient::new_tcp(&addr).with_job_id(job_id.clone());
+    let session = execute_do_nothing(&client).unwrap();
+
+    // The status can be queried by any client.
+    let status = ApiClient::new_tcp(&addr).status(&job_id).unwrap();
+    assert_eq!(status.state, JobState::Succeeded);
+    assert_eq!(status.segments_executed as usize, session.segments.len());
+    assert_eq!(status.segments_proven, 0);
+    assert_eq!(status.error, None);
+
+    let err = client.status(&JobId::new()).unwrap_err();
+    assert!(err.to_string().contains("unknown job"), "{err}");
+}
+
+#[test]
+fn daemon_job_cancel() {
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", DaemonOpts::default()).unwrap();
+    let addr = format!("127.0.0.1:{}", daemon.port().unwrap());
+    spawn_daemon(daemon);
+
+    let job_id = JobId::new();
+    let client = ApiClient::new_tcp(&addr).with_job_id(job_id.clone());
+    assert!(client.cancel(&job_id).is_err());
+
+    execute_do_nothing(&client).unwrap();
+
+    // Cancelling a job with nothing queued or running does not stop its later
+    // requests.
+    client.cancel(&job_id).unwrap();
+    execute_do_nothing(&client).unwrap();
+    let status = client.status(&job_id).unwrap();
+    assert_eq!(status.state, JobState::Succeeded);
+}
+
+#[test]
+fn sub_process_jobs_unsupported() {
+    // Each request to a sub-process is served by a new server, which cannot track jobs.
+    let job_id = JobId::new();
+    let client = ApiClient::new_sub_process("r0vm-not-spawned")
+        .unwrap()
+        .with_job_id(job_id.clone());
+
+    let err = client.status(&job_id).unwrap_err();
+    assert!(err.to_string().contains("API daemon"), "{err}");
+    let err = client.cancel(&job_id).unwrap_err();
+    assert!(err.to_string().contains("API daemon"), "{err}");
+    let err = execute_do_nothing(&client).unwrap_err();
+    assert!(err.to_string().contains("API daemon"), "{err}");
+}
+
+struct UnavailableConnector;
+
+impl Connector for UnavailableConnector {
+    fn connect(&self) -> Result<ConnectionWrapper> {
+        bail!("endpoint unavailable")
+    }
+}
+
+#[test]
+fn coordinator() {
+    let segment_limit_po2 = 16; // 64k cycles
+    let cycles = 1 << segment_limit_po2;
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles })
+        .unwrap()
+        .segment_limit_po2(segment_limit_po2)
+        .build()
+        .unwrap();
+
+    let mut coordinator =
+        ApiCoordinator::new(ProverOpts::default()).with_endpoint(UnavailableConnector);
+    for _ in 0..2 {
+        let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", DaemonOpts::default()).unwrap();
+        coordinator =
+            coordinator.with_tcp_endpoint(format!("127.0.0.1:{}", daemon.port().unwrap()));
+        spawn_daemon(daemon);
+    }
+
+    let receipt = coordinator.prove(env, MULTI_TEST_ELF).unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[test]
+fn coordinator_attempts_exhausted() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let coordinator = ApiCoordinator::new(ProverOpts::default())
+        .with_endpoint(UnavailableConnector)
+        .with_endpoint(UnavailableConnector)
+        .with_max_attempts(2);
+    let err = coordinator.prove_session(&session).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("segment 0 failed after 2 attempts"),
+        "{err}"
+    );
+
+    let coordinator = ApiCoordinator::new(ProverOpts::default());
+    assert!(coordinator.prove_session(&session).is_err());
+}
+
+#[test]
+fn coordinator_session_claim_mismatch() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    // The segments do not commit to this journal.
+    session.journal = Some(Journal::new(vec![1, 2, 3, 4]));
+
+    let daemon = ApiDaemon::bind_tcp("127.0.0.1:0", DaemonOpts::default()).unwrap();
+    let coordinator = ApiCoordinator::new(ProverOpts::default())
+        .with_tcp_endpoint(format!("127.0.0.1:{}", daemon.port().unwrap()));
+    spawn_daemon(daemon);
+    let err = coordinator.prove_session(&session).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("session and receipt claim do not match"),
+        "{err}"
+    );
+}
+
+#[test]
+fn coordinator_endpoint_always_fails() {
+    // Enough segments that the executor is still producing them when proving
+    // fails, and has to stop rather than wait for the queue to drain.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 17 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+
+    let coordinator = ApiCoordinator::new(ProverOpts::default())
+        .with_endpoint(UnavailableConnector)
+        .with_max_attempts(2);
+    let err = coordinator.prove(env, MULTI_TEST_ELF).unwrap_err();
+    assert!(err.to_string().contains("failed after 2 attempts"), "{err}");
+}
@@ -0,0 +1,129 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cooperative cancellation of execution and proving.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// A token used to cancel a running execution or proof from another thread.
+///
+/// A [CancellationToken] is cheap to clone, and all clones share the same
+/// state. Pass it to [crate::ExecutorEnvBuilder::cancellation_token] to cancel
+/// execution, and proving of that execution by a local prover, or set it as
+/// [crate::ProverOpts::cancellation_token] to cancel proving.
+/// Cancellation is cooperative: the executor checks the token
+/// periodically between instructions, and the prover checks it before proving
+/// each segment and before each recursion step. Once cancelled, the operation
+/// fails with a [CancellationError].
+///
+/// A token may also carry a wall-clock deadline, after which it behaves as if
+/// it were cancelled.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use risc0_zkvm::{CancellationError, CancellationToken, ExecutorEnv};
+///
+/// let token = CancellationToken::with_timeout(Duration::from_secs(60));
+/// let env = ExecutorEnv::builder()
+///     .cancellation_token(token.clone())
+///     .build()
+///     .unwrap();
+///
+/// // From another thread:
+/// token.cancel();
+/// assert_eq!(token.check(), Err(CancellationError::Cancelled));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Construct a [CancellationToken] without a deadline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a [CancellationToken] that expires at `deadline`.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Default::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Construct a [CancellationToken] that expires `timeout` from now.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Cancel this token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Return true if this token has been cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.check().is_err()
+    }
+
+    /// Return an error if this token has been cancelled or its deadline has
+    /// passed.
+    pub fn check(&self) -> Result<(), CancellationError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CancellationError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(CancellationError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when an operation is stopped by a [CancellationToken].
+///
+/// Operations return this wrapped in an [anyhow::Error]; use
+/// [anyhow::Error::downcast_ref] to distinguish it from other failures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancellationError {
+    /// The token was cancelled with [CancellationToken::cancel].
+    Cancelled,
+    /// The deadline of the token passed.
+    DeadlineExceeded,
+}
+
+impl fmt::Display for CancellationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CancellationError::Cancelled => write!(f, "operation was cancelled"),
+            CancellationError::DeadlineExceeded => write!(f, "operation deadline exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for CancellationError {}
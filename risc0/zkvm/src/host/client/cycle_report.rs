@@ -0,0 +1,165 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A structured breakdown of the cycles spent executing a guest.
+
+use serde::{Deserialize, Serialize};
+
+/// Cycles spent in the guest, broken down by function and by call stack.
+///
+/// A [CycleReport] is collected when execution is started with
+/// [crate::ExecutorEnvBuilder::enable_cycle_report], and is available from
+/// [crate::SessionInfo::cycle_report]. It is built from the same call stack
+/// tracking as the pprof output of [crate::ExecutorEnvBuilder::enable_profiler],
+/// and so only covers functions with symbols in the guest ELF.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct CycleReport {
+    /// The total number of cycles attributed to any call stack.
+    pub total_cycles: u64,
+
+    /// Cycles per function, sorted by descending [FunctionCycles::total_cycles].
+    pub functions: Vec<FunctionCycles>,
+
+    /// Cycles per unique call stack, sorted by descending [StackCycles::cycles].
+    pub stacks: Vec<StackCycles>,
+}
+
+/// Cycles attributed to a single function.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FunctionCycles {
+    /// The demangled name of the function.
+    pub name: String,
+
+    /// Cycles spent in the body of this function, excluding its callees.
+    pub self_cycles: u64,
+
+    /// Cycles spent in this function, including its callees.
+    ///
+    /// Cycles spent in recursive calls are only counted once.
+    pub total_cycles: u64,
+}
+
+/// Cycles attributed to a unique call stack.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StackCycles {
+    /// Names of the functions on the call stack, from the outermost caller to
+    /// the function the cycles were spent in.
+    pub frames: Vec<String>,
+
+    /// Cycles spent with exactly this call stack.
+    pub cycles: u64,
+}
+
+impl CycleReport {
+    /// Construct a [CycleReport] from the cycles spent at each call stack.
+    ///
+    /// Samples with an identical call stack are merged.
+    #[cfg(any(feature = "prove", test))]
+    pub(crate) fn from_stacks(samples: impl IntoIterator<Item = (Vec<String>, u64)>) -> Self {
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let mut stacks: BTreeMap<Vec<String>, u64> = BTreeMap::new();
+        for (frames, cycles) in samples {
+            if !frames.is_empty() {
+                *stacks.entry(frames).or_default() += cycles;
+            }
+        }
+
+        let mut functions: BTreeMap<&str, FunctionCycles> = BTreeMap::new();
+        for (frames, &cycles) in stacks.iter() {
+            let unique: BTreeSet<&str> = frames.iter().map(String::as_str).collect();
+            for name in unique {
+                functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionCycles {
+                        name: name.to_string(),
+                        self_cycles: 0,
+                        total_cycles: 0,
+                    })
+                    .total_cycles += cycles;
+            }
+            if let Some(leaf) = frames.last() {
+                functions.get_mut(leaf.as_str()).unwrap().self_cycles += cycles;
+            }
+        }
+
+        let mut functions: Vec<_> = functions.into_values().collect();
+        functions.sort_by(|a, b| b.total_cycles.cmp(&a.total_cycles));
+
+        let mut stacks: Vec<_> = stacks
+            .into_iter()
+            .map(|(frames, cycles)| StackCycles { frames, cycles })
+            .collect();
+        stacks.sort_by(|a, b| b.cycles.cmp(&a.cycles));
+
+        Self {
+            total_cycles: stacks.iter().map(|x| x.cycles).sum(),
+            functions,
+            stacks,
+        }
+    }
+
+    /// Return the cycles attributed to the function with the given name.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use risc0_zkvm::SessionInfo;
+    /// # fn check(info: SessionInfo) {
+    /// let report = info.cycle_report.as_ref().unwrap();
+    /// let hash = report.function("my_guest::hash_leaves").unwrap();
+    /// assert!(hash.total_cycles < 1_000_000);
+    /// # }
+    /// ```
+    pub fn function(&self, name: &str) -> Option<&FunctionCycles> {
+        self.functions.iter().find(|x| x.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CycleReport;
+
+    fn stack(frames: &[&str]) -> Vec<String> {
+        frames.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn from_stacks() {
+        let report = CycleReport::from_stacks([
+            (stack(&["main", "a"]), 10),
+            (stack(&["main", "a", "b"]), 5),
+            (stack(&["main", "b"]), 3),
+            (stack(&["main", "a"]), 2),
+            (stack(&["main", "r", "r"]), 7),
+            (stack(&[]), 100),
+        ]);
+
+        assert_eq!(report.total_cycles, 27);
+        assert_eq!(report.stacks.len(), 4);
+        assert_eq!(report.stacks[0].frames, stack(&["main", "a"]));
+        assert_eq!(report.stacks[0].cycles, 12);
+
+        let main = report.function("main").unwrap();
+        assert_eq!((main.self_cycles, main.total_cycles), (0, 27));
+        let a = report.function("a").unwrap();
+        assert_eq!((a.self_cycles, a.total_cycles), (12, 17));
+        let b = report.function("b").unwrap();
+        assert_eq!((b.self_cycles, b.total_cycles), (8, 8));
+        let r = report.function("r").unwrap();
+        assert_eq!((r.self_cycles, r.total_cycles), (7, 7));
+        assert_eq!(report.functions[0].name, "main");
+        assert!(report.function("c").is_none());
+    }
+}
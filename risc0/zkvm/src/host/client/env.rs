@@ -17,6 +17,7 @@
 use std::{
     cell::RefCell,
     collections::HashMap,
+    future::Future,
     io::{BufRead, BufReader, Cursor, Read, Write},
     mem,
     path::{Path, PathBuf},
@@ -30,14 +31,20 @@ use bytes::Bytes;
 use risc0_zkvm_platform::{self, fileno};
 use serde::Serialize;
 use tempfile::TempDir;
+use tokio::runtime::Handle;
 
+#[cfg(feature = "prove")]
+use crate::host::server::exec::io_log::IoLogMode;
 use crate::{
     host::client::{
         posix_io::PosixIo,
-        slice_io::{slice_io_from_fn, SliceIo, SliceIoTable},
+        slice_io::{
+            async_slice_io_from_fn, slice_io_from_fn, AsyncSliceIo, BlockingSliceIo, SliceIo,
+            SliceIoTable,
+        },
     },
     serde::to_vec,
-    Assumption, TraceCallback,
+    Assumption, CancellationToken, TraceCallback,
 };
 
 /// A builder pattern used to construct an [ExecutorEnv].
@@ -63,10 +70,16 @@ pub enum SegmentPath {
     Path(PathBuf),
 }
 
+/// A callback invoked with each chunk written to the journal and its offset.
+pub(crate) type JournalCallback<'a> = dyn FnMut(&[u8], usize) -> Result<()> + 'a;
+
 /// The [crate::Executor] is configured from this object.
 ///
 /// The executor environment holds configuration details that inform how the
 /// guest environment is set up prior to guest program execution.
+///
+/// Use a [SendExecutorEnv] to configure an environment on one thread or async
+/// task and execute it on another.
 #[derive(Default)]
 pub struct ExecutorEnv<'a> {
     pub(crate) env_vars: HashMap<String, String>,
@@ -77,9 +90,16 @@ pub struct ExecutorEnv<'a> {
     pub(crate) slice_io: Rc<RefCell<SliceIoTable<'a>>>,
     pub(crate) input: Vec<u8>,
     pub(crate) trace: Vec<Rc<RefCell<dyn TraceCallback + 'a>>>,
+    pub(crate) journal_callbacks: Vec<Rc<RefCell<JournalCallback<'a>>>>,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    #[cfg(feature = "prove")]
+    pub(crate) syscalls: Vec<(String, Rc<RefCell<dyn crate::Syscall + 'a>>)>,
+    #[cfg(feature = "prove")]
+    pub(crate) io_log: Option<IoLogMode>,
     pub(crate) assumptions: Rc<RefCell<Assumptions>>,
     pub(crate) segment_path: Option<SegmentPath>,
     pub(crate) pprof_out: Option<PathBuf>,
+    pub(crate) cycle_report: bool,
 }
 
 impl<'a> ExecutorEnv<'a> {
@@ -305,6 +325,25 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add an asynchronous handler for simple I/O handling.
+    ///
+    /// Requests are run on the `tokio` runtime of the given [Handle]. See
+    /// [AsyncSliceIo] for how the handler is driven during execution.
+    pub fn async_slice_io(
+        &mut self,
+        channel: &str,
+        handle: Handle,
+        handler: impl AsyncSliceIo + 'a,
+    ) -> &mut Self {
+        self.slice_io(
+            channel,
+            BlockingSliceIo {
+                handle,
+                inner: handler,
+            },
+        )
+    }
+
     /// Add a handler for simple I/O handling.
     pub fn io_callback<C: AsRef<str>>(
         &mut self,
@@ -318,6 +357,86 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add an asynchronous handler for simple I/O handling.
+    ///
+    /// The future returned by the callback is awaited while the guest is
+    /// blocked on `env::send_recv_slice`, on the `tokio` runtime of the given
+    /// [Handle]. See [AsyncSliceIo] for how the future is driven during
+    /// execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{Bytes, ExecutorEnv};
+    ///
+    /// async fn lookup(key: Bytes) -> anyhow::Result<Bytes> {
+    ///     // e.g. query a database
+    ///     Ok(key)
+    /// }
+    ///
+    /// let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let env = ExecutorEnv::builder()
+    ///     .async_io_callback("lookup", runtime.handle().clone(), lookup)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn async_io_callback<C, F, Fut>(
+        &mut self,
+        channel: C,
+        handle: Handle,
+        callback: F,
+    ) -> &mut Self
+    where
+        C: AsRef<str>,
+        F: Fn(Bytes) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<Bytes>> + Send + 'a,
+    {
+        self.async_slice_io(channel.as_ref(), handle, async_slice_io_from_fn(callback))
+    }
+
+    /// Add a handler for a custom syscall.
+    ///
+    /// Unlike [ExecutorEnvBuilder::slice_io], the handler implements the full
+    /// [crate::Syscall] trait, which gives it access to guest registers and
+    /// memory through a [crate::SyscallContext]. A handler registered with the
+    /// same name as an existing syscall replaces it.
+    ///
+    /// Custom syscalls are only available to the in-process executor.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{declare_syscall, ExecutorEnv, Syscall, SyscallContext};
+    ///
+    /// declare_syscall!(SYS_PC);
+    ///
+    /// struct SysPc;
+    ///
+    /// impl Syscall for SysPc {
+    ///     fn syscall(
+    ///         &mut self,
+    ///         _syscall: &str,
+    ///         ctx: &mut dyn SyscallContext,
+    ///         _to_guest: &mut [u32],
+    ///     ) -> anyhow::Result<(u32, u32)> {
+    ///         Ok((ctx.get_pc(), ctx.get_segment_index() as u32))
+    ///     }
+    /// }
+    ///
+    /// let env = ExecutorEnv::builder().syscall(SYS_PC, SysPc).build().unwrap();
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn syscall<C: AsRef<str>>(
+        &mut self,
+        name: C,
+        handler: impl crate::Syscall + 'a,
+    ) -> &mut Self {
+        self.inner
+            .syscalls
+            .push((name.as_ref().to_string(), Rc::new(RefCell::new(handler))));
+        self
+    }
+
     /// Add an [Assumption] to the [ExecutorEnv], for use in [composition].
     ///
     /// During execution, when the guest calls `env::verify` or
@@ -344,6 +463,68 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add a callback that is invoked as the guest writes to its journal.
+    ///
+    /// The callback receives each chunk as it is written, together with the
+    /// offset of that chunk within the journal of the current session. A
+    /// single commit from the guest may arrive as more than one chunk.
+    ///
+    /// Returning an error from the callback stops execution, and the error is
+    /// returned from [crate::ExecutorImpl::run]. This can be used to cancel a
+    /// long-running guest based on what it has committed so far.
+    ///
+    /// Journal callbacks are invoked by the in-process executor only.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .journal_callback(|chunk, offset| {
+    ///         println!("journal[{offset}..]: {chunk:?}");
+    ///         Ok(())
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn journal_callback(
+        &mut self,
+        callback: impl FnMut(&[u8], usize) -> Result<()> + 'a,
+    ) -> &mut Self {
+        self.inner
+            .journal_callbacks
+            .push(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Set a [CancellationToken] used to stop execution from another thread.
+    ///
+    /// The token is checked periodically between instructions. Once it is
+    /// cancelled, or its deadline has passed, execution stops with a
+    /// [crate::CancellationError].
+    ///
+    /// When this environment is proven by a [crate::LocalProver] or a
+    /// [crate::PipelineProver], the token also cancels proving, and is checked
+    /// before proving each segment and before each recursion step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use risc0_zkvm::{CancellationToken, ExecutorEnv};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .cancellation_token(CancellationToken::with_timeout(Duration::from_secs(10)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.inner.cancellation_token = Some(token);
+        self
+    }
+
     /// Set the path where segments will be stored.
     pub fn segment_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
         self.inner.segment_path = Some(SegmentPath::Path(path.as_ref().to_path_buf()));
@@ -355,4 +536,293 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self.inner.pprof_out = Some(path.as_ref().to_path_buf());
         self
     }
+
+    /// Collect a [crate::CycleReport] of the cycles spent per function and per
+    /// call stack.
+    ///
+    /// The report is available from [crate::SessionInfo::cycle_report] after
+    /// execution.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .enable_cycle_report()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn enable_cycle_report(&mut self) -> &mut Self {
+        self.inner.cycle_report = true;
+        self
+    }
+
+    /// Record the response to every syscall that returns host-provided data to
+    /// the guest, and write them to the file at `path`.
+    ///
+    /// This includes stdin and other reads, [SliceIo] and custom syscalls,
+    /// `sys_random`, environment variables and arguments. Output such as
+    /// writes to stdout or the journal is not recorded. The recording can be
+    /// passed to [ExecutorEnvBuilder::replay_io] to reproduce the execution
+    /// without access to the original inputs.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write(&42u32)
+    ///     .unwrap()
+    ///     .record_io("io.log")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn record_io<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.io_log = Some(IoLogMode::Record(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Replay the syscall responses recorded by [ExecutorEnvBuilder::record_io]
+    /// from the file at `path`.
+    ///
+    /// Recorded responses are returned to the guest in order instead of
+    /// calling the handlers configured on this builder, so the same guest
+    /// produces the same [crate::Session], journal and image ID as the
+    /// recorded execution. Execution fails if the guest makes a syscall that
+    /// differs from the next one in the recording, or halts before the
+    /// recording is consumed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder().replay_io("io.log").build().unwrap();
+    /// ```
+    #[cfg(feature = "prove")]
+    pub fn replay_io<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.io_log = Some(IoLogMode::Replay(path.as_ref().to_path_buf()));
+        self
+    }
+}
+
+type EnvStep<'a> = Box<dyn FnOnce(&mut ExecutorEnvBuilder<'a>) + Send + 'a>;
+
+/// A builder for a [SendExecutorEnv].
+///
+/// This mirrors [ExecutorEnvBuilder], except that every handler must be
+/// `Send`. Options that take no handler can be set with
+/// [SendExecutorEnvBuilder::with].
+#[derive(Default)]
+pub struct SendExecutorEnvBuilder<'a> {
+    steps: Vec<EnvStep<'a>>,
+}
+
+/// An executor environment that can be moved between threads or async tasks.
+///
+/// An [ExecutorEnv] accepts handlers that are not `Send`, so it cannot be
+/// moved to another thread. A [SendExecutorEnv] holds the configuration of an
+/// [ExecutorEnv] with `Send` handlers only, and is turned into an
+/// [ExecutorEnv] on the thread that runs the executor.
+///
+/// A [SendExecutorEnv] is `Send` but not `Sync`: it can be moved to another
+/// thread, but not shared between threads by reference.
+///
+/// # Example
+///
+/// ```
+/// use risc0_zkvm::SendExecutorEnv;
+///
+/// let env = SendExecutorEnv::builder()
+///     .write(&42u32)
+///     .unwrap()
+///     .with(|builder| {
+///         builder.session_limit(Some(32 * 1024 * 1024));
+///     })
+///     .build();
+///
+/// std::thread::spawn(move || {
+///     let env = env.into_env().unwrap();
+///     // e.g. default_prover().prove(env, ELF)
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct SendExecutorEnv<'a> {
+    steps: Vec<EnvStep<'a>>,
+}
+
+impl<'a> SendExecutorEnv<'a> {
+    /// Construct a [SendExecutorEnvBuilder].
+    pub fn builder() -> SendExecutorEnvBuilder<'a> {
+        SendExecutorEnvBuilder::default()
+    }
+
+    /// Build the [ExecutorEnv] on the current thread.
+    pub fn into_env(self) -> Result<ExecutorEnv<'a>> {
+        let mut builder = ExecutorEnv::builder();
+        for step in self.steps {
+            step(&mut builder);
+        }
+        builder.build()
+    }
+}
+
+impl<'a> SendExecutorEnvBuilder<'a> {
+    /// Finalize this builder to construct a [SendExecutorEnv].
+    ///
+    /// After calling `build`, the [SendExecutorEnvBuilder] will be reset to
+    /// default.
+    pub fn build(&mut self) -> SendExecutorEnv<'a> {
+        SendExecutorEnv {
+            steps: mem::take(&mut self.steps),
+        }
+    }
+
+    /// Configure the [ExecutorEnvBuilder] when the [ExecutorEnv] is built.
+    pub fn with(&mut self, f: impl FnOnce(&mut ExecutorEnvBuilder<'a>) + Send + 'a) -> &mut Self {
+        self.steps.push(Box::new(f));
+        self
+    }
+
+    /// See [ExecutorEnvBuilder::write].
+    pub fn write<T: Serialize>(&mut self, data: &T) -> Result<&mut Self> {
+        let words = to_vec(data)?;
+        Ok(self.write_slice(&words))
+    }
+
+    /// See [ExecutorEnvBuilder::write_slice].
+    pub fn write_slice<T: Pod>(&mut self, slice: &[T]) -> &mut Self {
+        let bytes: Vec<u8> = bytemuck::cast_slice(slice).to_vec();
+        self.with(move |builder| {
+            builder.write_slice(&bytes);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::stdin].
+    pub fn stdin(&mut self, reader: impl Read + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.stdin(reader);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::stdout].
+    pub fn stdout(&mut self, writer: impl Write + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.stdout(writer);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::stderr].
+    pub fn stderr(&mut self, writer: impl Write + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.stderr(writer);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::read_fd].
+    pub fn read_fd(&mut self, fd: u32, reader: impl BufRead + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.read_fd(fd, reader);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::write_fd].
+    pub fn write_fd(&mut self, fd: u32, writer: impl Write + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.write_fd(fd, writer);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::slice_io].
+    pub fn slice_io(&mut self, channel: &str, handler: impl SliceIo + Send + 'a) -> &mut Self {
+        let channel = channel.to_string();
+        self.with(move |builder| {
+            builder.slice_io(&channel, handler);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::async_slice_io].
+    pub fn async_slice_io(
+        &mut self,
+        channel: &str,
+        handle: Handle,
+        handler: impl AsyncSliceIo + 'a,
+    ) -> &mut Self {
+        let channel = channel.to_string();
+        self.with(move |builder| {
+            builder.async_slice_io(&channel, handle, handler);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::io_callback].
+    pub fn io_callback<C: AsRef<str>>(
+        &mut self,
+        channel: C,
+        callback: impl Fn(Bytes) -> Result<Bytes> + Send + 'a,
+    ) -> &mut Self {
+        let channel = channel.as_ref().to_string();
+        self.with(move |builder| {
+            builder.io_callback(channel, callback);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::async_io_callback].
+    pub fn async_io_callback<C, F, Fut>(
+        &mut self,
+        channel: C,
+        handle: Handle,
+        callback: F,
+    ) -> &mut Self
+    where
+        C: AsRef<str>,
+        F: Fn(Bytes) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<Bytes>> + Send + 'a,
+    {
+        let channel = channel.as_ref().to_string();
+        self.with(move |builder| {
+            builder.async_io_callback(channel, handle, callback);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::syscall].
+    #[cfg(feature = "prove")]
+    pub fn syscall<C: AsRef<str>>(
+        &mut self,
+        name: C,
+        handler: impl crate::Syscall + Send + 'a,
+    ) -> &mut Self {
+        let name = name.as_ref().to_string();
+        self.with(move |builder| {
+            builder.syscall(name, handler);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::add_assumption].
+    pub fn add_assumption(&mut self, assumption: impl Into<Assumption>) -> &mut Self {
+        let assumption = assumption.into();
+        self.with(move |builder| {
+            builder.add_assumption(assumption);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::trace_callback].
+    pub fn trace_callback(&mut self, callback: impl TraceCallback + Send + 'a) -> &mut Self {
+        self.with(move |builder| {
+            builder.trace_callback(callback);
+        })
+    }
+
+    /// See [ExecutorEnvBuilder::journal_callback].
+    pub fn journal_callback(
+        &mut self,
+        callback: impl FnMut(&[u8], usize) -> Result<()> + Send + 'a,
+    ) -> &mut Self {
+        self.with(move |builder| {
+            builder.journal_callback(callback);
+        })
+    }
 }
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub(crate) mod cancel;
+pub(crate) mod cycle_report;
 pub(crate) mod env;
 pub(crate) mod posix_io;
 pub(crate) mod prove;
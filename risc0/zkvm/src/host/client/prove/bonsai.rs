@@ -15,10 +15,13 @@
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use bonsai_sdk::alpha::Client;
+use bonsai_sdk::alpha::{Client, WaitOptions};
 
-use super::Prover;
-use crate::{compute_image_id, sha::Digestible, ExecutorEnv, ProverOpts, Receipt, VerifierContext};
+use super::{Prover, ReceiptKind};
+use crate::{
+    compute_image_id, sha::Digestible, CompactReceipt, ExecutorEnv, InnerReceipt, ProverOpts,
+    Receipt, VerifierContext,
+};
 
 /// An implementation of a [Prover] that runs proof workloads via Bonsai.
 ///
@@ -76,53 +79,68 @@ impl Prover for BonsaiProver {
         let session = client.create_session(image_id_hex, input_id, receipts_ids)?;
         tracing::debug!("Bonsai proving SessionID: {}", session.uuid);
 
-        loop {
-            // The session has already been started in the executor. Poll bonsai to check if
-            // the proof request succeeded.
-            let res = session.status(&client)?;
-            if res.status == "RUNNING" {
-                std::thread::sleep(Duration::from_secs(5));
-                continue;
-            }
-            if res.status == "SUCCEEDED" {
-                // Download the receipt, containing the output
-                let receipt_url = res
-                    .receipt_url
-                    .ok_or(anyhow!("API error, missing receipt on completed session"))?;
-
-                let stats = res
-                    .stats
-                    .context("Missing stats object on Bonsai status res")?;
-                tracing::debug!(
-                    "Bonsai usage: cycles: {} total_cycles: {}",
-                    stats.cycles,
-                    stats.total_cycles
-                );
-
-                let receipt_buf = client.download(&receipt_url)?;
-                let receipt: Receipt = bincode::deserialize(&receipt_buf)?;
-
-                if opts.prove_guest_errors {
-                    receipt.verify_integrity_with_context(ctx)?;
-                    ensure!(
-                        receipt.get_claim()?.pre.digest() == image_id,
-                        "received unexpected image ID: expected {}, found {}",
-                        hex::encode(image_id),
-                        hex::encode(receipt.get_claim()?.pre.digest())
-                    );
-                } else {
-                    receipt.verify_with_context(ctx, image_id)?;
-                }
-                return Ok(receipt);
-            } else {
-                bail!(
-                    "Bonsai prover workflow [{}] exited: {} err: {}",
-                    session.uuid,
-                    res.status,
-                    res.error_msg
-                        .unwrap_or("Bonsai workflow missing error_msg".into()),
-                );
-            }
+        // The session has already been started in the executor. Poll bonsai to check if
+        // the proof request succeeded.
+        let wait_opts = WaitOptions {
+            poll_interval: Duration::from_secs(5),
+            timeout: None,
+        };
+        let res = session.wait_for_completion(&client, &wait_opts, |res| {
+            tracing::trace!(
+                "Bonsai session status: {} state: {}",
+                res.status,
+                res.state.as_deref().unwrap_or_default()
+            )
+        })?;
+
+        // Download the receipt, containing the output
+        let receipt_url = res
+            .receipt_url
+            .ok_or(anyhow!("API error, missing receipt on completed session"))?;
+
+        let stats = res
+            .stats
+            .context("Missing stats object on Bonsai status res")?;
+        tracing::debug!(
+            "Bonsai usage: cycles: {} total_cycles: {}",
+            stats.cycles,
+            stats.total_cycles
+        );
+
+        let receipt_buf = client.download(&receipt_url)?;
+        let mut receipt: Receipt = bincode::deserialize(&receipt_buf)?;
+
+        // Bonsai returns succinct receipts, which are wrapped in Groth16 by a
+        // separate SNARK session when a compact receipt is requested.
+        if opts.receipt_kind == ReceiptKind::Compact {
+            let snark_session = client.create_snark(session.uuid.clone())?;
+            tracing::debug!("Bonsai SNARK SessionID: {}", snark_session.uuid);
+            let res = snark_session.wait_for_completion(&client, &wait_opts, |res| {
+                tracing::trace!("Bonsai SNARK status: {}", res.status)
+            })?;
+            let snark = res.output.ok_or(anyhow!(
+                "API error, missing output on completed SNARK session"
+            ))?;
+            receipt = Receipt::new(
+                InnerReceipt::Compact(CompactReceipt {
+                    seal: snark.snark.to_vec(),
+                    claim: receipt.get_claim()?,
+                }),
+                receipt.journal.bytes,
+            );
+        }
+
+        if opts.prove_guest_errors {
+            receipt.verify_integrity_with_context(ctx)?;
+            ensure!(
+                receipt.get_claim()?.pre.digest() == image_id,
+                "received unexpected image ID: expected {}, found {}",
+                hex::encode(image_id),
+                hex::encode(receipt.get_claim()?.pre.digest())
+            );
+        } else {
+            receipt.verify_with_context(ctx, image_id)?;
         }
+        Ok(receipt)
     }
 }
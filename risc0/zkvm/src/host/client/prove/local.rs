@@ -16,12 +16,12 @@ use anyhow::Result;
 
 use super::{Executor, Prover, ProverOpts};
 use crate::{
-    get_prover_server, host::server::session::NullSegmentRef, ExecutorEnv, ExecutorImpl, Receipt,
-    SegmentInfo, SessionInfo, VerifierContext,
+    host::server::{prove::prover_server, session::NullSegmentRef},
+    ExecutorEnv, ExecutorImpl, Receipt, SegmentInfo, SessionInfo, VerifierContext,
 };
 
 /// A [Prover] implementation that selects a [crate::ProverServer] by calling
-/// [get_prover_server].
+/// [crate::get_prover_server].
 pub struct LocalProver {
     name: String,
 }
@@ -43,7 +43,14 @@ impl Prover for LocalProver {
         elf: &[u8],
         opts: &ProverOpts,
     ) -> Result<Receipt> {
-        get_prover_server(opts)?.prove_with_ctx(env, ctx, elf)
+        // Without a token of its own, proving is cancelled by the same token as execution.
+        let token = opts
+            .cancellation_token
+            .clone()
+            .or_else(|| env.cancellation_token.clone());
+        let prover = prover_server(opts, token)?;
+        let receipt = prover.prove_with_ctx(env, ctx, elf)?;
+        prover.compress_to(&receipt, opts.receipt_kind)
     }
 
     fn get_name(&self) -> String {
@@ -66,6 +73,7 @@ impl Executor for LocalProver {
             segments,
             journal: session.journal.unwrap_or_default().into(),
             exit_code: session.exit_code,
+            cycle_report: session.cycle_report,
         })
     }
 }
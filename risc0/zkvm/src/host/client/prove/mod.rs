@@ -17,13 +17,16 @@ pub(crate) mod external;
 #[cfg(feature = "prove")]
 pub(crate) mod local;
 
-use std::{path::PathBuf, rc::Rc};
+use std::{path::PathBuf, rc::Rc, sync::Arc};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use self::{bonsai::BonsaiProver, external::ExternalProver};
-use crate::{is_dev_mode, ExecutorEnv, Receipt, SessionInfo, VerifierContext};
+use crate::{
+    is_dev_mode, CancellationToken, ExecutorEnv, InnerReceipt, Receipt, SessionInfo,
+    VerifierContext,
+};
 
 /// A Prover can execute a given ELF binary and produce a
 /// [Receipt] that can be used to verify correct computation.
@@ -104,6 +107,23 @@ pub struct ProverOpts {
     // moment if there is a better place. At some point before 1.0, this option should be moved or
     // dropped.
     pub prove_guest_errors: bool,
+    /// The kind of receipt to produce.
+    ///
+    /// Receipts are compressed as needed to reach this kind, but are never
+    /// expanded: a prover that produces a more compressed receipt than
+    /// requested, such as [BonsaiProver], returns it as is. Defaults to
+    /// [ReceiptKind::Composite].
+    #[serde(default)]
+    pub receipt_kind: ReceiptKind,
+    /// A token used to cancel proving from another thread.
+    ///
+    /// Provers running in-process check the token before proving each segment
+    /// and before each recursion step. If it is not set, a [Prover] proving an
+    /// [ExecutorEnv] is cancelled by [crate::ExecutorEnvBuilder::cancellation_token]
+    /// instead. The token is not serialized, so it is not sent to remote
+    /// provers.
+    #[serde(skip)]
+    pub cancellation_token: Option<CancellationToken>,
 }
 
 impl Default for ProverOpts {
@@ -113,6 +133,8 @@ impl Default for ProverOpts {
         Self {
             hashfn: "poseidon2".to_string(),
             prove_guest_errors: false,
+            receipt_kind: ReceiptKind::Composite,
+            cancellation_token: None,
         }
     }
 }
@@ -123,6 +145,36 @@ impl ProverOpts {
         Self {
             hashfn: "sha-256".to_string(),
             prove_guest_errors: false,
+            receipt_kind: ReceiptKind::Composite,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// The kind of receipt produced by a [Prover], from largest to smallest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ReceiptKind {
+    /// A [crate::CompositeReceipt], made of one receipt per segment plus
+    /// the receipts of any assumptions.
+    #[default]
+    Composite,
+    /// A [crate::SuccinctReceipt], compressed into a single STARK by the
+    /// recursion programs.
+    Succinct,
+    /// A [crate::CompactReceipt], wrapping the succinct receipt in a Groth16
+    /// proof.
+    Compact,
+}
+
+impl ReceiptKind {
+    /// Return the [ReceiptKind] of the given [InnerReceipt], or `None` for a
+    /// fake receipt produced in dev mode.
+    pub fn of(inner: &InnerReceipt) -> Option<Self> {
+        match inner {
+            InnerReceipt::Composite(_) => Some(Self::Composite),
+            InnerReceipt::Succinct(_) => Some(Self::Succinct),
+            InnerReceipt::Compact(_) => Some(Self::Compact),
+            InnerReceipt::Fake { .. } => None,
         }
     }
 }
@@ -144,13 +196,52 @@ impl ProverOpts {
 /// * LocalProver if the `prove` feature flag is enabled.
 /// * [ExternalProver] otherwise.
 pub fn default_prover() -> Rc<dyn Prover> {
+    Rc::from(prover_from_env() as Box<dyn Prover>)
+}
+
+/// Return a default [Prover] that can be shared between threads and async
+/// tasks.
+///
+/// The [Prover] is selected in the same way as [default_prover].
+pub fn default_prover_send() -> Arc<dyn Prover + Send + Sync> {
+    Arc::from(prover_from_env())
+}
+
+/// Return a default [Executor] based on environment variables and feature
+/// flags.
+///
+/// The `RISC0_EXECUTOR` environment variable, if specified, will select the
+/// following [Executor] implementation:
+/// * `local`: LocalProver to execute locally in-process. Note: this is
+///   only available when the `prove` feature is enabled.
+/// * `ipc`: [ExternalProver] to execute using an `r0vm` sub-process. Note:
+///   `r0vm` must be installed. To specify the path to `r0vm`, use
+///   `RISC0_SERVER_PATH`.
+///
+/// If `RISC0_EXECUTOR` is not specified, the following rules are used to select
+/// an [Executor]:
+/// * LocalProver if the `prove` feature flag is enabled.
+/// * [ExternalProver] otherwise.
+pub fn default_executor() -> Rc<dyn Executor> {
+    Rc::from(executor_from_env() as Box<dyn Executor>)
+}
+
+/// Return a default [Executor] that can be shared between threads and async
+/// tasks.
+///
+/// The [Executor] is selected in the same way as [default_executor].
+pub fn default_executor_send() -> Arc<dyn Executor + Send + Sync> {
+    Arc::from(executor_from_env())
+}
+
+fn prover_from_env() -> Box<dyn Prover + Send + Sync> {
     let explicit = std::env::var("RISC0_PROVER").unwrap_or_default();
     if !explicit.is_empty() {
         return match explicit.to_lowercase().as_str() {
-            "bonsai" => Rc::new(BonsaiProver::new("bonsai")),
-            "ipc" => Rc::new(ExternalProver::new("ipc", get_r0vm_path())),
+            "bonsai" => Box::new(BonsaiProver::new("bonsai")),
+            "ipc" => Box::new(ExternalProver::new("ipc", get_r0vm_path())),
             #[cfg(feature = "prove")]
-            "local" => Rc::new(self::local::LocalProver::new("local")),
+            "local" => Box::new(self::local::LocalProver::new("local")),
             _ => unimplemented!("Unsupported prover: {explicit}"),
         };
     }
@@ -159,49 +250,34 @@ pub fn default_prover() -> Rc<dyn Prover> {
         && std::env::var("BONSAI_API_URL").is_ok()
         && std::env::var("BONSAI_API_KEY").is_ok()
     {
-        return Rc::new(BonsaiProver::new("bonsai"));
+        return Box::new(BonsaiProver::new("bonsai"));
     }
 
     if cfg!(feature = "prove") {
         #[cfg(feature = "prove")]
-        return Rc::new(self::local::LocalProver::new("local"));
+        return Box::new(self::local::LocalProver::new("local"));
     }
 
-    Rc::new(ExternalProver::new("ipc", get_r0vm_path()))
+    Box::new(ExternalProver::new("ipc", get_r0vm_path()))
 }
 
-/// Return a default [Executor] based on environment variables and feature
-/// flags.
-///
-/// The `RISC0_EXECUTOR` environment variable, if specified, will select the
-/// following [Executor] implementation:
-/// * `local`: LocalProver to execute locally in-process. Note: this is
-///   only available when the `prove` feature is enabled.
-/// * `ipc`: [ExternalProver] to execute using an `r0vm` sub-process. Note:
-///   `r0vm` must be installed. To specify the path to `r0vm`, use
-///   `RISC0_SERVER_PATH`.
-///
-/// If `RISC0_EXECUTOR` is not specified, the following rules are used to select
-/// an [Executor]:
-/// * LocalProver if the `prove` feature flag is enabled.
-/// * [ExternalProver] otherwise.
-pub fn default_executor() -> Rc<dyn Executor> {
+fn executor_from_env() -> Box<dyn Executor + Send + Sync> {
     let explicit = std::env::var("RISC0_EXECUTOR").unwrap_or_default();
     if !explicit.is_empty() {
         return match explicit.to_lowercase().as_str() {
-            "ipc" => Rc::new(ExternalProver::new("ipc", get_r0vm_path())),
+            "ipc" => Box::new(ExternalProver::new("ipc", get_r0vm_path())),
             #[cfg(feature = "prove")]
-            "local" => Rc::new(self::local::LocalProver::new("local")),
+            "local" => Box::new(self::local::LocalProver::new("local")),
             _ => unimplemented!("Unsupported executor: {explicit}"),
         };
     }
 
     if cfg!(feature = "prove") {
         #[cfg(feature = "prove")]
-        return Rc::new(self::local::LocalProver::new("local"));
+        return Box::new(self::local::LocalProver::new("local"));
     }
 
-    Rc::new(ExternalProver::new("ipc", get_r0vm_path()))
+    Box::new(ExternalProver::new("ipc", get_r0vm_path()))
 }
 
 pub(crate) fn get_r0vm_path() -> PathBuf {
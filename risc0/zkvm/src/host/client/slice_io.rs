@@ -12,10 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use std::{
+    cell::RefCell, collections::BTreeMap, future::Future, marker::PhantomData, pin::Pin, rc::Rc,
+};
 
 use anyhow::Result;
 use bytes::Bytes;
+use tokio::runtime::Handle;
 
 /// An I/O handler that returns arbitrary data to the guest.
 ///
@@ -34,6 +37,33 @@ pub trait SliceIo {
     fn handle_io(&mut self, syscall: &str, from_guest: Bytes) -> Result<Bytes>;
 }
 
+/// An asynchronous version of [SliceIo].
+///
+/// On the guest side, use `env::send_recv_slice`. The guest is blocked until
+/// the future returned by [AsyncSliceIo::handle_io] completes, which allows the
+/// host to await network or database lookups while handling the request.
+///
+/// The executor itself is synchronous. Each future is run to completion on the
+/// executor thread with [Handle::block_on], using the `tokio` runtime whose
+/// [Handle] was supplied along with the handler, so that the future can use
+/// the I/O and time drivers of that runtime. As with [Handle::block_on], the
+/// executor must not be run from within an asynchronous context of that
+/// runtime, and the drivers of a `current_thread` runtime only make progress
+/// while the runtime itself is blocked on, so a `multi_thread` runtime should
+/// be used.
+pub trait AsyncSliceIo: Send {
+    /// Host side I/O handling
+    ///
+    /// Whatever data the guest sent is received by this function in
+    /// `from_guest`, and the returned future is to resolve to the data the host
+    /// is sending to the guest.
+    fn handle_io<'a>(
+        &'a mut self,
+        syscall: &'a str,
+        from_guest: Bytes,
+    ) -> Pin<Box<dyn Future<Output = Result<Bytes>> + Send + 'a>>;
+}
+
 #[derive(Clone, Default)]
 pub struct SliceIoTable<'a> {
     pub(crate) inner: BTreeMap<String, Rc<RefCell<dyn SliceIo + 'a>>>,
@@ -59,6 +89,50 @@ impl<'a> SliceIo for FnWrapper<'a> {
     }
 }
 
+struct AsyncFnWrapper<'a, F> {
+    callback: F,
+    phantom: PhantomData<&'a ()>,
+}
+
+pub fn async_slice_io_from_fn<'a, F, Fut>(callback: F) -> impl AsyncSliceIo + 'a
+where
+    F: Fn(Bytes) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<Bytes>> + Send + 'a,
+{
+    AsyncFnWrapper {
+        callback,
+        phantom: PhantomData,
+    }
+}
+
+impl<'a, F, Fut> AsyncSliceIo for AsyncFnWrapper<'a, F>
+where
+    F: Fn(Bytes) -> Fut + Send,
+    Fut: Future<Output = Result<Bytes>> + Send + 'a,
+{
+    fn handle_io<'b>(
+        &'b mut self,
+        _syscall: &'b str,
+        from_guest: Bytes,
+    ) -> Pin<Box<dyn Future<Output = Result<Bytes>> + Send + 'b>> {
+        Box::pin((self.callback)(from_guest))
+    }
+}
+
+/// Adapts an [AsyncSliceIo] to a [SliceIo] by blocking on each request with
+/// the given runtime.
+pub(crate) struct BlockingSliceIo<T> {
+    pub(crate) handle: Handle,
+    pub(crate) inner: T,
+}
+
+impl<T: AsyncSliceIo> SliceIo for BlockingSliceIo<T> {
+    fn handle_io(&mut self, syscall: &str, from_guest: Bytes) -> Result<Bytes> {
+        self.handle
+            .block_on(self.inner.handle_io(syscall, from_guest))
+    }
+}
+
 impl<'a> SliceIoTable<'a> {
     pub fn with_handler(&mut self, channel: &str, handler: impl SliceIo + 'a) -> &mut Self {
         self.inner
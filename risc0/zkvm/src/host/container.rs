@@ -0,0 +1,383 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A versioned container format for persisting receipts and segments.
+//!
+//! Every container starts with a fixed header, followed by the bincode
+//! encoding of the contained value. All integers are little-endian.
+//!
+//! | Size | Field                                                     |
+//! |------|-----------------------------------------------------------|
+//! | 8    | Magic bytes, [CONTAINER_MAGIC]                            |
+//! | 4    | Format version, see [CONTAINER_FORMAT_VERSION]            |
+//! | 4    | [ContainerKind] of the payload                            |
+//! | 4+n  | Length-prefixed UTF-8 version of `risc0-zkvm` that wrote it |
+//! | 4+n  | Length-prefixed UTF-8 hash function, or empty if none     |
+//! | 32   | SHA-256 checksum of the payload                           |
+//! | 8    | Length of the payload                                     |
+//! | n    | Payload                                                   |
+//!
+//! The magic bytes, format version, kind and `risc0-zkvm` version will keep
+//! this layout in all future format versions, so that readers can report
+//! which version of `risc0-zkvm` is needed to decode a newer container.
+//!
+//! Data that does not start with the magic bytes is decoded as the legacy
+//! format, which is the raw bincode encoding of the value.
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use hex::FromHex;
+use risc0_circuit_recursion::control_id::RECURSION_CONTROL_IDS;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    sha::{Digest, Sha256},
+    InnerReceipt, Receipt, SuccinctReceipt, VERSION,
+};
+
+/// Magic bytes at the start of every container.
+pub const CONTAINER_MAGIC: [u8; 8] = *b"RISC0ZKV";
+
+/// The current version of the container format.
+pub const CONTAINER_FORMAT_VERSION: u32 = 1;
+
+/// The kind of value stored in a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ContainerKind {
+    /// A [Receipt].
+    Receipt,
+    /// A [SuccinctReceipt].
+    SuccinctReceipt,
+    /// A [crate::Segment].
+    Segment,
+}
+
+impl ContainerKind {
+    fn to_u32(self) -> u32 {
+        match self {
+            ContainerKind::Receipt => 1,
+            ContainerKind::SuccinctReceipt => 2,
+            ContainerKind::Segment => 3,
+        }
+    }
+
+    fn from_u32(kind: u32) -> Result<Self> {
+        Ok(match kind {
+            1 => ContainerKind::Receipt,
+            2 => ContainerKind::SuccinctReceipt,
+            3 => ContainerKind::Segment,
+            _ => bail!("unsupported container kind: {kind}"),
+        })
+    }
+}
+
+/// The header of a container, describing its payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerHeader {
+    /// The format version of the container.
+    pub version: u32,
+
+    /// The kind of value stored in the container.
+    pub kind: ContainerKind,
+
+    /// The version of `risc0-zkvm` that wrote the container.
+    pub risc0_version: String,
+
+    /// The hash function used by the seal of the value, or empty if the value
+    /// has no such hash function.
+    pub hashfn: String,
+
+    /// The SHA-256 checksum of the payload.
+    pub checksum: Digest,
+}
+
+impl ContainerHeader {
+    /// Read the header of a container.
+    ///
+    /// Returns `None` if `bytes` does not start with [CONTAINER_MAGIC], which
+    /// indicates the legacy format.
+    pub fn read(bytes: &[u8]) -> Result<Option<Self>> {
+        Ok(Self::read_with_payload(bytes)?.map(|(header, _)| header))
+    }
+
+    fn read_with_payload(bytes: &[u8]) -> Result<Option<(Self, &[u8])>> {
+        let mut reader = Reader(bytes);
+        if !bytes.starts_with(&CONTAINER_MAGIC) {
+            return Ok(None);
+        }
+        reader.take(CONTAINER_MAGIC.len())?;
+        let version = reader.u32()?;
+        let kind = reader.u32()?;
+        let risc0_version = reader.string()?;
+        if version > CONTAINER_FORMAT_VERSION {
+            bail!(
+                "unsupported container format version: {version}, expected at most: \
+                 {CONTAINER_FORMAT_VERSION} (written by risc0-zkvm {risc0_version}, \
+                 this is risc0-zkvm {VERSION})"
+            );
+        }
+        let kind = ContainerKind::from_u32(kind)?;
+        let hashfn = reader.string()?;
+        let checksum = Digest::try_from(reader.take(32)?)?;
+        let len = u64::from_le_bytes(reader.take(8)?.try_into()?);
+        let payload = reader.take(len.try_into()?)?;
+        ensure!(reader.0.is_empty(), "trailing data after container payload");
+        let header = Self {
+            version,
+            kind,
+            risc0_version,
+            hashfn,
+            checksum,
+        };
+        Ok(Some((header, payload)))
+    }
+
+    fn write(&self, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(payload.len() + 128);
+        out.extend_from_slice(&CONTAINER_MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.kind.to_u32().to_le_bytes());
+        for field in [&self.risc0_version, &self.hashfn] {
+            out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            out.extend_from_slice(field.as_bytes());
+        }
+        out.extend_from_slice(self.checksum.as_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+}
+
+/// A value that can be persisted in the container format.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::{Container, Receipt};
+///
+/// # let receipt: Receipt = todo!();
+/// let bytes = receipt.to_container_bytes().unwrap();
+/// std::fs::write("receipt.bin", &bytes).unwrap();
+///
+/// let receipt = Receipt::from_container_bytes(&std::fs::read("receipt.bin").unwrap()).unwrap();
+/// ```
+pub trait Container: Serialize + DeserializeOwned {
+    /// The kind recorded in the header of the container.
+    const KIND: ContainerKind;
+
+    /// The hash function used by the seal of this value, or empty if there is
+    /// none.
+    fn hashfn(&self) -> String;
+
+    /// Encode this value as a container.
+    fn to_container_bytes(&self) -> Result<Vec<u8>> {
+        let payload = bincode::serialize(self)?;
+        let header = ContainerHeader {
+            version: CONTAINER_FORMAT_VERSION,
+            kind: Self::KIND,
+            risc0_version: VERSION.to_string(),
+            hashfn: self.hashfn(),
+            checksum: *crate::sha::Impl::hash_bytes(&payload),
+        };
+        Ok(header.write(&payload))
+    }
+
+    /// Decode a value from a container, or from the legacy format.
+    fn from_container_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some((header, payload)) = ContainerHeader::read_with_payload(bytes)? else {
+            return bincode::deserialize(bytes).map_err(|err| {
+                anyhow!(
+                    "failed to decode legacy {:?} without container header: {err}",
+                    Self::KIND
+                )
+            });
+        };
+        ensure!(
+            header.kind == Self::KIND,
+            "unexpected container kind: {:?}, expected: {:?}",
+            header.kind,
+            Self::KIND
+        );
+        ensure!(
+            *crate::sha::Impl::hash_bytes(payload) == header.checksum,
+            "container checksum mismatch, the data is corrupted"
+        );
+        let value: Self = bincode::deserialize(payload).with_context(|| {
+            format!(
+                "failed to decode {:?} written by risc0-zkvm {}",
+                Self::KIND,
+                header.risc0_version
+            )
+        })?;
+        ensure!(
+            value.hashfn() == header.hashfn,
+            "container hash function mismatch: {:?}, expected: {:?}",
+            value.hashfn(),
+            header.hashfn
+        );
+        Ok(value)
+    }
+}
+
+impl Container for Receipt {
+    const KIND: ContainerKind = ContainerKind::Receipt;
+
+    fn hashfn(&self) -> String {
+        match &self.inner {
+            InnerReceipt::Composite(inner) => inner
+                .segments
+                .first()
+                .map(|x| x.hashfn.clone())
+                .unwrap_or_default(),
+            InnerReceipt::Succinct(inner) => inner.hashfn(),
+            InnerReceipt::Compact(_) | InnerReceipt::Fake { .. } => String::new(),
+        }
+    }
+}
+
+impl Container for SuccinctReceipt {
+    const KIND: ContainerKind = ContainerKind::SuccinctReceipt;
+
+    fn hashfn(&self) -> String {
+        // The identity program is only run by identity_p254, which seals it with
+        // Poseidon254 so it can be verified by the Groth16 prover. Every other
+        // recursion program is sealed with Poseidon2.
+        let identity_id = RECURSION_CONTROL_IDS
+            .iter()
+            .find(|(name, _)| *name == "identity.zkr")
+            .and_then(|(_, id)| Digest::from_hex(id).ok());
+        if identity_id == Some(self.control_id) {
+            "poseidon254".to_string()
+        } else {
+            "poseidon2".to_string()
+        }
+    }
+}
+
+#[cfg(feature = "prove")]
+impl Container for crate::Segment {
+    const KIND: ContainerKind = ContainerKind::Segment;
+
+    fn hashfn(&self) -> String {
+        // The hash function is chosen when the segment is proven.
+        String::new()
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.0.len() >= len, "container is truncated");
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExitCode, MaybePruned, ReceiptClaim};
+
+    fn fake_receipt() -> Receipt {
+        let claim = ReceiptClaim {
+            pre: MaybePruned::Pruned(Digest::ZERO),
+            post: MaybePruned::Pruned(Digest::ZERO),
+            exit_code: ExitCode::Halted(0),
+            input: Digest::ZERO,
+            output: None.into(),
+        };
+        Receipt::new(InnerReceipt::Fake { claim }, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn round_trip() {
+        let receipt = fake_receipt();
+        let bytes = receipt.to_container_bytes().unwrap();
+        let header = ContainerHeader::read(&bytes).unwrap().unwrap();
+        assert_eq!(header.version, CONTAINER_FORMAT_VERSION);
+        assert_eq!(header.kind, ContainerKind::Receipt);
+        assert_eq!(header.risc0_version, VERSION);
+        let decoded = Receipt::from_container_bytes(&bytes).unwrap();
+        assert_eq!(decoded.journal.bytes, receipt.journal.bytes);
+    }
+
+    #[test]
+    fn legacy() {
+        let receipt = fake_receipt();
+        let bytes = bincode::serialize(&receipt).unwrap();
+        assert_eq!(ContainerHeader::read(&bytes).unwrap(), None);
+        let decoded = Receipt::from_container_bytes(&bytes).unwrap();
+        assert_eq!(decoded.journal.bytes, receipt.journal.bytes);
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut bytes = fake_receipt().to_container_bytes().unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let err = Receipt::from_container_bytes(&bytes).err().unwrap();
+        assert!(err.to_string().contains("checksum mismatch"), "{err}");
+
+        bytes.pop();
+        let err = Receipt::from_container_bytes(&bytes).err().unwrap();
+        assert!(err.to_string().contains("truncated"), "{err}");
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes = fake_receipt().to_container_bytes().unwrap();
+        bytes[8..12].copy_from_slice(&(CONTAINER_FORMAT_VERSION + 1).to_le_bytes());
+        let err = Receipt::from_container_bytes(&bytes).err().unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("unsupported container format version"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn succinct_hashfn() {
+        let receipt = |name| SuccinctReceipt {
+            seal: vec![],
+            control_id: RECURSION_CONTROL_IDS
+                .iter()
+                .find(|(x, _)| *x == name)
+                .map(|(_, id)| Digest::from_hex(id).unwrap())
+                .unwrap(),
+            claim: fake_receipt().inner.get_claim().unwrap(),
+        };
+        assert_eq!(receipt("join.zkr").hashfn(), "poseidon2");
+        assert_eq!(receipt("identity.zkr").hashfn(), "poseidon254");
+    }
+
+    #[test]
+    fn wrong_kind() {
+        let bytes = fake_receipt().to_container_bytes().unwrap();
+        let err = SuccinctReceipt::from_container_bytes(&bytes).err().unwrap();
+        assert!(
+            err.to_string().contains("unexpected container kind"),
+            "{err}"
+        );
+    }
+}
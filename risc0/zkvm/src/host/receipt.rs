@@ -812,3 +812,48 @@ impl Default for VerifierContext {
         }
     }
 }
+
+impl VerifierContext {
+    /// Verify a batch of receipts, each against its expected image ID.
+    ///
+    /// This is equivalent to calling [Receipt::verify_with_context] with this context on each
+    /// receipt, and returns the result for each receipt in the same order as the input. The hash
+    /// suites of the context are set up once and shared by every receipt in the batch.
+    ///
+    /// Receipts are verified on the calling thread, since the hash suites of a [VerifierContext]
+    /// cannot be shared across threads. See [VerifierContext::par_verify_batch] to verify a batch
+    /// in parallel.
+    pub fn verify_batch(
+        &self,
+        receipts: &[(Receipt, Digest)],
+    ) -> Vec<Result<(), VerificationError>> {
+        receipts
+            .iter()
+            .map(|(receipt, image_id)| receipt.verify_with_context(self, *image_id))
+            .collect()
+    }
+
+    /// Verify a batch of receipts in parallel, each against its expected image ID.
+    ///
+    /// This is equivalent to [VerifierContext::verify_batch], except that receipts are verified on
+    /// the rayon thread pool. Since a [VerifierContext] cannot be shared across threads, `make_ctx`
+    /// is called to construct a context for each worker, which is then reused for the receipts
+    /// verified by that worker.
+    #[cfg(feature = "prove")]
+    pub fn par_verify_batch<F>(
+        make_ctx: F,
+        receipts: &[(Receipt, Digest)],
+    ) -> Vec<Result<(), VerificationError>>
+    where
+        F: Fn() -> VerifierContext + Sync,
+    {
+        use rayon::prelude::*;
+
+        receipts
+            .par_iter()
+            .map_init(make_ctx, |ctx, (receipt, image_id)| {
+                receipt.verify_with_context(ctx, *image_id)
+            })
+            .collect()
+    }
+}
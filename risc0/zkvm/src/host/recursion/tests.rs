@@ -27,8 +27,8 @@ use super::{
     ProverOpts as RecursionProverOpts,
 };
 use crate::{
-    get_prover_server, ExecutorEnv, ExecutorImpl, InnerReceipt, ProverOpts, Receipt,
-    SegmentReceipt, Session, VerifierContext,
+    get_prover_server, sha::Digestible, CompositeReceipt, ExecutorEnv, ExecutorImpl, InnerReceipt,
+    ProverOpts, Receipt, SegmentReceipt, Session, VerifierContext,
 };
 
 // Failure on older mac minis in the lab with Intel UHD 630 graphics:
@@ -142,6 +142,7 @@ fn generate_busy_loop_segments(hashfn: &str) -> (Session, Vec<SegmentReceipt>) {
     let opts = ProverOpts {
         hashfn: hashfn.to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     };
     let prover = get_prover_server(&opts).unwrap();
 
@@ -197,6 +198,56 @@ fn test_recursion_lift_join_identity_e2e() {
     rollup_receipt.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[cfg_attr(
+    not(all(feature = "metal", target_os = "macos", target_arch = "x86_64")),
+    test
+)]
+#[serial]
+fn test_recursion_compress_tree_e2e() {
+    let (session, segments) = generate_busy_loop_segments("poseidon2");
+    let composite_receipt = CompositeReceipt {
+        segments,
+        assumptions: vec![],
+        journal_digest: session.journal.as_ref().map(|journal| journal.digest()),
+    };
+
+    let prover = get_prover_server(&ProverOpts::default()).unwrap();
+    let checkpoint_dir = tempfile::tempdir().unwrap();
+    let succinct_receipt = prover
+        .compress_tree(&composite_receipt, Some(checkpoint_dir.path()))
+        .unwrap();
+
+    // Every range in the join tree, including the root, should have been checkpointed.
+    let num_checkpoints = std::fs::read_dir(checkpoint_dir.path()).unwrap().count();
+    assert_eq!(num_checkpoints, 2 * composite_receipt.segments.len() - 1);
+
+    // Running again with the same checkpoint dir picks up the stored receipts.
+    let resumed_receipt = prover
+        .compress_tree(&composite_receipt, Some(checkpoint_dir.path()))
+        .unwrap();
+    assert_eq!(resumed_receipt, succinct_receipt);
+
+    // A valid receipt for a different range of segments is rejected as a checkpoint.
+    let num_segments = composite_receipt.segments.len();
+    std::fs::copy(
+        checkpoint_dir.path().join("0-1.receipt"),
+        checkpoint_dir
+            .path()
+            .join(format!("0-{num_segments}.receipt")),
+    )
+    .unwrap();
+    let err = prover
+        .compress_tree(&composite_receipt, Some(checkpoint_dir.path()))
+        .unwrap_err();
+    assert!(err.to_string().contains("does not match"), "{err}");
+
+    let receipt = Receipt::new(
+        InnerReceipt::Succinct(succinct_receipt),
+        session.journal.unwrap().bytes,
+    );
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
 #[cfg_attr(
     not(all(feature = "metal", target_os = "macos", target_arch = "x86_64")),
     test
@@ -12,9 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{cell::RefCell, io::Write, mem, rc::Rc, sync::Arc, time::Instant};
+use std::{cell::RefCell, collections::BTreeMap, io::Write, mem, rc::Rc, sync::Arc, time::Instant};
 
-use anyhow::{Context as _, Result};
+use anyhow::{ensure, Result};
 use human_repr::HumanDuration as _;
 use risc0_binfmt::{MemoryImage, Program};
 use risc0_circuit_rv32im::prove::emu::{
@@ -29,8 +29,9 @@ use risc0_zkvm_platform::{fileno, memory::GUEST_MAX_MEM, PAGE_SIZE};
 use tempfile::tempdir;
 
 use crate::{
-    host::client::env::SegmentPath, Assumption, Assumptions, ExecutorEnv, FileSegmentRef, Output,
-    Segment, SegmentRef, Session,
+    host::client::env::{JournalCallback, SegmentPath},
+    Assumption, Assumptions, ContinuationSnapshot, ExecutorEnv, FileSegmentRef, Output, Segment,
+    SegmentRef, Session,
 };
 
 use super::{
@@ -46,6 +47,7 @@ pub struct ExecutorImpl<'a> {
     image: MemoryImage,
     pub(crate) syscall_table: SyscallTable<'a>,
     profiler: Option<Rc<RefCell<Profiler>>>,
+    metadata: RefCell<BTreeMap<String, Vec<u8>>>,
 }
 
 impl<'a> ExecutorImpl<'a> {
@@ -80,7 +82,7 @@ impl<'a> ExecutorImpl<'a> {
         let program = Program::load_elf(elf, GUEST_MAX_MEM as u32)?;
         let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
 
-        let profiler = if env.pprof_out.is_some() {
+        let profiler = if env.pprof_out.is_some() || env.cycle_report {
             let profiler = Rc::new(RefCell::new(Profiler::new(elf, None)?));
             env.trace.push(profiler.clone());
             Some(profiler)
@@ -91,17 +93,58 @@ impl<'a> ExecutorImpl<'a> {
         Self::with_details(env, image, profiler)
     }
 
+    /// Construct a new [ExecutorImpl] that resumes a paused execution from a
+    /// [ContinuationSnapshot].
+    ///
+    /// The [Session] produced by running this executor will have a `pre_state`
+    /// equal to the `post_state` of the [Session] the snapshot was taken from.
+    ///
+    /// # Example
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, ExecutorImpl, ExitCode};
+    /// use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write(&MultiTestSpec::PauseContinue(0))
+    ///     .unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// let paused = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap().run().unwrap();
+    /// let snapshot = paused.snapshot().unwrap();
+    ///
+    /// let env = ExecutorEnv::builder().build().unwrap();
+    /// let resumed = ExecutorImpl::from_snapshot(env, snapshot).unwrap().run().unwrap();
+    /// assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    /// ```
+    ///
+    /// The profiler needs the ELF binary of the guest, which is not part of the
+    /// snapshot, so this returns an error if the [ExecutorEnv] enables
+    /// profiling or the cycle report.
+    pub fn from_snapshot(env: ExecutorEnv<'a>, snapshot: ContinuationSnapshot) -> Result<Self> {
+        snapshot.validate()?;
+        ensure!(
+            env.pprof_out.is_none() && !env.cycle_report,
+            "profiling is not supported when resuming from a continuation snapshot"
+        );
+        env.assumptions
+            .borrow_mut()
+            .cached
+            .extend(snapshot.assumptions);
+        Self::with_details(env, snapshot.image, None)
+    }
+
     fn with_details(
         env: ExecutorEnv<'a>,
         image: MemoryImage,
         profiler: Option<Rc<RefCell<Profiler>>>,
     ) -> Result<Self> {
-        let syscall_table = SyscallTable::new(&env);
+        let syscall_table = SyscallTable::new(&env)?;
         Ok(Self {
             env,
             image,
             syscall_table,
             profiler,
+            metadata: Default::default(),
         })
     }
 
@@ -122,7 +165,7 @@ impl<'a> ExecutorImpl<'a> {
     where
         F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
     {
-        let journal = Journal::default();
+        let journal = Journal::new(self.env.journal_callbacks.clone());
         self.env
             .posix_io
             .borrow_mut()
@@ -134,7 +177,11 @@ impl<'a> ExecutorImpl<'a> {
             .unwrap_or(DEFAULT_SEGMENT_LIMIT_PO2 as u32) as usize;
 
         let mut refs = Vec::new();
+        let cancellation_token = self.env.cancellation_token.clone();
         let mut exec = Executor::new(self.image.clone(), self, self.env.trace.clone());
+        if let Some(token) = cancellation_token {
+            exec = exec.with_interrupt_check(move || Ok(token.check()?));
+        }
 
         let start_time = Instant::now();
         let result = exec.run(segment_limit_po2, self.env.session_limit, |inner| {
@@ -181,6 +228,7 @@ impl<'a> ExecutorImpl<'a> {
             Ok(())
         })?;
         let elapsed = start_time.elapsed();
+        self.syscall_table.finish(result.exit_code)?;
 
         // Set the session_journal to the committed data iff the the guest set a non-zero output.
         let session_journal = result
@@ -198,14 +246,20 @@ impl<'a> ExecutorImpl<'a> {
         // Leave the assumptions cache so it can be used if execution is resumed from pause.
         let assumptions = mem::take(&mut self.env.assumptions.borrow_mut().accessed);
 
+        let mut cycle_report = None;
         if let Some(profiler) = self.profiler.take() {
-            let report = profiler.borrow_mut().finalize_to_vec();
-            std::fs::write(self.env.pprof_out.as_ref().unwrap(), report)?;
+            if self.env.cycle_report {
+                cycle_report = Some(profiler.borrow().cycle_report());
+            }
+            if let Some(pprof_out) = self.env.pprof_out.as_ref() {
+                let report = profiler.borrow_mut().finalize_to_vec();
+                std::fs::write(pprof_out, report)?;
+            }
         }
 
         self.image = result.post_image.clone();
 
-        let session = Session::new(
+        let mut session = Session::new(
             refs,
             session_journal,
             result.exit_code,
@@ -216,6 +270,8 @@ impl<'a> ExecutorImpl<'a> {
             result.pre_state,
             result.post_state,
         );
+        session.cycle_report = cycle_report;
+        session.metadata = self.metadata.take();
 
         tracing::info_span!("executor").in_scope(|| {
             tracing::info!("execution time: {}", elapsed.human_duration());
@@ -228,6 +284,7 @@ impl<'a> ExecutorImpl<'a> {
 
 struct ContextAdapter<'a> {
     ctx: &'a mut dyn NewSyscallContext,
+    metadata: &'a RefCell<BTreeMap<String, Vec<u8>>>,
 }
 
 impl<'a> SyscallContext for ContextAdapter<'a> {
@@ -235,6 +292,14 @@ impl<'a> SyscallContext for ContextAdapter<'a> {
         self.ctx.get_cycle()
     }
 
+    fn get_pc(&self) -> u32 {
+        self.ctx.get_pc()
+    }
+
+    fn get_segment_index(&self) -> usize {
+        self.ctx.get_segment_index()
+    }
+
     fn load_register(&mut self, idx: usize) -> u32 {
         self.ctx.peek_register(idx).unwrap()
     }
@@ -242,6 +307,16 @@ impl<'a> SyscallContext for ContextAdapter<'a> {
     fn load_u8(&mut self, addr: u32) -> Result<u8> {
         self.ctx.peek_u8(ByteAddr(addr))
     }
+
+    fn load_u32(&mut self, addr: u32) -> Result<u32> {
+        let addr = ByteAddr(addr);
+        ensure!(addr.is_aligned(), "unaligned word address: {addr:?}");
+        self.ctx.peek_u32(addr)
+    }
+
+    fn set_metadata(&mut self, key: &str, value: Vec<u8>) {
+        self.metadata.borrow_mut().insert(key.to_string(), value);
+    }
 }
 
 impl<'a> NewSyscall for ExecutorImpl<'a> {
@@ -251,24 +326,44 @@ impl<'a> NewSyscall for ExecutorImpl<'a> {
         ctx: &mut dyn NewSyscallContext,
         into_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        let mut ctx = ContextAdapter { ctx };
-        self.syscall_table
-            .get_syscall(&syscall)
-            .context(format!("Unknown syscall: {syscall:?}"))?
-            .borrow_mut()
-            .syscall(&syscall, &mut ctx, into_guest)
+        let mut ctx = ContextAdapter {
+            ctx,
+            metadata: &self.metadata,
+        };
+        self.syscall_table.syscall(syscall, &mut ctx, into_guest)
     }
 }
 
-// Capture the journal output in a buffer that we can access afterwards.
-#[derive(Clone, Default)]
-struct Journal {
+// Capture the journal output in a buffer that we can access afterwards, and
+// forward each write to the journal callbacks as it happens.
+#[derive(Clone)]
+struct Journal<'a> {
     buf: Rc<RefCell<Vec<u8>>>,
+    callbacks: Vec<Rc<RefCell<JournalCallback<'a>>>>,
+}
+
+impl<'a> Journal<'a> {
+    fn new(callbacks: Vec<Rc<RefCell<JournalCallback<'a>>>>) -> Self {
+        Self {
+            buf: Default::default(),
+            callbacks,
+        }
+    }
 }
 
-impl Write for Journal {
+impl<'a> Write for Journal<'a> {
     fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
-        self.buf.borrow_mut().write(bytes)
+        let offset = {
+            let mut buf = self.buf.borrow_mut();
+            let offset = buf.len();
+            buf.extend_from_slice(bytes);
+            offset
+        };
+        for callback in self.callbacks.iter() {
+            (callback.borrow_mut())(bytes, offset)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        }
+        Ok(bytes.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
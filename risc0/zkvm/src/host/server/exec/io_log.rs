@@ -0,0 +1,188 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording and replay of the responses the host gives to guest syscalls.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_zkvm_platform::syscall::{
+    nr::{SYS_CYCLE_COUNT, SYS_LOG, SYS_PANIC, SYS_VERIFY, SYS_VERIFY_INTEGRITY, SYS_WRITE},
+    reg_abi::{REG_A3, REG_A7},
+    SyscallName,
+};
+use serde::{Deserialize, Serialize};
+
+use super::syscall::{Syscall, SyscallContext};
+use crate::ExitCode;
+
+const IO_LOG_VERSION: u32 = 1;
+
+/// Syscalls that are always handled by the [crate::ExecutorEnv], even when
+/// replaying.
+///
+/// These either send data from the guest to the host, where they have side
+/// effects such as writing the journal, or return values that do not depend
+/// on host input.
+const PASSTHROUGH: &[SyscallName] = &[
+    SYS_CYCLE_COUNT,
+    SYS_LOG,
+    SYS_PANIC,
+    SYS_VERIFY,
+    SYS_VERIFY_INTEGRITY,
+    SYS_WRITE,
+];
+
+/// How host I/O is logged, as configured on the [crate::ExecutorEnvBuilder].
+#[derive(Clone)]
+pub(crate) enum IoLogMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// The response of the host to a single syscall.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct IoEvent {
+    syscall: String,
+    /// The argument registers a3 through a7 at the time of the syscall.
+    args: Vec<u32>,
+    to_guest: Vec<u32>,
+    a0: u32,
+    a1: u32,
+}
+
+pub(crate) enum IoLog {
+    Record(BufWriter<File>),
+    Replay {
+        events: VecDeque<IoEvent>,
+        index: usize,
+    },
+}
+
+impl IoLog {
+    pub(crate) fn new(mode: &IoLogMode) -> Result<Self> {
+        match mode {
+            IoLogMode::Record(path) => Self::record(path),
+            IoLogMode::Replay(path) => Self::replay(path),
+        }
+    }
+
+    fn record(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create I/O log: {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        bincode::serialize_into(&mut writer, &IO_LOG_VERSION)?;
+        Ok(Self::Record(writer))
+    }
+
+    fn replay(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open I/O log: {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        ensure!(
+            version == IO_LOG_VERSION,
+            "Unsupported I/O log version: {version}, expected {IO_LOG_VERSION}"
+        );
+        let mut events = VecDeque::new();
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(event) => events.push_back(event),
+                Err(err) => match *err {
+                    bincode::ErrorKind::Io(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                    err => bail!("Failed to read I/O log: {err}"),
+                },
+            }
+        }
+        Ok(Self::Replay { events, index: 0 })
+    }
+
+    /// Returns true if the given syscall is recorded or replayed.
+    pub(crate) fn is_logged(syscall: &str) -> bool {
+        !PASSTHROUGH.iter().any(|name| name.as_str() == syscall)
+    }
+
+    /// Handle a syscall, either by invoking `handler` and recording its
+    /// response, or by replaying a previously recorded response.
+    pub(crate) fn syscall(
+        &mut self,
+        syscall: &str,
+        handler: Option<&mut dyn Syscall>,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let args: Vec<_> = (REG_A3..=REG_A7)
+            .map(|idx| ctx.load_register(idx))
+            .collect();
+        match self {
+            IoLog::Record(writer) => {
+                let handler = handler.with_context(|| format!("Unknown syscall: {syscall:?}"))?;
+                let (a0, a1) = handler.syscall(syscall, ctx, to_guest)?;
+                let event = IoEvent {
+                    syscall: syscall.to_string(),
+                    args,
+                    to_guest: to_guest.to_vec(),
+                    a0,
+                    a1,
+                };
+                bincode::serialize_into(writer, &event)?;
+                Ok((a0, a1))
+            }
+            IoLog::Replay { events, index } => {
+                let event = events.pop_front().with_context(|| {
+                    format!("I/O log exhausted at event {index}: guest called {syscall:?}")
+                })?;
+                ensure!(
+                    event.syscall == syscall
+                        && event.args == args
+                        && event.to_guest.len() == to_guest.len(),
+                    "I/O log mismatch at event {index}: guest called {syscall:?} with args \
+                     {args:x?} and {} words to guest, but the log has {:?} with args {:x?} \
+                     and {} words to guest",
+                    to_guest.len(),
+                    event.syscall,
+                    event.args,
+                    event.to_guest.len()
+                );
+                *index += 1;
+                to_guest.copy_from_slice(&event.to_guest);
+                Ok((event.a0, event.a1))
+            }
+        }
+    }
+
+    /// Called at the end of each run of the executor.
+    ///
+    /// Flushes a recording, and ensures that a replayed log was fully consumed
+    /// once the guest has halted.
+    pub(crate) fn finish(&mut self, exit_code: ExitCode) -> Result<()> {
+        match self {
+            IoLog::Record(writer) => Ok(writer.flush()?),
+            IoLog::Replay { events, index } => {
+                if matches!(exit_code, ExitCode::Halted(_)) && !events.is_empty() {
+                    bail!(
+                        "I/O log mismatch: guest halted after {index} events, but the log has {} more",
+                        events.len()
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+}
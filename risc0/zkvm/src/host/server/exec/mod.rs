@@ -19,6 +19,7 @@
 //! contains an execution trace of the specified program.
 
 pub(crate) mod executor;
+pub(crate) mod io_log;
 pub(crate) mod profiler;
 pub(crate) mod syscall;
 #[cfg(test)]
@@ -41,7 +41,7 @@ use rrs_lib::instruction_formats::{IType, JType, OPCODE_JAL, OPCODE_JALR};
 use rustc_demangle::demangle;
 
 use self::proto::Line;
-use crate::{TraceCallback, TraceEvent};
+use crate::{CycleReport, TraceCallback, TraceEvent};
 
 mod proto {
     // Generated proto interface.
@@ -298,18 +298,36 @@ impl Profiler {
         frames
     }
 
-    /// Walk the profile tree rooted at node_ref, adding all call stacks in the profile to the
-    /// profile under construction. All call stacks encountered build on top of the base_stack.
-    fn walk_stacks(&mut self, node_ref: Rc<RefCell<CallNode>>, base_stack: Vec<Frame>) {
+    /// Walk the profile tree rooted at node_ref, collecting every call stack in the profile along
+    /// with the program counter and cycles counted at it. All call stacks encountered build on top
+    /// of the base_stack.
+    fn collect_stacks(
+        &self,
+        node_ref: Rc<RefCell<CallNode>>,
+        base_stack: Vec<Frame>,
+        stacks: &mut Vec<(Vec<Frame>, u32, usize)>,
+    ) {
         let node = node_ref.borrow();
-        for (&pc, count) in &node.counts {
+        for (&pc, &count) in &node.counts {
             let mut new_stack = base_stack.clone();
             let frames = self.lookup_pc(pc.into());
             if !frames.is_empty() {
                 new_stack.extend(frames);
             }
 
-            let location_ids: Vec<_> = new_stack
+            stacks.push((new_stack.clone(), pc, count));
+            if let Some(next_node_ref) = node.calls.get(&pc) {
+                self.collect_stacks(next_node_ref.clone(), new_stack, stacks);
+            }
+        }
+    }
+
+    /// Add all call stacks in the profile tree to the profile under construction.
+    fn walk_stacks(&mut self) {
+        let mut stacks = Vec::new();
+        self.collect_stacks(Rc::clone(&self.root), Vec::new(), &mut stacks);
+        for (stack, pc, count) in stacks {
+            let location_ids: Vec<_> = stack
                 .iter()
                 .rev()
                 .map(|fr| {
@@ -327,37 +345,42 @@ impl Profiler {
                 .collect();
             let sample = proto::Sample {
                 location_id: location_ids,
-                value: vec![*count as i64],
+                value: vec![count as i64],
                 ..Default::default()
             };
 
             if !sample.location_id.is_empty() {
                 self.profile.add_sample(sample);
             }
-
-            if let Some(next_node_ref) = node.calls.get(&pc) {
-                self.walk_stacks(next_node_ref.clone(), new_stack);
-            }
         }
     }
 
     /// Count and save the profiling samples, write the results to `output_path`.
     #[cfg(test)]
     pub(crate) fn finalize(mut self) -> ProfileBuilder {
-        let root_ref = Rc::clone(&self.root);
         tracing::debug!("{}", self.root.borrow().fmt(0, &self));
-        self.walk_stacks(root_ref, Vec::new());
+        self.walk_stacks();
         self.profile
     }
 
     /// Count and save the profiling samples, consuming the profiler and
     /// returning the compiled profile protobuf, encoded as bytes.
     pub fn finalize_to_vec(&mut self) -> Vec<u8> {
-        let root_ref = Rc::clone(&self.root);
         tracing::debug!("{}", self.root.borrow().fmt(0, &self));
-        self.walk_stacks(root_ref, Vec::new());
+        self.walk_stacks();
         self.profile.profile.encode_to_vec()
     }
+
+    /// Summarize the cycles counted so far as a [CycleReport].
+    pub(crate) fn cycle_report(&self) -> CycleReport {
+        let mut stacks = Vec::new();
+        self.collect_stacks(Rc::clone(&self.root), Vec::new(), &mut stacks);
+        CycleReport::from_stacks(
+            stacks.into_iter().map(|(stack, _, count)| {
+                (stack.into_iter().map(|fr| fr.name).collect(), count as u64)
+            }),
+        )
+    }
 }
 
 impl TraceCallback for Profiler {
@@ -16,7 +16,7 @@
 
 use std::{cell::RefCell, cmp::min, collections::HashMap, rc::Rc, str::from_utf8};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bytes::Bytes;
 use risc0_zkvm_platform::{
     fileno,
@@ -31,6 +31,7 @@ use risc0_zkvm_platform::{
     WORD_SIZE,
 };
 
+use super::io_log::IoLog;
 use crate::{
     host::client::{
         env::{Assumptions, ExecutorEnv},
@@ -38,7 +39,7 @@ use crate::{
         slice_io::SliceIo,
     },
     sha::{Digest, Digestible},
-    Assumption, MaybePruned, PrunedValueError, ReceiptClaim,
+    Assumption, ExitCode, MaybePruned, PrunedValueError, ReceiptClaim,
 };
 
 /// A host-side implementation of a system call.
@@ -53,16 +54,30 @@ pub trait Syscall {
 }
 
 /// Access to memory and machine state for syscalls.
+///
+/// Guest memory is read-only from a syscall. Data is returned to the guest
+/// through the `to_guest` buffer and the `(a0, a1)` return values of
+/// [Syscall::syscall], so that it is recorded for proving.
 pub trait SyscallContext {
     /// Returns the current cycle being executed.
     fn get_cycle(&self) -> usize;
 
+    /// Returns the program counter of the `ecall` instruction that invoked the
+    /// syscall.
+    fn get_pc(&self) -> u32;
+
+    /// Returns the index of the [crate::Segment] being executed.
+    fn get_segment_index(&self) -> usize;
+
     /// Loads the value of the given register, e.g. REG_A0.
     fn load_register(&mut self, idx: usize) -> u32;
 
     /// Loads an individual byte from memory.
     fn load_u8(&mut self, addr: u32) -> Result<u8>;
 
+    /// Loads an individual word from memory. `addr` must be word-aligned.
+    fn load_u32(&mut self, addr: u32) -> Result<u32>;
+
     /// Loads bytes from the given region of memory.
     fn load_region(&mut self, addr: u32, size: u32) -> Result<Vec<u8>> {
         let mut region = Vec::new();
@@ -71,17 +86,38 @@ pub trait SyscallContext {
         }
         Ok(region)
     }
+
+    /// Loads `count` words from the given region of memory. `addr` must be
+    /// word-aligned.
+    fn load_region_words(&mut self, addr: u32, count: u32) -> Result<Vec<u32>> {
+        (0..count)
+            .map(|i| self.load_u32(addr + i * WORD_SIZE as u32))
+            .collect()
+    }
+
+    /// Attach metadata to the [crate::Session] being executed.
+    ///
+    /// The value is available as [crate::Session::metadata] once execution
+    /// finishes, and replaces any previous value with the same key.
+    fn set_metadata(&mut self, key: &str, value: Vec<u8>);
 }
 
 #[derive(Clone)]
 pub(crate) struct SyscallTable<'a> {
     pub(crate) inner: HashMap<String, Rc<RefCell<dyn Syscall + 'a>>>,
+    io_log: Option<Rc<RefCell<IoLog>>>,
 }
 
 impl<'a> SyscallTable<'a> {
-    pub fn new(env: &ExecutorEnv<'a>) -> Self {
+    pub fn new(env: &ExecutorEnv<'a>) -> Result<Self> {
+        let io_log = env
+            .io_log
+            .as_ref()
+            .map(|mode| Ok::<_, anyhow::Error>(Rc::new(RefCell::new(IoLog::new(mode)?))))
+            .transpose()?;
         let mut this = Self {
             inner: HashMap::new(),
+            io_log,
         };
 
         let sys_verify = SysVerify::new(env.assumptions.clone());
@@ -103,8 +139,11 @@ impl<'a> SyscallTable<'a> {
             this.inner
                 .insert(syscall.clone(), Rc::new(RefCell::new(handler)));
         }
+        for (syscall, handler) in env.syscalls.iter() {
+            this.inner.insert(syscall.clone(), handler.clone());
+        }
 
-        this
+        Ok(this)
     }
 
     pub(crate) fn with_syscall(
@@ -120,6 +159,40 @@ impl<'a> SyscallTable<'a> {
     pub(crate) fn get_syscall(&self, name: &str) -> Option<&Rc<RefCell<(dyn Syscall + 'a)>>> {
         self.inner.get(name)
     }
+
+    /// Dispatch a syscall to its handler, recording or replaying the response
+    /// if host I/O is being logged.
+    pub(crate) fn syscall(
+        &self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let handler = self.get_syscall(syscall);
+        if let Some(io_log) = self.io_log.as_ref() {
+            if IoLog::is_logged(syscall) {
+                let mut handler = handler.map(|handler| handler.borrow_mut());
+                return io_log.borrow_mut().syscall(
+                    syscall,
+                    handler.as_deref_mut().map(|handler| handler as _),
+                    ctx,
+                    to_guest,
+                );
+            }
+        }
+        handler
+            .with_context(|| format!("Unknown syscall: {syscall:?}"))?
+            .borrow_mut()
+            .syscall(syscall, ctx, to_guest)
+    }
+
+    /// Finish logging host I/O at the end of a run of the executor.
+    pub(crate) fn finish(&self, exit_code: ExitCode) -> Result<()> {
+        match self.io_log.as_ref() {
+            Some(io_log) => io_log.borrow_mut().finish(exit_code),
+            None => Ok(()),
+        }
+    }
 }
 
 pub(crate) struct SysCycleCount;
@@ -16,7 +16,8 @@ use std::{
     collections::{BTreeMap, HashSet},
     io::Cursor,
     str::from_utf8,
-    sync::Mutex,
+    sync::{Arc, Mutex},
+    time::Instant,
 };
 
 use anyhow::Result;
@@ -26,7 +27,14 @@ use risc0_zkvm_methods::{
     multi_test::{MultiTestSpec, SYS_MULTI_TEST},
     HELLO_COMMIT_ELF, MULTI_TEST_ELF, RAND_ELF, SLICE_IO_ELF, STANDARD_LIB_ELF,
 };
-use risc0_zkvm_platform::{fileno, syscall::nr::SYS_RANDOM, PAGE_SIZE, WORD_SIZE};
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::{
+        nr::SYS_RANDOM,
+        reg_abi::{REG_A3, REG_A4},
+    },
+    PAGE_SIZE, WORD_SIZE,
+};
 use sha2::{Digest as _, Sha256};
 use test_log::test;
 
@@ -40,7 +48,7 @@ use crate::{
     },
     serde::to_vec,
     sha::{Digest, Digestible},
-    ExecutorEnv, ExecutorImpl, ExitCode,
+    CancellationError, CancellationToken, ExecutorEnv, ExecutorImpl, ExitCode, SendExecutorEnv,
 };
 
 fn run_test(spec: MultiTestSpec) {
@@ -182,6 +190,138 @@ fn host_syscall() {
     assert_eq!(*actual.lock().unwrap(), expected[..expected.len() - 1]);
 }
 
+#[test]
+fn host_syscall_async() {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    let expected: Vec<Bytes> = vec!["".into(), "a".into(), "ab".into(), "abc".into()];
+    let input = MultiTestSpec::Syscall {
+        count: expected.len() as u32 - 1,
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    // Reply to each request with the request followed by the next letter.
+    let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let addr = listener.local_addr().unwrap();
+    runtime.spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            buf.push(b'a' + buf.len() as u8);
+            stream.write_all(&buf).await.unwrap();
+        }
+    });
+
+    let actual: Arc<Mutex<Vec<Bytes>>> = Default::default();
+    let env = SendExecutorEnv::builder()
+        .write(&input)
+        .unwrap()
+        .async_io_callback(SYS_MULTI_TEST, runtime.handle().clone(), {
+            let actual = actual.clone();
+            move |buf: Bytes| {
+                let actual = actual.clone();
+                async move {
+                    actual.lock().unwrap().push(buf.clone());
+                    let mut stream = TcpStream::connect(addr).await?;
+                    stream.write_all(&buf).await?;
+                    stream.shutdown().await?;
+                    let mut reply = Vec::new();
+                    stream.read_to_end(&mut reply).await?;
+                    Ok(reply.into())
+                }
+            }
+        })
+        .build();
+
+    // The environment can be moved to another thread for execution.
+    let exit_code = std::thread::spawn(move || {
+        let env = env.into_env().unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap()
+            .exit_code
+    })
+    .join()
+    .unwrap();
+    assert_eq!(exit_code, ExitCode::Halted(0));
+    assert_eq!(*actual.lock().unwrap(), expected[..expected.len() - 1]);
+}
+
+#[test]
+fn send_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    assert_send::<SendExecutorEnv>();
+    assert_send_sync(&crate::default_prover_send());
+    assert_send_sync(&crate::default_executor_send());
+}
+
+#[test]
+fn custom_syscall() {
+    // Implements the `env::send_recv_slice` protocol, replying with one word.
+    #[derive(Default)]
+    struct Echo {
+        pending: bool,
+        calls: Arc<Mutex<Vec<(u32, usize, Vec<u32>)>>>,
+    }
+
+    impl Syscall for Echo {
+        fn syscall(
+            &mut self,
+            _syscall: &str,
+            ctx: &mut dyn SyscallContext,
+            to_guest: &mut [u32],
+        ) -> Result<(u32, u32)> {
+            let buf_ptr = ctx.load_register(REG_A3);
+            let buf_len = ctx.load_register(REG_A4);
+            let words = ctx.load_region_words(buf_ptr, buf_len / WORD_SIZE as u32)?;
+            self.calls
+                .lock()
+                .unwrap()
+                .push((ctx.get_pc(), ctx.get_segment_index(), words));
+            self.pending = !self.pending;
+            if self.pending {
+                return Ok((WORD_SIZE as u32, 0));
+            }
+            to_guest[0] = 0xdeadbeef;
+            let count = self.calls.lock().unwrap().len() as u32;
+            ctx.set_metadata("calls", count.to_le_bytes().to_vec());
+            Ok((0, 0))
+        }
+    }
+
+    let echo = Echo::default();
+    let calls = echo.calls.clone();
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Syscall { count: 2 })
+        .unwrap()
+        .syscall(SYS_MULTI_TEST, echo)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 4);
+    for (pc, segment_index, _) in calls.iter() {
+        assert_ne!(*pc, 0);
+        assert_eq!(*segment_index, 0);
+    }
+    // The second request sends back the word from the first reply.
+    assert!(calls[0].2.is_empty());
+    assert_eq!(calls[2].2, vec![0xdeadbeef]);
+    assert_eq!(session.metadata["calls"], 4u32.to_le_bytes());
+}
+
 // Make sure panics in the callback get propagated correctly.
 #[test]
 #[should_panic(expected = "I am panicking from here!")]
@@ -242,6 +382,67 @@ fn bigint_accel() {
     }
 }
 
+#[test]
+fn bigint_modulus() {
+    use num_bigint::BigUint;
+    use rand::RngCore;
+    use risc0_zkvm_platform::syscall::bigint::WIDTH_384_WORDS;
+
+    type Words = [u32; WIDTH_384_WORDS];
+
+    fn to_words(x: &BigUint) -> Words {
+        let mut words = [0u32; WIDTH_384_WORDS];
+        for (word, digit) in words.iter_mut().zip(x.to_u32_digits()) {
+            *word = digit;
+        }
+        words
+    }
+
+    // The BLS12-381 base field modulus.
+    let n = BigUint::parse_bytes(
+        b"1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab",
+        16,
+    )
+    .unwrap();
+    let mut rng = rand::thread_rng();
+    let mut random = || {
+        let mut bytes = [0u8; 4 * WIDTH_384_WORDS];
+        rng.fill_bytes(&mut bytes);
+        BigUint::from_bytes_le(&bytes) % &n
+    };
+
+    for (x, y) in [
+        (BigUint::default(), random()),
+        (&n - 1u32, &n - 1u32),
+        (random(), random()),
+    ] {
+        let exp = random();
+        let input = MultiTestSpec::BigIntModulus {
+            x: to_words(&x),
+            y: to_words(&y),
+            exp: exp.to_u32_digits(),
+            modulus: to_words(&n),
+        };
+        let env = ExecutorEnv::builder()
+            .write(&input)
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+        let session = exec.run().unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+        let (add, sub, mul, pow, inv): (Words, Words, Words, Words, Option<Words>) =
+            crate::serde::from_slice(&session.journal.unwrap().bytes).unwrap();
+        assert_eq!(add, to_words(&((&x + &y) % &n)));
+        assert_eq!(sub, to_words(&((&x + &n - &y) % &n)));
+        assert_eq!(mul, to_words(&((&x * &y) % &n)));
+        assert_eq!(pow, to_words(&x.modpow(&exp, &n)));
+        let expected_inv = (x != BigUint::default()).then(|| to_words(&x.modpow(&(&n - 2u32), &n)));
+        assert_eq!(inv, expected_inv);
+    }
+}
+
 #[test]
 fn env_stdio() {
     const MSG: &str = "Hello world!  This is a test of standard input and output.";
@@ -751,6 +952,45 @@ fn random() {
     run_test(MultiTestSpec::DoRandom);
 }
 
+#[test]
+fn record_replay_io() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("io.log");
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoRandom)
+        .unwrap()
+        .record_io(&path)
+        .build()
+        .unwrap();
+    let recorded = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    // Neither stdin nor the random values are provided by the host this time.
+    let env = ExecutorEnv::builder().replay_io(&path).build().unwrap();
+    let replayed = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(replayed.exit_code, ExitCode::Halted(0));
+    assert_eq!(replayed.journal, recorded.journal);
+    assert_eq!(
+        replayed.post_image.compute_id(),
+        recorded.post_image.compute_id()
+    );
+
+    // A different guest makes different requests.
+    let env = ExecutorEnv::builder().replay_io(&path).build().unwrap();
+    let err = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(format!("{err:?}").contains("I/O log"), "{err:?}");
+}
+
 #[test]
 #[should_panic(expected = "WARNING: `getrandom()` called from guest.")]
 fn getrandom_panic() {
@@ -779,6 +1019,80 @@ fn slice_io() {
     run(b"0000");
 }
 
+#[test]
+fn journal_callback() {
+    let chunks: Arc<Mutex<Vec<(Vec<u8>, usize)>>> = Default::default();
+    let env = ExecutorEnv::builder()
+        .journal_callback({
+            let chunks = chunks.clone();
+            move |chunk, offset| {
+                chunks.lock().unwrap().push((chunk.to_vec(), offset));
+                Ok(())
+            }
+        })
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let chunks = chunks.lock().unwrap();
+    assert!(!chunks.is_empty());
+    let mut streamed = Vec::new();
+    for (chunk, offset) in chunks.iter() {
+        assert_eq!(*offset, streamed.len());
+        streamed.extend_from_slice(chunk);
+    }
+    assert_eq!(streamed, b"hello world");
+    assert_eq!(session.journal.unwrap().bytes, streamed);
+}
+
+#[test]
+fn journal_callback_cancel() {
+    let env = ExecutorEnv::builder()
+        .journal_callback(|_, _| anyhow::bail!("cancelled by journal callback"))
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(format!("{err:?}").contains("cancelled by journal callback"));
+}
+
+#[test]
+fn cancellation_token() {
+    let run = |token: CancellationToken| {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles: 1 << 20 })
+            .unwrap()
+            .cancellation_token(token)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .err()
+            .unwrap()
+    };
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let err = run(token);
+    assert_eq!(
+        err.downcast_ref::<CancellationError>(),
+        Some(&CancellationError::Cancelled)
+    );
+
+    let err = run(CancellationToken::with_deadline(Instant::now()));
+    assert_eq!(
+        err.downcast_ref::<CancellationError>(),
+        Some(&CancellationError::DeadlineExceeded)
+    );
+}
+
 // Check that a compliant host will return an error on panic.
 #[test]
 fn panic() {
@@ -891,6 +1205,76 @@ fn profiler() {
     assert!(check(&fr, addr), "{fr:#?} {addr}");
 }
 
+#[test]
+fn cycle_report() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Profiler)
+        .unwrap()
+        .enable_cycle_report()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    let report = session.cycle_report.unwrap();
+    assert!(report.total_cycles > 0);
+    assert_eq!(
+        report.total_cycles,
+        report.stacks.iter().map(|x| x.cycles).sum::<u64>()
+    );
+    for name in ["profile_test_func1", "profile_test_func2"] {
+        let func = report
+            .functions
+            .iter()
+            .find(|x| x.name.contains(name))
+            .unwrap_or_else(|| panic!("{name} missing from {report:#?}"));
+        assert!(func.total_cycles > 0);
+        assert!(func.self_cycles <= func.total_cycles);
+    }
+}
+
+#[test]
+fn snapshot_resume_profiling() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseContinue(0))
+        .unwrap()
+        .build()
+        .unwrap();
+    let paused = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(paused.exit_code, ExitCode::Paused(0));
+
+    // Profiling options are rejected rather than silently ignored.
+    let env = ExecutorEnv::builder()
+        .enable_cycle_report()
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_snapshot(env, paused.snapshot().unwrap())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("profiling"), "{err}");
+
+    let env = ExecutorEnv::builder()
+        .enable_profiler("profile.pb")
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_snapshot(env, paused.snapshot().unwrap())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("profiling"), "{err}");
+
+    let env = ExecutorEnv::builder().build().unwrap();
+    let resumed = ExecutorImpl::from_snapshot(env, paused.snapshot().unwrap())
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+}
+
 #[test]
 fn oom() {
     let env = ExecutorEnv::builder()
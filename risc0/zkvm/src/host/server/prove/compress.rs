@@ -0,0 +1,285 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compression of composite receipts with the lift, join and resolve recursion steps.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+use super::ProverServer;
+use crate::{
+    host::receipt::{CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt},
+    sha::Digestible,
+    Container, ReceiptClaim,
+};
+
+/// The recursion steps used to compress a [CompositeReceipt].
+///
+/// This is implemented for every [ProverServer], and by the API coordinator for remote servers.
+/// Provers whose recursion steps can be shared across threads also provide a [Sync]
+/// implementation, which allows independent subtrees to be proven concurrently with
+/// [compress_segments_par].
+pub(crate) trait JoinSteps {
+    /// Lift a [SegmentReceipt] into a [SuccinctReceipt].
+    fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt>;
+
+    /// Join two [SuccinctReceipt]s of adjacent ranges into a single [SuccinctReceipt].
+    fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt>;
+
+    /// Remove an assumption from a conditional [SuccinctReceipt] using a [SuccinctReceipt]
+    /// proving that assumption.
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        assumption: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt>;
+}
+
+impl<P: ProverServer + ?Sized> JoinSteps for P {
+    fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
+        ProverServer::lift(self, receipt)
+    }
+
+    fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        ProverServer::join(self, a, b)
+    }
+
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        assumption: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        ProverServer::resolve(self, conditional, assumption)
+    }
+}
+
+/// Compress a [CompositeReceipt] into a single [SuccinctReceipt], folding its segment receipts
+/// left-to-right and then resolving its assumptions.
+pub(crate) fn compress<S: JoinSteps + ?Sized>(
+    steps: &S,
+    receipt: &CompositeReceipt,
+) -> Result<SuccinctReceipt> {
+    // Compress all receipts in the top-level session into one succinct receipt for the session.
+    let continuation_receipt = receipt
+        .segments
+        .iter()
+        .try_fold(
+            None,
+            |left: Option<SuccinctReceipt>, right: &SegmentReceipt| -> Result<_> {
+                Ok(Some(match left {
+                    Some(left) => steps.join(&left, &steps.lift(right)?)?,
+                    None => steps.lift(right)?,
+                }))
+            },
+        )?
+        .ok_or(anyhow!(
+            "malformed composite receipt has no continuation segment receipts"
+        ))?;
+
+    resolve_assumptions(steps, &receipt.assumptions, continuation_receipt)
+}
+
+/// Compress assumptions and resolve them to get the final succinct receipt.
+pub(crate) fn resolve_assumptions<S: JoinSteps + ?Sized>(
+    steps: &S,
+    assumptions: &[InnerReceipt],
+    continuation_receipt: SuccinctReceipt,
+) -> Result<SuccinctReceipt> {
+    assumptions.iter().try_fold(
+        continuation_receipt,
+        |conditional: SuccinctReceipt, assumption: &InnerReceipt| match assumption {
+            InnerReceipt::Succinct(assumption) => steps.resolve(&conditional, assumption),
+            InnerReceipt::Composite(assumption) => {
+                steps.resolve(&conditional, &compress(steps, assumption)?)
+            }
+            InnerReceipt::Fake { .. } => bail!(
+                "compressing composite receipts with fake receipt assumptions is not supported"
+            ),
+            InnerReceipt::Compact(_) => bail!(
+                "compressing composite receipts with Compact receipt assumptions is not supported"
+            ),
+        },
+    )
+}
+
+/// Lift and join the given segment receipts into a single [SuccinctReceipt].
+///
+/// Adjacent receipts are joined pairwise, forming a balanced binary tree of depth
+/// `ceil(log2(n))`. Each step is run in turn on the calling thread.
+///
+/// If `checkpoint_dir` is specified, each intermediate [SuccinctReceipt] is written to that
+/// directory as it is produced. Receipts already present in the directory are loaded instead of
+/// being proven again, which allows an interrupted run to resume from the last finished join.
+pub(crate) fn compress_segments<S: JoinSteps + ?Sized>(
+    steps: &S,
+    segments: &[SegmentReceipt],
+    checkpoint_dir: Option<&Path>,
+) -> Result<SuccinctReceipt> {
+    let tree = JoinTree::new(steps, segments, checkpoint_dir)?;
+    tree.prove_range(0, segments.len())
+}
+
+/// Lift and join the given segment receipts into a single [SuccinctReceipt], proving independent
+/// subtrees concurrently.
+///
+/// All segments are lifted in parallel. Otherwise this is the same as [compress_segments].
+pub(crate) fn compress_segments_par<S: JoinSteps + Sync + ?Sized>(
+    steps: &S,
+    segments: &[SegmentReceipt],
+    checkpoint_dir: Option<&Path>,
+) -> Result<SuccinctReceipt> {
+    let tree = JoinTree::new(steps, segments, checkpoint_dir)?;
+    tree.prove_range_par(0, segments.len())
+}
+
+struct JoinTree<'a, S: ?Sized> {
+    steps: &'a S,
+    segments: &'a [SegmentReceipt],
+    checkpoint_dir: Option<&'a Path>,
+}
+
+impl<'a, S: JoinSteps + Sync + ?Sized> JoinTree<'a, S> {
+    /// Produce a [SuccinctReceipt] covering the segments in the range `start..end`, proving the
+    /// two halves of the range concurrently.
+    fn prove_range_par(&self, start: usize, end: usize) -> Result<SuccinctReceipt> {
+        self.prove_range_with(start, end, |mid| {
+            rayon::join(
+                || self.prove_range_par(start, mid),
+                || self.prove_range_par(mid, end),
+            )
+        })
+    }
+}
+
+impl<'a, S: JoinSteps + ?Sized> JoinTree<'a, S> {
+    fn new(
+        steps: &'a S,
+        segments: &'a [SegmentReceipt],
+        checkpoint_dir: Option<&'a Path>,
+    ) -> Result<Self> {
+        ensure!(
+            !segments.is_empty(),
+            "malformed composite receipt has no continuation segment receipts"
+        );
+        if let Some(dir) = checkpoint_dir {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create checkpoint dir: {}", dir.display()))?;
+        }
+        Ok(Self {
+            steps,
+            segments,
+            checkpoint_dir,
+        })
+    }
+
+    /// Produce a [SuccinctReceipt] covering the segments in the range `start..end`.
+    fn prove_range(&self, start: usize, end: usize) -> Result<SuccinctReceipt> {
+        self.prove_range_with(start, end, |mid| {
+            (self.prove_range(start, mid), self.prove_range(mid, end))
+        })
+    }
+
+    /// Produce a [SuccinctReceipt] covering the segments in the range `start..end`, using
+    /// `subtrees` to prove the ranges `start..mid` and `mid..end` when the range must be split.
+    fn prove_range_with(
+        &self,
+        start: usize,
+        end: usize,
+        subtrees: impl FnOnce(usize) -> (Result<SuccinctReceipt>, Result<SuccinctReceipt>),
+    ) -> Result<SuccinctReceipt> {
+        if let Some(receipt) = self.load_checkpoint(start, end)? {
+            tracing::debug!("compress: loaded checkpoint for segments {start}..{end}");
+            return Ok(receipt);
+        }
+
+        let receipt = if end - start == 1 {
+            tracing::debug!("compress: lift segment {start}");
+            self.steps.lift(&self.segments[start])?
+        } else {
+            let mid = start + (end - start) / 2;
+            let (left, right) = subtrees(mid);
+            tracing::debug!("compress: join segments {start}..{mid} and {mid}..{end}");
+            self.steps.join(&left?, &right?)?
+        };
+
+        self.store_checkpoint(start, end, &receipt)?;
+        Ok(receipt)
+    }
+
+    /// The claim of a receipt covering the segments in the range `start..end`, as produced by
+    /// lifting and joining them.
+    fn range_claim(&self, start: usize, end: usize) -> ReceiptClaim {
+        let first = &self.segments[start].claim;
+        let last = &self.segments[end - 1].claim;
+        ReceiptClaim {
+            pre: first.pre.clone(),
+            post: last.post.clone(),
+            exit_code: last.exit_code,
+            input: first.input,
+            output: last.output.clone(),
+        }
+    }
+
+    fn checkpoint_path(&self, start: usize, end: usize) -> Option<PathBuf> {
+        self.checkpoint_dir
+            .map(|dir| dir.join(format!("{start}-{end}.receipt")))
+    }
+
+    /// Load a previously stored receipt for the range `start..end`, if one exists.
+    ///
+    /// A checkpoint is only accepted if its seal verifies and its claim is exactly the claim that
+    /// lifting and joining the segments in the range would produce. This guards against reusing
+    /// a corrupted checkpoint, or a checkpoint directory written for a different receipt.
+    fn load_checkpoint(&self, start: usize, end: usize) -> Result<Option<SuccinctReceipt>> {
+        let Some(path) = self.checkpoint_path(start, end) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read(&path)?;
+        let receipt = SuccinctReceipt::from_container_bytes(&contents)
+            .with_context(|| format!("failed to decode checkpoint: {}", path.display()))?;
+        receipt
+            .verify_integrity()
+            .with_context(|| format!("failed to verify checkpoint: {}", path.display()))?;
+
+        if receipt.claim.digest() != self.range_claim(start, end).digest() {
+            return Err(anyhow!(
+                "checkpoint {} does not match segments {start}..{end}",
+                path.display()
+            ));
+        }
+
+        Ok(Some(receipt))
+    }
+
+    fn store_checkpoint(&self, start: usize, end: usize, receipt: &SuccinctReceipt) -> Result<()> {
+        let Some(path) = self.checkpoint_path(start, end) else {
+            return Ok(());
+        };
+
+        // Write to a temporary file first so that a crash mid-write never leaves behind a
+        // truncated checkpoint.
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, receipt.to_container_bytes()?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
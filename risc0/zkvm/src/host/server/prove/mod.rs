@@ -14,22 +14,28 @@
 
 //! Run the zkVM guest and prove its results.
 
+pub(crate) mod compress;
 mod dev_mode;
+pub(crate) mod pipeline;
 mod prover_impl;
 #[cfg(test)]
 mod tests;
 
-use std::rc::Rc;
+use std::{path::Path, rc::Rc};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::Result;
 use cfg_if::cfg_if;
 use risc0_core::field::baby_bear::{BabyBear, Elem, ExtElem};
 use risc0_zkp::hal::{CircuitHal, Hal};
 
+pub use self::pipeline::PipelineProver;
 use self::{dev_mode::DevModeProver, prover_impl::ProverImpl};
 use crate::{
-    host::receipt::{CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt},
-    is_dev_mode, ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, Segment, Session, VerifierContext,
+    host::receipt::{
+        CompactReceipt, CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt,
+    },
+    is_dev_mode, CancellationToken, ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, ReceiptKind,
+    Segment, Session, VerifierContext,
 };
 
 /// A ProverServer can execute a given ELF binary and produce a [Receipt]
@@ -87,39 +93,78 @@ pub trait ProverServer {
     /// accomplishes this by iterative application of the recursion programs including lift, join,
     /// and resolve.
     fn compress(&self, receipt: &CompositeReceipt) -> Result<SuccinctReceipt> {
-        // Compress all receipts in the top-level session into one succinct receipt for the session.
-        let continuation_receipt = receipt
-            .segments
-            .iter()
-            .try_fold(
-                None,
-                |left: Option<SuccinctReceipt>, right: &SegmentReceipt| -> Result<_> {
-                    Ok(Some(match left {
-                        Some(left) => self.join(&left, &self.lift(right)?)?,
-                        None => self.lift(right)?,
-                    }))
-                },
-            )?
-            .ok_or(anyhow!(
-                "malformed composite receipt has no continuation segment receipts"
-            ))?;
-
-        // Compress assumptions and resolve them to get the final succinct receipt.
-        receipt.assumptions.iter().try_fold(
-            continuation_receipt,
-            |conditional: SuccinctReceipt, assumption: &InnerReceipt| match assumption {
-                InnerReceipt::Succinct(assumption) => self.resolve(&conditional, assumption),
-                InnerReceipt::Composite(assumption) => {
-                    self.resolve(&conditional, &self.compress(assumption)?)
-                }
-                InnerReceipt::Fake { .. } => bail!(
-                    "compressing composite receipts with fake receipt assumptions is not supported"
-                ),
-                InnerReceipt::Compact(_) => bail!(
-                    "compressing composite receipts with Compact receipt assumptions is not supported"
-                )
-            },
-        )
+        compress::compress(self, receipt)
+    }
+
+    /// Compress a [CompositeReceipt] into a single [SuccinctReceipt], joining segments as a
+    /// balanced binary tree.
+    ///
+    /// Unlike [ProverServer::compress], which folds segment receipts left-to-right, this joins
+    /// adjacent receipts pairwise, so that the depth of the recursion is logarithmic in the number
+    /// of segments. The default implementation proves each step in turn with
+    /// [ProverServer::lift] and [ProverServer::join]; the local prover lifts all segments in
+    /// parallel and proves independent joins concurrently.
+    ///
+    /// If `checkpoint_dir` is specified, every intermediate [SuccinctReceipt] is written to it.
+    /// When called again with the same directory, receipts found there are reused, allowing an
+    /// interrupted run to pick up from the last finished join rather than starting over.
+    fn compress_tree(
+        &self,
+        receipt: &CompositeReceipt,
+        checkpoint_dir: Option<&Path>,
+    ) -> Result<SuccinctReceipt> {
+        let continuation_receipt =
+            compress::compress_segments(self, &receipt.segments, checkpoint_dir)?;
+        compress::resolve_assumptions(self, &receipt.assumptions, continuation_receipt)
+    }
+
+    /// Compact a [SuccinctReceipt] into a [CompactReceipt].
+    ///
+    /// The receipt is first proven again with [ProverServer::identity_p254], and the resulting
+    /// seal is then wrapped in a Groth16 proof. With the `groth16-native` feature, the Groth16
+    /// proof is produced by `risc0_groth16::native::stark_to_snark`, which runs the external
+    /// circom witness generator (an x86-64 binary) and computes the proof in-process; otherwise it
+    /// is produced in Docker by [crate::stark_to_snark]. Implementations may override this to use
+    /// another Groth16 prover.
+    fn compact(&self, receipt: &SuccinctReceipt) -> Result<CompactReceipt> {
+        let ident_receipt = self.identity_p254(receipt)?;
+        let seal = stark_to_snark(&ident_receipt.get_seal_bytes())?.to_vec();
+        Ok(CompactReceipt {
+            seal,
+            claim: receipt.claim.clone(),
+        })
+    }
+
+    /// Compress a [Receipt] into the specified [ReceiptKind].
+    ///
+    /// Receipts that are already at least as compressed as requested, and fake receipts produced
+    /// in dev mode, are returned unchanged.
+    fn compress_to(&self, receipt: &Receipt, kind: ReceiptKind) -> Result<Receipt> {
+        let inner = match (&receipt.inner, kind) {
+            (InnerReceipt::Composite(inner), ReceiptKind::Succinct) => {
+                InnerReceipt::Succinct(self.compress(inner)?)
+            }
+            (InnerReceipt::Composite(inner), ReceiptKind::Compact) => {
+                InnerReceipt::Compact(self.compact(&self.compress(inner)?)?)
+            }
+            (InnerReceipt::Succinct(inner), ReceiptKind::Compact) => {
+                InnerReceipt::Compact(self.compact(inner)?)
+            }
+            _ => return Ok(receipt.clone()),
+        };
+        Ok(Receipt::new(inner, receipt.journal.bytes.clone()))
+    }
+}
+
+/// Wrap the seal of an identity_p254 receipt in a Groth16 proof with the Groth16 prover selected
+/// by feature flags.
+fn stark_to_snark(identity_p254_seal_bytes: &[u8]) -> Result<risc0_groth16::Seal> {
+    cfg_if! {
+        if #[cfg(feature = "groth16-native")] {
+            risc0_groth16::native::stark_to_snark(identity_p254_seal_bytes)
+        } else {
+            risc0_groth16::docker::stark_to_snark(identity_p254_seal_bytes)
+        }
     }
 }
 
@@ -155,25 +200,28 @@ mod cuda {
     use risc0_zkp::hal::cuda::{CudaHalPoseidon2, CudaHalSha256};
 
     use super::{HalPair, ProverImpl, ProverServer};
-    use crate::ProverOpts;
+    use crate::{CancellationToken, ProverOpts};
 
-    pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
+    pub fn get_prover_server(
+        opts: &ProverOpts,
+        cancellation_token: Option<CancellationToken>,
+    ) -> Result<Rc<dyn ProverServer>> {
         match opts.hashfn.as_str() {
             "sha-256" => {
                 let hal = Rc::new(CudaHalSha256::new());
                 let circuit_hal = Rc::new(CudaCircuitHalSha256::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "cuda",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("cuda", HalPair { hal, circuit_hal })
+                        .with_cancellation_token(cancellation_token.clone()),
+                ))
             }
             "poseidon2" => {
                 let hal = Rc::new(CudaHalPoseidon2::new());
                 let circuit_hal = Rc::new(CudaCircuitHalPoseidon2::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "cuda",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("cuda", HalPair { hal, circuit_hal })
+                        .with_cancellation_token(cancellation_token.clone()),
+                ))
             }
             _ => bail!("Unsupported hashfn: {}", opts.hashfn),
         }
@@ -191,25 +239,28 @@ mod metal {
     };
 
     use super::{HalPair, ProverImpl, ProverServer};
-    use crate::ProverOpts;
+    use crate::{CancellationToken, ProverOpts};
 
-    pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
+    pub fn get_prover_server(
+        opts: &ProverOpts,
+        cancellation_token: Option<CancellationToken>,
+    ) -> Result<Rc<dyn ProverServer>> {
         match opts.hashfn.as_str() {
             "sha-256" => {
                 let hal = Rc::new(MetalHalSha256::new());
                 let circuit_hal = Rc::new(MetalCircuitHal::<MetalHashSha256>::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "metal",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("metal", HalPair { hal, circuit_hal })
+                        .with_cancellation_token(cancellation_token.clone()),
+                ))
             }
             "poseidon2" => {
                 let hal = Rc::new(MetalHalPoseidon2::new());
                 let circuit_hal = Rc::new(MetalCircuitHal::<MetalHashPoseidon2>::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "metal",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("metal", HalPair { hal, circuit_hal })
+                        .with_cancellation_token(cancellation_token.clone()),
+                ))
             }
             _ => bail!("Unsupported hashfn: {}", opts.hashfn),
         }
@@ -228,9 +279,12 @@ mod cpu {
     };
 
     use super::{HalPair, ProverImpl, ProverServer};
-    use crate::ProverOpts;
+    use crate::{CancellationToken, ProverOpts};
 
-    pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
+    pub fn get_prover_server(
+        opts: &ProverOpts,
+        cancellation_token: Option<CancellationToken>,
+    ) -> Result<Rc<dyn ProverServer>> {
         let suite = match opts.hashfn.as_str() {
             "sha-256" => Sha256HashSuite::new_suite(),
             "poseidon2" => Poseidon2HashSuite::new_suite(),
@@ -239,13 +293,25 @@ mod cpu {
         let hal = Rc::new(CpuHal::new(suite));
         let circuit_hal = Rc::new(CpuCircuitHal::new());
         let hal_pair = HalPair { hal, circuit_hal };
-        Ok(Rc::new(ProverImpl::new("cpu", hal_pair)))
+        Ok(Rc::new(
+            ProverImpl::new("cpu", hal_pair).with_cancellation_token(cancellation_token.clone()),
+        ))
     }
 }
 
 /// Select a [ProverServer] based on the specified [ProverOpts] and currently
 /// compiled features.
+///
+/// The [ProverServer] stops with a [crate::CancellationError] once
+/// [ProverOpts::cancellation_token] is cancelled.
 pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
+    prover_server(opts, opts.cancellation_token.clone())
+}
+
+pub(crate) fn prover_server(
+    opts: &ProverOpts,
+    cancellation_token: Option<CancellationToken>,
+) -> Result<Rc<dyn ProverServer>> {
     if is_dev_mode() {
         eprintln!("WARNING: proving in dev mode. This will not generate valid, secure proofs.");
         return Ok(Rc::new(DevModeProver));
@@ -253,11 +319,11 @@ pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
 
     cfg_if! {
         if #[cfg(feature = "cuda")] {
-            cuda::get_prover_server(opts)
+            cuda::get_prover_server(opts, cancellation_token)
         } else if #[cfg(feature = "metal")] {
-            metal::get_prover_server(opts)
+            metal::get_prover_server(opts, cancellation_token)
         } else {
-            cpu::get_prover_server(opts)
+            cpu::get_prover_server(opts, cancellation_token)
         }
     }
 }
@@ -0,0 +1,549 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving segments while the executor is still running.
+
+use std::{
+    collections::BTreeMap,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::{
+    compress::{resolve_assumptions, JoinSteps},
+    prover_server, ProverServer,
+};
+use crate::{
+    host::{receipt::SuccinctReceipt, server::session::NullSegmentRef},
+    is_dev_mode,
+    sha::Digestible,
+    CancellationToken, ExecutorEnv, ExecutorImpl, InnerReceipt, ProverOpts, Receipt, Segment,
+    Session, VerifierContext,
+};
+
+/// A prover that proves [Segment]s as they are produced by the executor.
+///
+/// [crate::ProverServer::prove_with_ctx] runs the executor to completion before proving begins,
+/// which requires every [Segment] of the session to be kept around, usually on disk, until it is
+/// proven. The [PipelineProver] instead hands each [Segment] to a bounded pool of workers as soon
+/// as the executor emits it. Each worker proves the segment and immediately lifts it, and the
+/// resulting receipts are joined in order as they arrive.
+///
+/// Since the executor blocks whenever the queue of unproven segments is full, and segments are
+/// dropped as soon as they are proven, peak memory use is bounded by the number of workers and the
+/// queue depth rather than by the length of the session. Workers also wait rather than run more
+/// than that many segments ahead of the oldest segment not yet joined, so that the lifted receipts
+/// waiting to be joined in order stay bounded too. The result is a [Receipt] wrapping a
+/// [SuccinctReceipt].
+pub struct PipelineProver {
+    opts: ProverOpts,
+    workers: usize,
+    queue_depth: usize,
+}
+
+impl PipelineProver {
+    /// Construct a [PipelineProver] that uses the specified [ProverOpts].
+    ///
+    /// By default, a single worker is used with a queue of one pending segment.
+    pub fn new(opts: ProverOpts) -> Self {
+        Self {
+            opts,
+            workers: 1,
+            queue_depth: 1,
+        }
+    }
+
+    /// Set the number of workers proving segments concurrently.
+    pub fn with_workers(self, workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    /// Set the number of segments that may wait to be proven before the executor is paused.
+    pub fn with_queue_depth(self, queue_depth: usize) -> Self {
+        Self {
+            queue_depth,
+            ..self
+        }
+    }
+
+    /// Prove the specified ELF binary.
+    pub fn prove(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<Receipt> {
+        self.prove_with_ctx(env, &VerifierContext::default(), elf)
+    }
+
+    /// Prove the specified ELF binary using the specified [VerifierContext].
+    pub fn prove_with_ctx(
+        &self,
+        env: ExecutorEnv<'_>,
+        ctx: &VerifierContext,
+        elf: &[u8],
+    ) -> Result<Receipt> {
+        // Without a token of its own, proving is cancelled by the same token as execution.
+        let token = self
+            .opts
+            .cancellation_token
+            .clone()
+            .or_else(|| env.cancellation_token.clone());
+        if is_dev_mode() {
+            return prover_server(&self.opts, token)?.prove_with_ctx(env, ctx, elf);
+        }
+        if self.opts.hashfn != "poseidon2" {
+            bail!(
+                "PipelineProver requires the poseidon2 hash function, got: {}",
+                self.opts.hashfn
+            );
+        }
+
+        let mut exec = ExecutorImpl::from_elf(env, elf)?;
+        let pipeline = LocalPipeline {
+            opts: &self.opts,
+            token: token.clone(),
+        };
+        let (session, continuation_receipt) =
+            prove_segments(&pipeline, self.workers, self.queue_depth, |submit| {
+                exec.run_with_callback(|segment| {
+                    submit(segment)?;
+                    Ok(Box::new(NullSegmentRef))
+                })
+            })?;
+
+        let prover = prover_server(&self.opts, token)?;
+        let succinct_receipt = resolve_session(prover.as_ref(), &session, continuation_receipt)?;
+        session_receipt(&session, succinct_receipt, ctx)
+    }
+}
+
+/// Proves segments with a local prover for each thread.
+struct LocalPipeline<'a> {
+    opts: &'a ProverOpts,
+    token: Option<CancellationToken>,
+}
+
+impl SegmentPipeline for LocalPipeline<'_> {
+    // Each thread owns its own prover, since the HAL is not shareable across threads.
+    type Worker = Rc<dyn ProverServer>;
+
+    fn worker(&self) -> Result<Self::Worker> {
+        prover_server(self.opts, self.token.clone())
+    }
+
+    fn lift_segment(&self, prover: &Self::Worker, segment: Segment) -> Result<SuccinctReceipt> {
+        let receipt = prover.prove_segment(&VerifierContext::default(), &segment)?;
+        drop(segment);
+        prover.lift(&receipt)
+    }
+
+    fn join(
+        &self,
+        prover: &Self::Worker,
+        left: &SuccinctReceipt,
+        right: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        prover.join(left, right)
+    }
+}
+
+/// The steps used to prove a stream of [Segment]s and join them in order.
+///
+/// This is implemented by the [PipelineProver] and by the API coordinator, which share
+/// [prove_segments] to schedule the steps.
+pub(crate) trait SegmentPipeline: Sync {
+    /// State owned by each thread running the steps.
+    type Worker;
+
+    /// Create the state for a new thread.
+    fn worker(&self) -> Result<Self::Worker>;
+
+    /// Prove a [Segment] and lift the resulting receipt.
+    fn lift_segment(&self, worker: &Self::Worker, segment: Segment) -> Result<SuccinctReceipt>;
+
+    /// Join two [SuccinctReceipt]s of adjacent ranges of segments.
+    fn join(
+        &self,
+        worker: &Self::Worker,
+        left: &SuccinctReceipt,
+        right: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt>;
+}
+
+type LiftResult = (u32, Result<SuccinctReceipt>);
+
+/// Prove the segments passed to `submit` by `produce` with `workers` threads, and join them in
+/// order into a single [SuccinctReceipt].
+///
+/// `submit` blocks while `queue_depth` segments are waiting to be proven, and workers do not run
+/// more than `workers + queue_depth` segments ahead of the oldest segment not yet joined. If
+/// proving fails, `submit` returns an error so that `produce` can stop early.
+pub(crate) fn prove_segments<P, T, F>(
+    pipeline: &P,
+    workers: usize,
+    queue_depth: usize,
+    produce: F,
+) -> Result<(T, SuccinctReceipt)>
+where
+    P: SegmentPipeline,
+    F: FnOnce(&mut dyn FnMut(Segment) -> Result<()>) -> Result<T>,
+{
+    let window = JoinWindow::new(workers + queue_depth);
+
+    thread::scope(|scope| {
+        let (segment_tx, segment_rx) = sync_channel::<Segment>(queue_depth);
+        let segment_rx = Arc::new(Mutex::new(segment_rx));
+        let (lift_tx, lift_rx) = channel::<LiftResult>();
+
+        for _ in 0..workers {
+            let segment_rx = segment_rx.clone();
+            let lift_tx = lift_tx.clone();
+            let window = &window;
+            scope.spawn(move || worker(pipeline, segment_rx, lift_tx, window));
+        }
+        // Only the workers hold the queue's receiver, so that sending to the queue fails rather
+        // than blocks once every worker has exited.
+        drop(segment_rx);
+        drop(lift_tx);
+
+        let joiner = {
+            let window = &window;
+            scope.spawn(move || joiner(pipeline, lift_rx, window))
+        };
+
+        let mut aborted = false;
+        let output = produce(&mut |segment| {
+            if window.failed() {
+                aborted = true;
+                bail!("segment proving failed, aborting");
+            }
+            segment_tx.send(segment).map_err(|_| {
+                aborted = true;
+                anyhow!("segment proving workers exited unexpectedly")
+            })
+        });
+        drop(segment_tx);
+        if output.is_err() {
+            window.fail();
+        }
+
+        let continuation_receipt = joiner
+            .join()
+            .map_err(|_| anyhow!("segment joiner thread panicked"))?;
+        match continuation_receipt {
+            // Report why proving failed, rather than that it stopped execution.
+            Err(err) if aborted => Err(err),
+            continuation_receipt => Ok((output?, continuation_receipt?)),
+        }
+    })
+}
+
+/// Prove and lift segments pulled from the shared queue until it is closed.
+fn worker<P: SegmentPipeline>(
+    pipeline: &P,
+    segment_rx: Arc<Mutex<Receiver<Segment>>>,
+    lift_tx: Sender<LiftResult>,
+    window: &JoinWindow,
+) {
+    let state = match pipeline.worker() {
+        Ok(state) => state,
+        Err(err) => {
+            window.fail();
+            let _ = lift_tx.send((0, Err(err)));
+            return;
+        }
+    };
+
+    loop {
+        let segment = match segment_rx.lock().unwrap().recv() {
+            Ok(segment) => segment,
+            Err(_) => return,
+        };
+        let index = segment.index;
+        if !window.wait(index) {
+            return;
+        }
+
+        tracing::debug!("pipeline: proving segment {index}");
+        let result = pipeline.lift_segment(&state, segment);
+        if result.is_err() {
+            window.fail();
+        }
+        if lift_tx.send((index, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Join lifted receipts in segment order as they arrive from the workers.
+fn joiner<P: SegmentPipeline>(
+    pipeline: &P,
+    lift_rx: Receiver<LiftResult>,
+    window: &JoinWindow,
+) -> Result<SuccinctReceipt> {
+    let fail = |err| {
+        window.fail();
+        err
+    };
+
+    let state = pipeline.worker().map_err(fail)?;
+    let mut pending = BTreeMap::new();
+    let mut next_index = 0;
+    let mut acc: Option<SuccinctReceipt> = None;
+
+    for (index, result) in lift_rx.iter() {
+        pending.insert(index, result.map_err(fail)?);
+
+        // Fold every receipt that is now contiguous with the accumulated prefix.
+        while let Some(receipt) = pending.remove(&next_index) {
+            tracing::debug!("pipeline: joining segment {next_index}");
+            acc = Some(match acc {
+                Some(left) => pipeline.join(&state, &left, &receipt).map_err(fail)?,
+                None => receipt,
+            });
+            next_index += 1;
+            window.advance(next_index);
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(fail(anyhow!(
+            "missing lifted receipt for segment {next_index}"
+        )));
+    }
+    acc.ok_or(anyhow!("session produced no segments"))
+}
+
+/// Resolve the assumptions of a [Session] whose segments were joined into `continuation_receipt`.
+pub(crate) fn resolve_session<S: JoinSteps + ?Sized>(
+    steps: &S,
+    session: &Session,
+    continuation_receipt: SuccinctReceipt,
+) -> Result<SuccinctReceipt> {
+    let assumptions = session
+        .assumptions
+        .iter()
+        .map(|x| Ok(x.as_receipt()?.inner.clone()))
+        .collect::<Result<Vec<_>>>()?;
+    resolve_assumptions(steps, &assumptions, continuation_receipt)
+}
+
+/// Wrap the [SuccinctReceipt] proving a [Session] in a [Receipt], and check that it verifies and
+/// matches the session.
+pub(crate) fn session_receipt(
+    session: &Session,
+    succinct_receipt: SuccinctReceipt,
+    ctx: &VerifierContext,
+) -> Result<Receipt> {
+    let receipt = Receipt::new(
+        InnerReceipt::Succinct(succinct_receipt),
+        session.journal.clone().unwrap_or_default().bytes,
+    );
+
+    // Verify the receipt to catch if something is broken in the proving process.
+    receipt.verify_integrity_with_context(ctx)?;
+    if receipt.get_claim()?.digest() != session.get_claim()?.digest() {
+        tracing::debug!("receipt and session claim do not match");
+        tracing::debug!("receipt claim: {:#?}", receipt.get_claim()?);
+        tracing::debug!("session claim: {:#?}", session.get_claim()?);
+        bail!(
+            "session and receipt claim do not match: session {}, receipt {}",
+            hex::encode(session.get_claim()?.digest()),
+            hex::encode(receipt.get_claim()?.digest())
+        );
+    }
+
+    Ok(receipt)
+}
+
+/// Keeps the workers from proving segments more than a fixed number of segments ahead of the
+/// joiner, so that at most that many lifted receipts wait to be joined in order.
+struct JoinWindow {
+    size: u32,
+    next_index: Mutex<u32>,
+    advanced: Condvar,
+    failed: AtomicBool,
+}
+
+impl JoinWindow {
+    fn new(size: usize) -> Self {
+        Self {
+            size: size.try_into().unwrap_or(u32::MAX),
+            next_index: Mutex::new(0),
+            advanced: Condvar::new(),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Block until segment `index` is within the window, returning false if proving has failed.
+    ///
+    /// The segment the joiner waits for is always within the window, so this cannot deadlock.
+    fn wait(&self, index: u32) -> bool {
+        let mut next_index = self.next_index.lock().unwrap();
+        while index >= next_index.saturating_add(self.size) && !self.failed() {
+            next_index = self.advanced.wait(next_index).unwrap();
+        }
+        !self.failed()
+    }
+
+    /// Record that every segment before `next_index` has been joined.
+    fn advance(&self, next_index: u32) {
+        *self.next_index.lock().unwrap() = next_index;
+        self.advanced.notify_all();
+    }
+
+    /// Record that proving has failed, waking any waiting workers.
+    fn fail(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+        // Notify while holding the lock, so that a worker cannot miss the wakeup between checking
+        // the flag and waiting.
+        let _guard = self.next_index.lock().unwrap();
+        self.advanced.notify_all();
+    }
+
+    fn failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::channel,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use anyhow::{bail, Result};
+    use risc0_zkp::core::digest::Digest;
+    use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
+
+    use super::{prove_segments, JoinWindow, SegmentPipeline};
+    use crate::{
+        host::{receipt::SuccinctReceipt, server::session::NullSegmentRef},
+        ExecutorEnv, ExecutorImpl, ExitCode, MaybePruned, ReceiptClaim, Segment,
+    };
+
+    // Lifts segments to fake receipts, and fails to join them once the producer is about to block
+    // on the full queue.
+    struct FailingJoin {
+        submitted: AtomicBool,
+    }
+
+    impl SegmentPipeline for FailingJoin {
+        type Worker = ();
+
+        fn worker(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn lift_segment(&self, _: &(), _: Segment) -> Result<SuccinctReceipt> {
+            Ok(SuccinctReceipt {
+                seal: vec![],
+                control_id: Digest::ZERO,
+                claim: ReceiptClaim {
+                    pre: MaybePruned::Pruned(Digest::ZERO),
+                    post: MaybePruned::Pruned(Digest::ZERO),
+                    exit_code: ExitCode::SystemSplit,
+                    input: Digest::ZERO,
+                    output: None.into(),
+                },
+            })
+        }
+
+        fn join(
+            &self,
+            _: &(),
+            _: &SuccinctReceipt,
+            _: &SuccinctReceipt,
+        ) -> Result<SuccinctReceipt> {
+            while !self.submitted.load(Ordering::Relaxed) {
+                thread::yield_now();
+            }
+            // Give the producer time to block on the full queue.
+            thread::sleep(Duration::from_millis(100));
+            bail!("join failed")
+        }
+    }
+
+    #[test]
+    fn join_fails_with_full_queue() {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles: 1 << 17 })
+            .unwrap()
+            .segment_limit_po2(14)
+            .build()
+            .unwrap();
+        let mut segments = Vec::new();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run_with_callback(|segment| {
+                segments.push(segment);
+                Ok(Box::new(NullSegmentRef))
+            })
+            .unwrap();
+
+        // While the first join is running, the worker lifts segments 0 to 2 and then waits with
+        // segment 3, and segments 4 and 5 fill the queue, so the producer blocks on segment 6.
+        let (workers, queue_depth) = (1, 2);
+        let blocked = (workers + 2 * queue_depth + 1) as u32;
+        assert!(segments.len() > blocked as usize);
+
+        let pipeline = FailingJoin {
+            submitted: AtomicBool::new(false),
+        };
+        let err = prove_segments(&pipeline, workers, queue_depth, |submit| {
+            for segment in segments {
+                if segment.index == blocked {
+                    pipeline.submitted.store(true, Ordering::Relaxed);
+                }
+                submit(segment)?;
+            }
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("join failed"), "{err}");
+    }
+
+    #[test]
+    fn join_window() {
+        let window = JoinWindow::new(2);
+        assert!(window.wait(0));
+        assert!(window.wait(1));
+
+        thread::scope(|scope| {
+            let window = &window;
+            let (done_tx, done_rx) = channel();
+            scope.spawn(move || done_tx.send(window.wait(2)).unwrap());
+            assert!(done_rx.recv_timeout(Duration::from_millis(100)).is_err());
+            window.advance(1);
+            assert!(done_rx.recv().unwrap());
+        });
+
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| window.wait(10));
+            window.fail();
+            assert!(!waiter.join().unwrap());
+        });
+    }
+}
@@ -12,18 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::Path;
+
 use anyhow::{bail, Result};
 use risc0_core::field::baby_bear::{BabyBear, Elem, ExtElem};
 use risc0_zkp::hal::{CircuitHal, Hal};
 
-use super::{HalPair, ProverServer};
+use super::{
+    compress::{compress_segments_par, resolve_assumptions, JoinSteps},
+    HalPair, ProverServer,
+};
 use crate::{
     host::{
         receipt::{CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt},
         recursion::{identity_p254, join, lift, resolve},
     },
     sha::Digestible,
-    Receipt, Segment, Session, VerifierContext,
+    CancellationToken, Receipt, Segment, Session, VerifierContext,
 };
 
 /// An implementation of a Prover that runs locally.
@@ -34,6 +39,7 @@ where
 {
     name: String,
     hal_pair: HalPair<H, C>,
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl<H, C> ProverImpl<H, C>
@@ -46,10 +52,66 @@ where
         Self {
             name: name.to_string(),
             hal_pair,
+            cancellation_token: None,
+        }
+    }
+
+    /// Set a [CancellationToken] that is checked before proving each segment
+    /// and before each recursion step.
+    pub fn with_cancellation_token(self, cancellation_token: Option<CancellationToken>) -> Self {
+        Self {
+            cancellation_token,
+            ..self
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        check_cancelled(self.cancellation_token.as_ref())
+    }
+
+    fn recursion_steps(&self) -> RecursionSteps<'_> {
+        RecursionSteps {
+            cancellation_token: self.cancellation_token.as_ref(),
         }
     }
 }
 
+fn check_cancelled(cancellation_token: Option<&CancellationToken>) -> Result<()> {
+    if let Some(token) = cancellation_token {
+        token.check()?;
+    }
+    Ok(())
+}
+
+/// The recursion steps of a [ProverImpl].
+///
+/// The recursion programs do not use the prover's [HalPair], so these steps can be shared across
+/// threads to prove independent parts of a join tree concurrently.
+struct RecursionSteps<'a> {
+    cancellation_token: Option<&'a CancellationToken>,
+}
+
+impl JoinSteps for RecursionSteps<'_> {
+    fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
+        check_cancelled(self.cancellation_token)?;
+        lift(receipt)
+    }
+
+    fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        check_cancelled(self.cancellation_token)?;
+        join(a, b)
+    }
+
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        assumption: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        check_cancelled(self.cancellation_token)?;
+        resolve(conditional, assumption)
+    }
+}
+
 impl<H, C> ProverServer for ProverImpl<H, C>
 where
     H: Hal<Field = BabyBear, Elem = Elem, ExtElem = ExtElem>,
@@ -128,6 +190,7 @@ where
 
         use crate::host::receipt::decode_receipt_claim_from_seal;
 
+        self.check_cancelled()?;
         let hashfn = self.hal_pair.hal.get_hash_suite().name.clone();
 
         let prover =
@@ -153,11 +216,22 @@ where
     }
 
     fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
-        lift(receipt)
+        self.recursion_steps().lift(receipt)
     }
 
     fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt> {
-        join(a, b)
+        self.recursion_steps().join(a, b)
+    }
+
+    fn compress_tree(
+        &self,
+        receipt: &CompositeReceipt,
+        checkpoint_dir: Option<&Path>,
+    ) -> Result<SuccinctReceipt> {
+        let steps = self.recursion_steps();
+        let continuation_receipt =
+            compress_segments_par(&steps, &receipt.segments, checkpoint_dir)?;
+        resolve_assumptions(&steps, &receipt.assumptions, continuation_receipt)
     }
 
     fn resolve(
@@ -165,10 +239,11 @@ where
         conditional: &SuccinctReceipt,
         assumption: &SuccinctReceipt,
     ) -> Result<SuccinctReceipt> {
-        resolve(conditional, assumption)
+        self.recursion_steps().resolve(conditional, assumption)
     }
 
     fn identity_p254(&self, a: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        self.check_cancelled()?;
         identity_p254(a)
     }
 }
@@ -31,7 +31,9 @@ use super::{get_prover_server, HalPair, ProverImpl};
 use crate::{
     host::server::testutils,
     serde::{from_slice, to_vec},
-    ExecutorEnv, ExecutorImpl, ExitCode, ProverOpts, ProverServer, Receipt, Session,
+    sha::Digestible,
+    CancellationError, CancellationToken, ContinuationSnapshot, ExecutorEnv, ExecutorImpl,
+    ExitCode, PipelineProver, ProverOpts, ProverServer, Receipt, ReceiptKind, Session,
     VerifierContext,
 };
 
@@ -39,6 +41,7 @@ fn prover_opts_fast() -> ProverOpts {
     ProverOpts {
         hashfn: "sha-256".to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     }
 }
 
@@ -58,6 +61,7 @@ fn prove_nothing(hashfn: &str) -> Result<Receipt> {
     let opts = ProverOpts {
         hashfn: hashfn.to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     };
     get_prover_server(&opts).unwrap().prove(env, MULTI_TEST_ELF)
 }
@@ -107,6 +111,76 @@ fn check_image_id() {
     );
 }
 
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn verify_batch() {
+    let receipt = prove_nothing("sha-256").unwrap();
+    let image_id: Digest = MULTI_TEST_ID.into();
+    let mut wrong_image_id = image_id;
+    wrong_image_id.as_mut_words()[0] ^= 1;
+    let mut tampered = receipt.clone();
+    tampered.journal.bytes.push(0);
+    let batch = [
+        (receipt.clone(), image_id),
+        (tampered, image_id),
+        (receipt, wrong_image_id),
+    ];
+    assert_eq!(
+        VerifierContext::default().verify_batch(&batch),
+        vec![
+            Ok(()),
+            Err(VerificationError::JournalDigestMismatch),
+            Err(VerificationError::ImageVerificationError),
+        ]
+    );
+
+    // Verifying in parallel gives the same results in the same order.
+    let batch: Vec<_> = batch.iter().cycle().take(16).cloned().collect();
+    assert_eq!(
+        VerifierContext::par_verify_batch(VerifierContext::default, &batch),
+        VerifierContext::default().verify_batch(&batch)
+    );
+
+    // The batch is verified with the hash suites of the given context.
+    let mut ctx = VerifierContext::default();
+    ctx.suites.remove("sha-256");
+    assert_eq!(
+        ctx.verify_batch(&batch[..1]),
+        vec![Err(VerificationError::InvalidHashSuite)]
+    );
+}
+
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn cancellation_token() {
+    let session = ExecutorImpl::from_elf(
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap(),
+        MULTI_TEST_ELF,
+    )
+    .unwrap()
+    .run()
+    .unwrap();
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let opts = ProverOpts {
+        cancellation_token: Some(token),
+        ..prover_opts_fast()
+    };
+    let err = get_prover_server(&opts)
+        .unwrap()
+        .prove_session(&VerifierContext::default(), &session)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CancellationError>(),
+        Some(&CancellationError::Cancelled)
+    );
+}
+
 #[test]
 #[serial]
 fn sha_basics() {
@@ -282,6 +356,23 @@ fn session_events() {
     assert_eq!(on_post_prove_segment_flag.take(), true);
 }
 
+#[test]
+#[serial]
+fn pipeline_prover() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 16 })
+        .unwrap()
+        .segment_limit_po2(16)
+        .build()
+        .unwrap();
+    let receipt = PipelineProver::new(ProverOpts::default())
+        .with_workers(2)
+        .prove(env, MULTI_TEST_ELF)
+        .unwrap();
+    receipt.inner.succinct().unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
 // These tests come from:
 // https://github.com/riscv-software-src/riscv-tests
 // They were built using the toolchain from:
@@ -402,6 +493,41 @@ fn pause_resume() {
     prove_session_fast(&session);
 }
 
+#[test]
+fn snapshot_resume() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseContinue(0))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+
+    // Run until sys_pause and save the snapshot as if handing it to another process.
+    let paused = exec.run().unwrap();
+    assert_eq!(paused.exit_code, ExitCode::Paused(0));
+    let paused_receipt = prove_session_fast(&paused);
+    let bytes = bincode::serialize(&paused.snapshot().unwrap()).unwrap();
+    drop(exec);
+
+    // Resume from the snapshot and run until sys_halt.
+    let snapshot: ContinuationSnapshot = bincode::deserialize(&bytes).unwrap();
+    let env = ExecutorEnv::builder().build().unwrap();
+    let resumed = ExecutorImpl::from_snapshot(env, snapshot)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(resumed.pre_state.digest(), paused.post_state.digest());
+    let resumed_receipt = prove_session_fast(&resumed);
+
+    let paused_claim = paused_receipt.get_claim().unwrap();
+    let resumed_claim = resumed_receipt.get_claim().unwrap();
+    assert_eq!(resumed_claim.pre.digest(), paused_claim.post.digest());
+
+    // A halted session cannot be resumed.
+    assert!(resumed.snapshot().is_err());
+}
+
 #[test]
 fn continuation() {
     const COUNT: usize = 2; // Number of total chunks to aim for.
@@ -488,6 +614,57 @@ fn stark2snark() {
     receipt.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[test]
+fn compress_to_succinct() {
+    let receipt = prove_nothing("poseidon2").unwrap();
+    let prover = get_prover_server(&ProverOpts::default()).unwrap();
+
+    let same = prover
+        .compress_to(&receipt, ReceiptKind::Composite)
+        .unwrap();
+    same.inner.composite().unwrap();
+
+    let succinct = prover.compress_to(&receipt, ReceiptKind::Succinct).unwrap();
+    succinct.inner.succinct().unwrap();
+    succinct.verify(MULTI_TEST_ID).unwrap();
+
+    let again = prover
+        .compress_to(&succinct, ReceiptKind::Composite)
+        .unwrap();
+    again.inner.succinct().unwrap();
+}
+
+#[cfg(feature = "docker")]
+#[test]
+fn compress_to_compact() {
+    let receipt = prove_nothing("poseidon2").unwrap();
+    let prover = get_prover_server(&ProverOpts::default()).unwrap();
+
+    let compact = prover.compress_to(&receipt, ReceiptKind::Compact).unwrap();
+    compact.inner.compact().unwrap();
+    compact.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[test]
+fn local_prove_receipt_kind() {
+    use crate::{LocalProver, Prover as _};
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let opts = ProverOpts {
+        receipt_kind: ReceiptKind::Succinct,
+        ..Default::default()
+    };
+    let receipt = LocalProver::new("local")
+        .prove_with_ctx(env, &VerifierContext::default(), MULTI_TEST_ELF, &opts)
+        .unwrap();
+    receipt.inner.succinct().unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
 mod sys_verify {
     use risc0_zkvm_methods::{
         multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
@@ -515,6 +692,7 @@ mod sys_verify {
         let opts = ProverOpts {
             hashfn: "sha-256".to_string(),
             prove_guest_errors: true,
+            ..Default::default()
         };
 
         let env = ExecutorEnvBuilder::default()
@@ -16,19 +16,19 @@
 //! execution traces between the execution phase and the proving phase.
 
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     fs,
     path::{Path, PathBuf},
 };
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use risc0_binfmt::{MemoryImage, SystemState};
 use risc0_circuit_rv32im::prove::segment::Segment as CircuitSegment;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    host::client::env::SegmentPath, sha::Digest, Assumption, Assumptions, ExitCode, Journal,
-    Output, ReceiptClaim,
+    host::client::env::SegmentPath, sha::Digest, Assumption, Assumptions, Container, CycleReport,
+    ExitCode, Journal, Output, ReceiptClaim,
 };
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -78,6 +78,61 @@ pub struct Session {
 
     /// The system state of the final [MemoryImage] at the end of execution.
     pub post_state: SystemState,
+
+    /// The cycles spent per function and per call stack, if requested with
+    /// [crate::ExecutorEnvBuilder::enable_cycle_report].
+    pub cycle_report: Option<CycleReport>,
+
+    /// Metadata attached by syscall handlers, see
+    /// [crate::SyscallContext::set_metadata].
+    pub metadata: BTreeMap<String, Vec<u8>>,
+}
+
+/// The current version of the [ContinuationSnapshot] format.
+pub const CONTINUATION_SNAPSHOT_VERSION: u32 = 1;
+
+/// The state required to resume a paused execution.
+///
+/// A [ContinuationSnapshot] is produced by [Session::snapshot] once a guest has
+/// reached [ExitCode::Paused]. It can be serialized, moved to another host
+/// process, and passed to [crate::ExecutorImpl::from_snapshot] to continue
+/// execution. The resulting [Session] starts from the [SystemState] this
+/// snapshot was taken at, so its `pre_state` matches the `post_state` of the
+/// paused [Session].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ContinuationSnapshot {
+    /// The version of the snapshot format, see [CONTINUATION_SNAPSHOT_VERSION].
+    pub version: u32,
+
+    /// The [MemoryImage] at the point where execution was paused.
+    pub image: MemoryImage,
+
+    /// The [SystemState] of the paused [Session].
+    pub post_state: SystemState,
+
+    /// The assumptions made by the guest before it was paused.
+    ///
+    /// These are made available to the resumed execution, in addition to any
+    /// assumptions added to its [crate::ExecutorEnv].
+    pub assumptions: Vec<Assumption>,
+}
+
+impl ContinuationSnapshot {
+    /// Check that this snapshot can be resumed by this version of the zkVM.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.version != CONTINUATION_SNAPSHOT_VERSION {
+            bail!(
+                "unsupported continuation snapshot version: {}, expected: {}",
+                self.version,
+                CONTINUATION_SNAPSHOT_VERSION
+            );
+        }
+        ensure!(
+            self.image.get_system_state() == self.post_state,
+            "continuation snapshot image does not match its post state"
+        );
+        Ok(())
+    }
 }
 
 /// The execution trace of a portion of a program.
@@ -142,6 +197,8 @@ impl Session {
             total_cycles,
             pre_state,
             post_state,
+            cycle_report: None,
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -199,6 +256,24 @@ impl Session {
         })
     }
 
+    /// Capture a [ContinuationSnapshot] that can be used to resume this
+    /// [Session].
+    ///
+    /// Only a [Session] that ended with [ExitCode::Paused] can be resumed.
+    pub fn snapshot(&self) -> Result<ContinuationSnapshot> {
+        ensure!(
+            matches!(self.exit_code, ExitCode::Paused(_)),
+            "cannot snapshot a session with exit code {:?}",
+            self.exit_code
+        );
+        Ok(ContinuationSnapshot {
+            version: CONTINUATION_SNAPSHOT_VERSION,
+            image: self.post_image.clone(),
+            post_state: self.post_state.clone(),
+            assumptions: self.assumptions.clone(),
+        })
+    }
+
     /// Log cycle information for this [Session].
     ///
     /// This logs the total and user cycles for this [Session] at the INFO level.
@@ -265,8 +340,7 @@ pub struct FileSegmentRef {
 impl SegmentRef for FileSegmentRef {
     fn resolve(&self) -> Result<Segment> {
         let contents = fs::read(&self.path)?;
-        let segment = bincode::deserialize(&contents)?;
-        Ok(segment)
+        Segment::from_container_bytes(&contents)
     }
 }
 
@@ -284,8 +358,8 @@ impl FileSegmentRef {
     ///
     /// This builds a FileSegmentRef that stores `segment` in a file at `path`.
     pub fn new(segment: &Segment, dir: &SegmentPath) -> Result<Self> {
-        let path = dir.path().join(format!("{}.bincode", segment.index));
-        fs::write(&path, bincode::serialize(&segment)?)?;
+        let path = dir.path().join(format!("{}.segment", segment.index));
+        fs::write(&path, segment.to_container_bytes()?)?;
         Ok(Self {
             path,
             _dir: dir.clone(),
@@ -53,6 +53,7 @@
 //! | client           | all except rv32im | std        | Enables the client API.                                                                                                                                      |
 //! | cuda             |                   | prove, std | Enables CUDA GPU acceleration for the prover. Requires CUDA toolkit to be installed.                                                                         |
 //! | disable-dev-mode | all except rv32im |            | Disables dev mode so that proving and verifying may not be faked. Used to prevent a misplaced `RISC0_DEV_MODE` from breaking security in production systems. |
+//! | groth16-native   | all except rv32im | prove      | Produces the Groth16 proofs of compact receipts in-process instead of in Docker, using the native prover of `risc0-groth16`.                                 |
 //! | metal            | macos             | prove, std | Enables Metal GPU acceleration for the prover.                                                                                                               |
 //! | prove            | all except rv32im | std        | Enables the prover, incompatible within the zkvm guest.                                                                                                      |
 //! | std              | all               |            | Support for the Rust stdlib.                                                                                                                                 |
@@ -74,6 +75,7 @@ extern crate alloc;
 pub mod guest;
 #[cfg(not(target_os = "zkvm"))]
 mod host;
+pub mod merkle_journal;
 mod receipt_claim;
 pub mod serde;
 pub mod sha;
@@ -95,14 +97,22 @@ pub use self::receipt_claim::{Assumptions, MaybePruned, Output, PrunedValueError
 #[cfg(all(not(target_os = "zkvm"), feature = "prove",))]
 pub use {
     self::host::{
-        api::server::Server as ApiServer,
+        api::{
+            coordinator::Coordinator as ApiCoordinator,
+            daemon::{Daemon as ApiDaemon, DaemonOpts},
+            server::Server as ApiServer,
+        },
         client::prove::local::LocalProver,
         recursion::RECURSION_PO2,
         server::{
-            exec::executor::ExecutorImpl,
-            prove::{get_prover_server, HalPair, ProverServer},
+            exec::{
+                executor::ExecutorImpl,
+                syscall::{Syscall, SyscallContext},
+            },
+            prove::{get_prover_server, HalPair, PipelineProver, ProverServer},
             session::{
-                FileSegmentRef, Segment, SegmentRef, Session, SessionEvents, SimpleSegmentRef,
+                ContinuationSnapshot, FileSegmentRef, Segment, SegmentRef, Session, SessionEvents,
+                SimpleSegmentRef, CONTINUATION_SNAPSHOT_VERSION,
             },
         },
     },
@@ -115,14 +125,22 @@ pub use {
 pub use {
     self::host::{
         api::{
-            client::Client as ApiClient, Asset, AssetRequest, Connector, SegmentInfo, SessionInfo,
+            client::Client as ApiClient, Asset, AssetRequest, Connector, JobId, JobState,
+            JobStatus, SegmentInfo, SessionInfo,
         },
         client::{
-            env::{ExecutorEnv, ExecutorEnvBuilder},
+            cancel::{CancellationError, CancellationToken},
+            cycle_report::{CycleReport, FunctionCycles, StackCycles},
+            env::{ExecutorEnv, ExecutorEnvBuilder, SendExecutorEnv, SendExecutorEnvBuilder},
             prove::{
-                bonsai::BonsaiProver, default_executor, default_prover, external::ExternalProver,
-                Executor, Prover, ProverOpts,
+                bonsai::BonsaiProver, default_executor, default_executor_send, default_prover,
+                default_prover_send, external::ExternalProver, Executor, Prover, ProverOpts,
+                ReceiptKind,
             },
+            slice_io::AsyncSliceIo,
+        },
+        container::{
+            Container, ContainerHeader, ContainerKind, CONTAINER_FORMAT_VERSION, CONTAINER_MAGIC,
         },
     },
     risc0_circuit_rv32im::trace::{TraceCallback, TraceEvent},
@@ -0,0 +1,306 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A journal of independent records committed to as a SHA-256 Merkle tree.
+//!
+//! A guest that produces many independent records can collect them in a [MerkleJournal] and
+//! commit only its [root](MerkleJournal::root) to the journal, using
+//! [commit_merkle_root](crate::guest::env::commit_merkle_root). The [Output](crate::Output) of the
+//! resulting receipt then commits to the root alone. The root binds the number of records as well
+//! as the tree over them, so a proof cannot claim a record sits at a different position in a
+//! journal of a different size. Given the same records, the host can produce
+//! a [MerkleProof] for any one of them, allowing a verifier to check that record against the
+//! receipt without access to the rest of the journal. After verifying the receipt, the root is
+//! read back from the journal with `Digest::try_from(receipt.journal.bytes.as_slice())`.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use risc0_zkvm::merkle_journal::MerkleJournal;
+//!
+//! let mut journal = MerkleJournal::new();
+//! for record in [b"alice".as_slice(), b"bob", b"carol"] {
+//!     journal.push(record).unwrap();
+//! }
+//! let root = journal.root();
+//!
+//! // Prove that "bob" is the second record committed to by the root.
+//! let proof = journal.prove(1).unwrap();
+//! proof.verify(b"bob", &root).unwrap();
+//! assert!(proof.verify(b"mallory", &root).is_err());
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use risc0_binfmt::tagged_struct;
+use serde::{Deserialize, Serialize};
+
+use crate::sha::{self, Digest, Sha256};
+
+const LEAF_TAG: &str = "risc0.MerkleJournal.Leaf";
+const NODE_TAG: &str = "risc0.MerkleJournal.Node";
+const ROOT_TAG: &str = "risc0.MerkleJournal.Root";
+
+/// An append-only list of records committed to by a SHA-256 Merkle tree.
+///
+/// Leaves and inner nodes are hashed with distinct tags, so that a leaf can never be mistaken for
+/// an inner node. The tree has the same shape as the one used in certificate transparency
+/// (RFC 6962): the left subtree of every node is a perfect binary tree containing the largest
+/// power of two number of leaves that is less than the number of leaves under that node. The
+/// [root](MerkleJournal::root) is the root of that tree hashed together with the number of leaves.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MerkleJournal {
+    leaves: Vec<Digest>,
+}
+
+impl MerkleJournal {
+    /// Construct an empty [MerkleJournal].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a record to the journal, returning its index.
+    ///
+    /// Returns an error if the journal already holds [u32::MAX] records.
+    pub fn push(&mut self, record: &[u8]) -> Result<u32, MerkleJournalFull> {
+        let index = u32::try_from(self.leaves.len()).map_err(|_| MerkleJournalFull)?;
+        if index == u32::MAX {
+            return Err(MerkleJournalFull);
+        }
+        self.leaves.push(hash_leaf(record));
+        Ok(index)
+    }
+
+    /// Return the number of records in the journal.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Return true if no records have been added to the journal.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Compute the root committing to all records and to their number.
+    ///
+    /// The tree over an empty journal is taken to be [Digest::ZERO].
+    pub fn root(&self) -> Digest {
+        let tree = match self.leaves.is_empty() {
+            true => Digest::ZERO,
+            false => subtree_root(&self.leaves),
+        };
+        // push never lets the journal grow past u32::MAX records.
+        hash_root(&tree, self.leaves.len() as u32)
+    }
+
+    /// Produce a [MerkleProof] that the record at `index` is included in this journal.
+    pub fn prove(&self, index: u32) -> Result<MerkleProof, MerkleProofError> {
+        if index as usize >= self.leaves.len() {
+            return Err(MerkleProofError);
+        }
+        let mut siblings = Vec::new();
+        subtree_path(&self.leaves, index as usize, &mut siblings);
+        Ok(MerkleProof {
+            index,
+            leaf_count: u32::try_from(self.leaves.len()).map_err(|_| MerkleProofError)?,
+            siblings,
+        })
+    }
+}
+
+/// Proof that a record is included in a [MerkleJournal] with a given root.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MerkleProof {
+    /// Index of the record within the journal.
+    pub index: u32,
+
+    /// Total number of records in the journal.
+    pub leaf_count: u32,
+
+    /// Digests of the sibling subtrees on the path from the record to the root, ordered from the
+    /// leaf upward.
+    pub siblings: Vec<Digest>,
+}
+
+impl MerkleProof {
+    /// Verify that `record` is included in the journal with the given `root`.
+    ///
+    /// The `root` commits to the number of records, so [MerkleProof::leaf_count] and
+    /// [MerkleProof::index] are checked against it along with the record.
+    pub fn verify(&self, record: &[u8], root: &Digest) -> Result<(), MerkleProofError> {
+        if self.index >= self.leaf_count {
+            return Err(MerkleProofError);
+        }
+        let computed = path_root(
+            self.index as usize,
+            self.leaf_count as usize,
+            hash_leaf(record),
+            &self.siblings,
+        )
+        .ok_or(MerkleProofError)?;
+        match hash_root(&computed, self.leaf_count) == *root {
+            true => Ok(()),
+            false => Err(MerkleProofError),
+        }
+    }
+}
+
+/// Error returned when a [MerkleProof] does not prove inclusion of a record.
+#[derive(Debug, Clone, Copy)]
+pub struct MerkleProofError;
+
+impl fmt::Display for MerkleProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "record is not included in the merkle journal")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MerkleProofError {}
+
+/// Error returned when pushing a record to a [MerkleJournal] that cannot index any more records.
+#[derive(Debug, Clone, Copy)]
+pub struct MerkleJournalFull;
+
+impl fmt::Display for MerkleJournalFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "merkle journal cannot hold more than u32::MAX records")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MerkleJournalFull {}
+
+fn hash_leaf(record: &[u8]) -> Digest {
+    tagged_struct::<sha::Impl>(LEAF_TAG, &[*sha::Impl::hash_bytes(record)], &[])
+}
+
+fn hash_node(left: &Digest, right: &Digest) -> Digest {
+    tagged_struct::<sha::Impl>(NODE_TAG, &[left, right], &[])
+}
+
+fn hash_root(tree: &Digest, leaf_count: u32) -> Digest {
+    tagged_struct::<sha::Impl>(ROOT_TAG, &[tree], &[leaf_count])
+}
+
+/// Number of leaves in the left subtree of a node with `n > 1` leaves.
+fn split_point(n: usize) -> usize {
+    n.next_power_of_two() / 2
+}
+
+fn subtree_root(leaves: &[Digest]) -> Digest {
+    if leaves.len() == 1 {
+        return leaves[0];
+    }
+    let (left, right) = leaves.split_at(split_point(leaves.len()));
+    hash_node(&subtree_root(left), &subtree_root(right))
+}
+
+fn subtree_path(leaves: &[Digest], index: usize, siblings: &mut Vec<Digest>) {
+    if leaves.len() == 1 {
+        return;
+    }
+    let k = split_point(leaves.len());
+    let (left, right) = leaves.split_at(k);
+    if index < k {
+        subtree_path(left, index, siblings);
+        siblings.push(subtree_root(right));
+    } else {
+        subtree_path(right, index - k, siblings);
+        siblings.push(subtree_root(left));
+    }
+}
+
+/// Recompute the root of a subtree with `n` leaves from the leaf at `index` and its path.
+fn path_root(index: usize, n: usize, leaf: Digest, siblings: &[Digest]) -> Option<Digest> {
+    if n == 1 {
+        return siblings.is_empty().then_some(leaf);
+    }
+    let (sibling, rest) = siblings.split_last()?;
+    let k = split_point(n);
+    Some(match index < k {
+        true => hash_node(&path_root(index, k, leaf, rest)?, sibling),
+        false => hash_node(sibling, &path_root(index - k, n - k, leaf, rest)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_node, split_point, MerkleJournal, MerkleProof};
+    use crate::sha::Digest;
+
+    #[test]
+    fn split_point_is_largest_power_of_two_below() {
+        for (n, k) in [(2, 1), (3, 2), (4, 2), (5, 4), (8, 4), (9, 8), (17, 16)] {
+            assert_eq!(split_point(n), k, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn prove_and_verify_all_records() {
+        for count in 1..=17u32 {
+            let mut journal = MerkleJournal::new();
+            let records: Vec<Vec<u8>> = (0..count).map(|i| i.to_le_bytes().to_vec()).collect();
+            for record in records.iter() {
+                journal.push(record).unwrap();
+            }
+            let root = journal.root();
+
+            for (index, record) in records.iter().enumerate() {
+                let proof = journal.prove(index as u32).unwrap();
+                proof.verify(record, &root).unwrap();
+
+                // The proof must not verify a different record, root, or position.
+                assert!(proof.verify(b"not a record", &root).is_err());
+                assert!(proof.verify(record, &Digest::ZERO).is_err());
+                let mut moved = proof.clone();
+                moved.index = (moved.index + 1) % count;
+                if count > 1 {
+                    assert!(moved.verify(record, &root).is_err());
+                }
+            }
+            assert!(journal.prove(count).is_err());
+        }
+    }
+
+    #[test]
+    fn position_forgery() {
+        let mut journal = MerkleJournal::new();
+        for record in [b"l0".as_slice(), b"l1", b"l2"] {
+            journal.push(record).unwrap();
+        }
+        let root = journal.root();
+        let proof = journal.prove(2).unwrap();
+        proof.verify(b"l2", &root).unwrap();
+
+        // With three leaves, the tree root is H(H(l0, l1), l2). Claiming a two leaf journal with
+        // l2 at index 1 reproduces the same tree root, so the proof must be rejected because the
+        // root commits to the leaf count.
+        let forged = MerkleProof {
+            index: 1,
+            leaf_count: 2,
+            siblings: vec![hash_node(&journal.leaves[0], &journal.leaves[1])],
+        };
+        assert!(forged.verify(b"l2", &root).is_err());
+    }
+
+    #[test]
+    fn empty_journal() {
+        let journal = MerkleJournal::new();
+        assert!(journal.is_empty());
+        assert_ne!(journal.root(), Digest::ZERO);
+        assert!(journal.prove(0).is_err());
+    }
+}